@@ -0,0 +1,72 @@
+use std::net::{SocketAddr, UdpSocket};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use a2s::{A2SClient, A2SConfig};
+
+/// A local server answering every datagram with the same info response.
+fn info_server() -> SocketAddr {
+    let mut response = vec![0xff, 0xff, 0xff, 0xff, 0x49, 17];
+    response.extend(b"Benchmark Server\0de_dust2\0csgo\0Counter-Strike: Global Offensive\0");
+    response.extend(730u16.to_le_bytes());
+    response.extend(&[5, 24, 1, b'd', b'l', 0, 1]);
+    response.extend(b"1.38.0.0\0");
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = socket.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let mut buf = [0; 1400];
+        while let Ok((_, src)) = socket.recv_from(&mut buf) {
+            let _ = socket.send_to(&response, src);
+        }
+    });
+
+    addr
+}
+
+/// Pool sizes compared: allocating a receive buffer per query, and the default pool.
+const POOL_SIZES: [(&str, usize); 2] = [("unpooled", 0), ("pooled", 4)];
+
+#[cfg(not(feature = "async"))]
+fn query_info(c: &mut Criterion) {
+    let addr = info_server();
+
+    let mut group = c.benchmark_group("info");
+    for (name, size) in POOL_SIZES {
+        let client = A2SClient::with_config(A2SConfig {
+            buffer_pool_size: size,
+            ..Default::default()
+        })
+        .unwrap();
+        group.bench_function(name, |b| b.iter(|| client.info(addr).unwrap()));
+    }
+    group.finish();
+}
+
+#[cfg(feature = "async")]
+fn query_info(c: &mut Criterion) {
+    let addr = info_server();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("info");
+    for (name, size) in POOL_SIZES {
+        // Fresh sockets per query, each drawing a buffer, as with randomized source ports
+        let client = runtime
+            .block_on(A2SClient::with_config(A2SConfig {
+                buffer_pool_size: size,
+                randomize_source_port: true,
+                ..Default::default()
+            }))
+            .unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| runtime.block_on(client.info(addr)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, query_info);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use a2s::rules::Rule;
+use a2s::{ParseContext, ParseResponse};
+
+/// A rules payload shaped like a heavily modded server's: a few hundred plugin cvars, with
+/// mostly short values and the odd long one.
+fn large_rules_payload() -> Vec<u8> {
+    let mut rules = vec![
+        ("mp_timelimit".to_owned(), "30".to_owned()),
+        ("sv_gravity".to_owned(), "800".to_owned()),
+        (
+            "sv_tags".to_owned(),
+            "alltalk,increased_maxplayers,nocrits,respawntimes".to_owned(),
+        ),
+        ("sm_nextmap".to_owned(), "pl_badwater".to_owned()),
+    ];
+    for plugin in 0..60 {
+        for cvar in ["enabled", "version", "interval", "message", "flags"] {
+            let value = match cvar {
+                "version" => format!("1.{}.{}", plugin % 7, plugin),
+                "message" => format!("Welcome to the server, visit our website #{}", plugin),
+                _ => (plugin * 3).to_string(),
+            };
+            rules.push((format!("sm_plugin{}_{}", plugin, cvar), value));
+        }
+    }
+
+    let mut payload = vec![0x45];
+    payload.extend((rules.len() as u16).to_le_bytes());
+    for (name, value) in &rules {
+        payload.extend(name.as_bytes());
+        payload.push(0);
+        payload.extend(value.as_bytes());
+        payload.push(0);
+    }
+    payload
+}
+
+fn parse_rules(c: &mut Criterion) {
+    let payload = large_rules_payload();
+    let ctx = ParseContext::default();
+
+    let mut group = c.benchmark_group("rules");
+    group.bench_function("owned", |b| {
+        b.iter(|| Vec::<Rule>::parse(black_box(&payload), &ctx).unwrap())
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter(|| Rule::parse_borrowed(black_box(&payload)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_rules);
+criterion_main!(benches);
@@ -0,0 +1,19 @@
+#![no_main]
+
+use a2s::{parse_response, QueryKind};
+use libfuzzer_sys::fuzz_target;
+
+// The first byte picks the query kind (and The Ship parsing for players), the rest is the
+// datagram.
+fuzz_target!(|data: &[u8]| {
+    if let Some((&selector, datagram)) = data.split_first() {
+        let kind = match selector % 3 {
+            0 => QueryKind::Info,
+            1 => QueryKind::Players,
+            _ => QueryKind::Rules,
+        };
+        let app_id = if selector & 0x80 != 0 { 2400 } else { 0 };
+
+        let _ = parse_response(datagram, kind, app_id);
+    }
+});
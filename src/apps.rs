@@ -0,0 +1,40 @@
+/// Games known to run query-able servers, by Steam application ID. Sorted by ID, as
+/// [`app_name`] searches it. Games with IDs wider than the 16-bit `app_id` of info responses are
+/// left out.
+const KNOWN_APPS: [(u16, &str); 25] = [
+    (10, "Counter-Strike"),
+    (20, "Team Fortress Classic"),
+    (30, "Day of Defeat"),
+    (40, "Deathmatch Classic"),
+    (50, "Half-Life: Opposing Force"),
+    (60, "Ricochet"),
+    (70, "Half-Life"),
+    (80, "Counter-Strike: Condition Zero"),
+    (130, "Half-Life: Blue Shift"),
+    (240, "Counter-Strike: Source"),
+    (300, "Day of Defeat: Source"),
+    (320, "Half-Life 2: Deathmatch"),
+    (360, "Half-Life Deathmatch: Source"),
+    (440, "Team Fortress 2"),
+    (500, "Left 4 Dead"),
+    (550, "Left 4 Dead 2"),
+    (630, "Alien Swarm"),
+    (730, "Counter-Strike: Global Offensive"),
+    (1002, "Rag Doll Kung Fu"),
+    (2400, "The Ship"),
+    (4000, "Garry's Mod"),
+    (17500, "Zombie Panic! Source"),
+    (17520, "Synergy"),
+    (17580, "Dystopia"),
+    (17710, "Nuclear Dawn"),
+];
+
+/// Name of the game with Steam application ID `app_id`, for display. `None` for games this
+/// table doesn't know, in which case [`Info::game`](crate::info::Info::game) is the best
+/// fallback. [`Info::game_name`](crate::info::Info::game_name) picks between the two.
+pub fn app_name(app_id: u16) -> Option<&'static str> {
+    KNOWN_APPS
+        .binary_search_by_key(&app_id, |&(id, _)| id)
+        .ok()
+        .map(|index| KNOWN_APPS[index].1)
+}
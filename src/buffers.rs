@@ -0,0 +1,65 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::MAX_DATAGRAM_SIZE;
+
+/// Receive buffers kept between queries, so that scanning doesn't allocate (and zero) a buffer
+/// large enough for any datagram for every response. See
+/// [`A2SConfig::buffer_pool_size`](crate::A2SConfig::buffer_pool_size).
+#[derive(Debug, Default)]
+pub(crate) struct BufferPool {
+    free: Mutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    /// A buffer of [`MAX_DATAGRAM_SIZE`] bytes, taken from the pool if it has one. It goes back
+    /// to the pool when dropped, unless the pool already holds `max` buffers.
+    pub(crate) fn get(self: &Arc<Self>, max: usize) -> PooledBuffer {
+        let buf = self
+            .free
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+            .unwrap_or_else(|| vec![0; MAX_DATAGRAM_SIZE]);
+
+        PooledBuffer {
+            buf,
+            pool: self.clone(),
+            max,
+        }
+    }
+}
+
+/// A buffer borrowed from a [`BufferPool`]. Its contents are whatever the previous user left.
+pub(crate) struct PooledBuffer {
+    buf: Vec<u8>,
+    pool: Arc<BufferPool>,
+    max: usize,
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let mut free = self
+            .pool
+            .free
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if free.len() < self.max {
+            free.push(std::mem::take(&mut self.buf));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+#[cfg(not(feature = "async"))]
+use std::net::ToSocketAddrs;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "async")]
+use tokio::net::lookup_host;
+
+use crate::errors::Result;
+use crate::{first_addr, A2SClient, MIN_DNS_CACHE_TTL};
+
+/// Addresses names resolved to, shared by the clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct DnsCache {
+    entries: Mutex<HashMap<String, (SocketAddr, Instant)>>,
+}
+
+impl DnsCache {
+    /// The address `host` resolved to, unless it expired.
+    fn get(&self, host: &str) -> Option<SocketAddr> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        match entries.get(host) {
+            Some(&(addr, expiry)) if Instant::now() < expiry => Some(addr),
+            Some(_) => {
+                entries.remove(host);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, host: &str, addr: SocketAddr, ttl: Duration) {
+        let expiry = Instant::now() + ttl.max(MIN_DNS_CACHE_TTL);
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(host.to_owned(), (addr, expiry));
+    }
+
+    fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+impl A2SClient {
+    /// Resolves `host` (e.g. `"host:27015"`) like [`resolve_addr`](crate::resolve_addr), reusing
+    /// the address it last resolved to for
+    /// [`A2SConfig::dns_cache_ttl`](crate::A2SConfig::dns_cache_ttl). Clones of the client share
+    /// the cache.
+    ///
+    /// Queries resolve the address they're given every time, so a monitor polling servers by
+    /// name should query the address resolved here instead:
+    /// `client.info(client.resolve("host:27015")?)`.
+    #[cfg(not(feature = "async"))]
+    pub fn resolve(&self, host: &str) -> Result<SocketAddr> {
+        if let Some(addr) = self.cached_addr(host) {
+            return Ok(addr);
+        }
+
+        let addr = first_addr(host.to_socket_addrs()?)?;
+        self.cache_addr(host, addr);
+        Ok(addr)
+    }
+
+    /// Resolves `host` (e.g. `"host:27015"`) like [`resolve_addr`](crate::resolve_addr), reusing
+    /// the address it last resolved to for
+    /// [`A2SConfig::dns_cache_ttl`](crate::A2SConfig::dns_cache_ttl). Clones of the client share
+    /// the cache.
+    ///
+    /// Queries resolve the address they're given every time, so a monitor polling servers by
+    /// name should query the address resolved here instead:
+    /// `client.info(client.resolve("host:27015").await?).await`.
+    #[cfg(feature = "async")]
+    pub async fn resolve(&self, host: &str) -> Result<SocketAddr> {
+        if let Some(addr) = self.cached_addr(host) {
+            return Ok(addr);
+        }
+
+        let addr = first_addr(lookup_host(host).await?)?;
+        self.cache_addr(host, addr);
+        Ok(addr)
+    }
+
+    /// Forgets every address cached by [`resolve`](Self::resolve), e.g. after a server moved.
+    pub fn clear_dns_cache(&self) {
+        self.dns_cache.clear();
+    }
+
+    fn cached_addr(&self, host: &str) -> Option<SocketAddr> {
+        self.config.dns_cache_ttl?;
+        self.dns_cache.get(host)
+    }
+
+    fn cache_addr(&self, host: &str, addr: SocketAddr) {
+        if let Some(ttl) = self.config.dns_cache_ttl {
+            self.dns_cache.insert(host, addr, ttl);
+        }
+    }
+}
@@ -1,14 +1,23 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
 use thiserror::Error;
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug, Error)]
 pub enum Error {
+    #[cfg(feature = "codec")]
     #[error("IO error {0}")]
     Io(#[from] std::io::Error),
 
     #[error("Failed to reserve memory {0}")]
-    TryReserveError(#[from] std::collections::TryReserveError),
+    TryReserveError(#[from] alloc::collections::TryReserveError),
+
+    /// A response ended before one of its fields.
+    #[error("Unexpected end of response")]
+    UnexpectedEof,
 
     #[error("Socket timed out")]
     ErrTimeout,
@@ -16,6 +25,20 @@ pub enum Error {
     #[error("Invalid response")]
     InvalidResponse,
 
+    /// A malformed response, carrying its first bytes. Only produced with
+    /// [`A2SConfig::dump_invalid_responses`](crate::A2SConfig::dump_invalid_responses).
+    #[error("Invalid response, starting with {}", hex(.0))]
+    InvalidResponseWithDump(Vec<u8>),
+
+    #[error("Empty response, the server may be restarting")]
+    EmptyResponse,
+
+    /// The server asked for a challenge while [`ChallengeMode::Never`] forbids the handshake.
+    ///
+    /// [`ChallengeMode::Never`]: crate::ChallengeMode::Never
+    #[error("The server requires a challenge")]
+    ChallengeRequired,
+
     #[error("Mismatch packet ID")]
     MismatchID,
 
@@ -25,6 +48,64 @@ pub enum Error {
     #[error("Decompressed checksum does not match")]
     CheckSumMismatch,
 
+    /// The server's host answered with ICMP port unreachable, so it's up but nothing listens on
+    /// the port. Only reported reliably by connected sockets, see [`A2SClient::connect`].
+    ///
+    /// [`A2SClient::connect`]: crate::A2SClient::connect
+    #[error("Connection refused, nothing listens on the server's port")]
+    ConnectionRefused,
+
+    #[error("Source port {0} is already in use")]
+    AddrInUse(u16),
+
+    #[error("Incomplete response, received {received} of {expected} fragments")]
+    IncompleteResponse { received: usize, expected: usize },
+
     #[error("{0}")]
     Other(&'static str),
 }
+
+impl Error {
+    /// Whether the error was caused by the server not answering in time.
+    #[cfg(feature = "codec")]
+    pub(crate) fn is_timeout(&self) -> bool {
+        match self {
+            Error::ErrTimeout | Error::IncompleteResponse { .. } => true,
+            Error::Io(err) => matches!(
+                err.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+            ),
+            _ => false,
+        }
+    }
+
+    /// Converts a socket error, reporting the OS relaying an ICMP port unreachable (a refused
+    /// or reset connection depending on the platform) as [`Error::ConnectionRefused`].
+    #[cfg(feature = "std")]
+    pub(crate) fn from_socket(err: std::io::Error) -> Error {
+        match err.kind() {
+            std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::ConnectionReset => {
+                Error::ConnectionRefused
+            }
+            _ => Error::Io(err),
+        }
+    }
+
+    /// Whether the error was caused by the response being malformed, with or without a dump of
+    /// it ([`Error::InvalidResponseWithDump`]). Prefer it to matching [`Error::InvalidResponse`],
+    /// which `A2SConfig::dump_invalid_responses` turns into the other variant.
+    pub fn is_invalid_response(&self) -> bool {
+        matches!(
+            self,
+            Error::InvalidResponse | Error::InvalidResponseWithDump(_) | Error::UnexpectedEof
+        )
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,78 @@
+use crate::errors::{Error, Result};
+use crate::{A2SConfig, Reassembly, Response};
+
+/// Reassembles responses from datagrams received by the caller, for driving your own socket
+/// loop or transport: the multi-packet handling of [`A2SClient`](crate::A2SClient), without
+/// its socket.
+///
+/// Datagrams are pushed one at a time, and once a response is complete its payload is
+/// returned, reassembled, decompressed and checksummed with the limits of the
+/// [`A2SConfig`] given, and without its packet header, ready for
+/// [`ParseResponse::parse`](crate::ParseResponse::parse). Only one response is reassembled at a
+/// time, so datagrams of different servers must go to different assemblers.
+#[derive(Debug)]
+pub struct FragmentAssembler {
+    config: A2SConfig,
+    pending: Option<Reassembly>,
+}
+
+impl FragmentAssembler {
+    pub fn new() -> Self {
+        Self::with_config(A2SConfig::default())
+    }
+
+    /// An assembler accepting responses within the limits of `config`, such as
+    /// [`A2SConfig::max_packets`] and [`A2SConfig::max_bz2_size`].
+    pub fn with_config(config: A2SConfig) -> Self {
+        FragmentAssembler {
+            config,
+            pending: None,
+        }
+    }
+
+    /// Adds a datagram, returning the response's payload if it completes one, or `None` while
+    /// fragments are missing. Fragments may arrive in any order, and duplicates are ignored.
+    ///
+    /// An error discards the response being reassembled, e.g. [`Error::MismatchID`] for a
+    /// fragment of another response, so the next datagram starts a new one. With
+    /// [`A2SConfig::resync_fragments`] such fragments are reassembled alongside instead.
+    pub fn push(&mut self, datagram: &[u8]) -> Result<Option<Vec<u8>>> {
+        let reassembly = match self.pending.take() {
+            Some(mut reassembly) => {
+                reassembly.push(datagram.to_vec(), &self.config)?;
+                reassembly
+            }
+            None => match Response::start(datagram.to_vec(), &self.config)? {
+                Response::Complete(payload) => return Ok(Some(payload)),
+                Response::Partial(reassembly) => reassembly,
+            },
+        };
+
+        if reassembly.is_complete() {
+            reassembly.finish().map(Some)
+        } else {
+            self.pending = Some(reassembly);
+            Ok(None)
+        }
+    }
+
+    /// Whether a multi-packet response is still missing fragments.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Gives up on the response being reassembled, returning [`Error::IncompleteResponse`] with
+    /// how many of its fragments were received, e.g. once the caller's timeout elapses. `None`
+    /// if no response is pending.
+    pub fn reset(&mut self) -> Option<Error> {
+        self.pending
+            .take()
+            .map(|reassembly| reassembly.timed_out(Error::ErrTimeout))
+    }
+}
+
+impl Default for FragmentAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
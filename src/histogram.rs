@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// How large a response received by a query was, as recorded by a [`SizeCollector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseSize {
+    /// Size of the reassembled payload, decompressed if the response was, which is what
+    /// [`A2SConfig::max_bz2_size`](crate::A2SConfig::max_bz2_size) limits
+    pub payload: usize,
+
+    /// Number of datagrams the response arrived in, which is what
+    /// [`A2SConfig::max_packets`](crate::A2SConfig::max_packets) limits
+    pub packets: usize,
+
+    /// Size of the largest of those datagrams, headers included, which for fragments is what
+    /// [`A2SConfig::max_size`](crate::A2SConfig::max_size) limits
+    pub largest_datagram: usize,
+
+    /// Total size of those datagrams, headers included
+    pub bytes: usize,
+}
+
+/// Receives the size of every response a client receives, once set with
+/// [`A2SClient::size_collector`](crate::A2SClient::size_collector). Failed queries, and attempts
+/// retried after a timeout, record nothing.
+///
+/// It's called on the querying thread or task, before the response is parsed, so it should be
+/// quick. [`SizeHistogram`] is a ready-made one.
+pub trait SizeCollector: Send + Sync {
+    fn record(&self, size: ResponseSize);
+}
+
+/// A [`SizeCollector`] keeping a histogram of payload sizes and one of packet counts, for
+/// picking response limits from what servers actually send. Share it between the clients of a
+/// fleet through an `Arc`, and read it from anywhere.
+///
+/// Payload sizes are bucketed by powers of two, packet counts are exact.
+#[derive(Debug, Default)]
+pub struct SizeHistogram {
+    histograms: Mutex<Histograms>,
+}
+
+#[derive(Debug, Default)]
+struct Histograms {
+    count: u64,
+    /// Counts by the power of two payload sizes are at most
+    payloads: BTreeMap<usize, u64>,
+    packets: BTreeMap<usize, u64>,
+    max_payload: usize,
+    max_datagram: usize,
+}
+
+impl SizeHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of responses recorded.
+    pub fn count(&self) -> u64 {
+        self.lock().count
+    }
+
+    /// Payload sizes as `(bound, count)` buckets in increasing order, `count` responses having a
+    /// payload of at most `bound` bytes and more than the previous power of two. Empty buckets
+    /// are left out.
+    pub fn payload_sizes(&self) -> Vec<(usize, u64)> {
+        self.lock()
+            .payloads
+            .iter()
+            .map(|(&bound, &count)| (bound, count))
+            .collect()
+    }
+
+    /// Packet counts as `(packets, count)` pairs in increasing order, `count` responses having
+    /// arrived in `packets` datagrams.
+    pub fn packet_counts(&self) -> Vec<(usize, u64)> {
+        self.lock()
+            .packets
+            .iter()
+            .map(|(&packets, &count)| (packets, count))
+            .collect()
+    }
+
+    /// The bucket bound at least a `quantile` (from 0.0 to 1.0) of payloads fit in, e.g. the
+    /// size 99% of responses don't exceed for `0.99`. `None` if nothing was recorded.
+    pub fn payload_quantile(&self, quantile: f64) -> Option<usize> {
+        let histograms = self.lock();
+        let wanted = (quantile.clamp(0.0, 1.0) * histograms.count as f64).ceil() as u64;
+
+        let mut seen = 0;
+        histograms
+            .payloads
+            .iter()
+            .find(|(_, &count)| {
+                seen += count;
+                seen >= wanted.max(1)
+            })
+            .map(|(&bound, _)| bound)
+    }
+
+    /// Largest payload recorded, in bytes.
+    pub fn max_payload(&self) -> usize {
+        self.lock().max_payload
+    }
+
+    /// Largest number of packets a response arrived in.
+    pub fn max_packets(&self) -> usize {
+        self.lock().packets.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Largest datagram recorded, headers included.
+    pub fn max_datagram(&self) -> usize {
+        self.lock().max_datagram
+    }
+
+    /// Forgets everything recorded.
+    pub fn clear(&self) {
+        *self.lock() = Histograms::default();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Histograms> {
+        self.histograms
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl SizeCollector for SizeHistogram {
+    fn record(&self, size: ResponseSize) {
+        let mut histograms = self.lock();
+        histograms.count += 1;
+        *histograms
+            .payloads
+            .entry(size.payload.next_power_of_two())
+            .or_default() += 1;
+        *histograms.packets.entry(size.packets).or_default() += 1;
+        histograms.max_payload = histograms.max_payload.max(size.payload);
+        histograms.max_datagram = histograms.max_datagram.max(size.largest_datagram);
+    }
+}
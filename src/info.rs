@@ -1,7 +1,17 @@
-use std::convert::TryFrom;
-use std::io::{Cursor, ErrorKind, Write};
-#[cfg(not(feature = "async"))]
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::net::SocketAddr;
+use core::time::Duration;
+#[cfg(feature = "codec")]
+use std::io::Cursor;
+#[cfg(all(feature = "std", not(feature = "async")))]
 use std::net::ToSocketAddrs;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 #[cfg(feature = "async")]
 use tokio::net::ToSocketAddrs;
@@ -9,17 +19,22 @@ use tokio::net::ToSocketAddrs;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use bitflags::bitflags;
+use crc::crc64;
 
 use crate::errors::{Error, Result};
-use crate::{A2SClient, ReadCString};
+use crate::reader::BytesReader;
+#[cfg(feature = "std")]
+use crate::{read_challenge, request_packet, A2SClient, QueryKind};
+use crate::{with_dump, ParseContext, ParseResponse};
 
-const INFO_REQUEST: [u8; 25] = [
+#[cfg(feature = "codec")]
+pub(crate) const INFO_REQUEST: [u8; 25] = [
     0xFF, 0xFF, 0xFF, 0xFF, 0x54, 0x53, 0x6F, 0x75, 0x72, 0x63, 0x65, 0x20, 0x45, 0x6E, 0x67, 0x69,
     0x6E, 0x65, 0x20, 0x51, 0x75, 0x65, 0x72, 0x79, 0x00,
 ];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct TheShip {
     /// Indicates the game mode
@@ -30,9 +45,69 @@ pub struct TheShip {
 
     /// Time (in seconds) before a player is arrested while being witnessed.
     pub duration: u8,
+
+    /// Why the response was read with these fields.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub detection: TheShipDetection,
+}
+
+/// The app ID The Ship's servers report.
+pub const THE_SHIP_APP_ID: u16 = 2400;
+
+/// The protocol The Ship's servers report.
+pub const THE_SHIP_PROTOCOL: u8 = 7;
+
+/// What identified an info response as one with The Ship's fields.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum TheShipDetection {
+    /// Both the app ID and protocol are The Ship's.
+    #[default]
+    AppIdAndProtocol,
+
+    /// The app ID is The Ship's but the protocol isn't. The fields were still read, as the
+    /// response only parses cleanly, version and EDF fields with nothing left over, with them.
+    Layout,
+
+    /// The 16-bit app ID isn't The Ship's, but the low 24 bits of the EDF game ID are, see
+    /// [`Info::true_app_id`]. The game ID comes after the fields, so they are only read if the
+    /// response parses into such a game ID with them.
+    GameId,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// The order an info response's EDF fields were read in, see
+/// [`A2SConfig::lenient_edf`](crate::A2SConfig::lenient_edf).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum EdfOrder {
+    /// The order servers write them in: port, Steam ID, SourceTV, keywords and game ID.
+    #[default]
+    Standard,
+
+    /// Keywords before the Steam ID, as some mods write them: port, keywords, Steam ID,
+    /// SourceTV and game ID.
+    KeywordsFirst,
+
+    /// SourceTV last, as in the order the fields are often documented in: port, Steam ID,
+    /// keywords, game ID and SourceTV.
+    SourceTvLast,
+}
+
+impl EdfOrder {
+    /// Orders tried after the standard one fails.
+    const ALTERNATIVES: [EdfOrder; 2] = [EdfOrder::KeywordsFirst, EdfOrder::SourceTvLast];
+
+    /// The EDF flags of the fields, in the order they are read.
+    fn flags(self) -> [u8; 5] {
+        match self {
+            EdfOrder::Standard => [0x80, 0x10, 0x40, 0x20, 0x01],
+            EdfOrder::KeywordsFirst => [0x80, 0x20, 0x10, 0x40, 0x01],
+            EdfOrder::SourceTvLast => [0x80, 0x10, 0x20, 0x01, 0x40],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[repr(u8)]
 pub enum TheShipMode {
@@ -59,7 +134,7 @@ impl From<u8> for TheShipMode {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct ExtendedServerInfo {
     /// The server's game port number.
@@ -80,7 +155,117 @@ pub struct ExtendedServerInfo {
     pub game_id: Option<u64>,
 }
 
-#[derive(Debug, Clone)]
+impl ExtendedServerInfo {
+    fn parse_reader(data: &mut BytesReader, the_ship: bool) -> Result<Self> {
+        data.skip_packet_header();
+
+        match data.read_u8()? {
+            0x49 => {}
+            0x6d => {
+                return Ok(ExtendedServerInfo {
+                    port: None,
+                    steam_id: None,
+                    keywords: None,
+                    game_id: None,
+                })
+            }
+            _ => return Err(Error::InvalidResponse),
+        }
+
+        let protocol = data.read_u8()?;
+        // Name, map, folder and game
+        for _ in 0..4 {
+            data.skip_cstring();
+        }
+        let app_id = data.read_u16_le()?;
+        // Players, max players, bots, server type, OS, visibility and VAC
+        data.read_bytes(7)?;
+        if the_ship_detection(data, protocol, app_id, the_ship).is_some() {
+            data.read_bytes(3)?;
+        }
+        data.skip_cstring();
+        let edf = if data.is_empty() { 0 } else { data.read_u8()? };
+
+        Ok(read_extended_server_info(data, edf, EdfOrder::Standard, 1, &mut Vec::new()).0)
+    }
+
+    /// The server's SteamID split into its components, if present.
+    pub fn parsed_steam_id(&self) -> Option<SteamId> {
+        self.steam_id.map(SteamId)
+    }
+}
+
+/// A 64-bit SteamID, as reported in the extended server info.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SteamId(pub u64);
+
+impl SteamId {
+    /// The account number, the lowest 32 bits.
+    pub fn account_id(&self) -> u32 {
+        self.0 as u32
+    }
+
+    /// The account instance, 20 bits.
+    pub fn instance(&self) -> u32 {
+        (self.0 >> 32) as u32 & 0xFFFFF
+    }
+
+    /// The account type, 4 bits. Game servers are usually 3 (GameServer) or 4 (AnonGameServer).
+    pub fn account_type(&self) -> u8 {
+        (self.0 >> 52) as u8 & 0xF
+    }
+
+    /// The universe, the highest 8 bits. 1 is the public universe.
+    pub fn universe(&self) -> u8 {
+        (self.0 >> 56) as u8
+    }
+
+    /// Renders the textual `STEAM_X:Y:Z` form.
+    pub fn steam2(&self) -> String {
+        format!(
+            "STEAM_{}:{}:{}",
+            self.universe(),
+            self.account_id() & 1,
+            self.account_id() >> 1
+        )
+    }
+
+    /// Renders the textual `[T:U:A]` form, e.g. `[G:1:123456]` for a game server.
+    pub fn steam3(&self) -> String {
+        let letter = match self.account_type() {
+            1 => 'U',
+            2 => 'M',
+            3 => 'G',
+            4 => 'A',
+            5 => 'P',
+            6 => 'C',
+            7 => 'g',
+            8 => 'T',
+            10 => 'a',
+            _ => 'I',
+        };
+
+        if self.account_type() == 4 {
+            format!(
+                "[{}:{}:{}:{}]",
+                letter,
+                self.universe(),
+                self.account_id(),
+                self.instance()
+            )
+        } else {
+            format!("[{}:{}:{}]", letter, self.universe(), self.account_id())
+        }
+    }
+}
+
+impl core::fmt::Display for SteamId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.steam2())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct SourceTVInfo {
     /// Spectator port number for SourceTV.
@@ -90,7 +275,7 @@ pub struct SourceTVInfo {
     pub name: String,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[repr(u8)]
 pub enum ServerType {
@@ -111,7 +296,7 @@ impl TryFrom<u8> for ServerType {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[repr(u8)]
 pub enum ServerOS {
@@ -133,7 +318,44 @@ impl TryFrom<u8> for ServerOS {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Details of how an info query went, rather than of the server's info itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct InfoMeta {
+    /// Time the server took to answer, see [`A2SClient::info_and_ping`].
+    pub ping: Duration,
+
+    /// Whether the server demanded a challenge before answering. Servers patched against
+    /// reflection attacks (since late 2020) do, older ones answer right away.
+    pub challenge_required: bool,
+
+    /// Number of packets the info response was split into, 1 unless it was a multi-packet
+    /// response. Useful to tune [`A2SConfig::max_packets`](crate::A2SConfig::max_packets).
+    pub packets: usize,
+
+    /// Size of the request the info response answered, packet header and challenge included.
+    pub request_bytes: usize,
+
+    /// Total size of the datagrams the info response arrived in, headers included.
+    pub response_bytes: usize,
+
+    /// The reassembled (and decompressed) payload the info was parsed from, packet header
+    /// excluded, if [`A2SConfig::keep_payload`](crate::A2SConfig::keep_payload) is set. It can be
+    /// stored and parsed again later with [`ParseResponse::parse`](crate::ParseResponse::parse),
+    /// so that caches keep what the server sent rather than what this version made of it.
+    pub payload: Option<Vec<u8>>,
+}
+
+impl InfoMeta {
+    /// How many times larger the response was than the request, as measured when assessing
+    /// how much a server amplifies reflected traffic. Challenge handshakes exist to keep this
+    /// close to 1 for unverified sources, so only the final exchange is accounted for.
+    pub fn amplification(&self) -> f64 {
+        self.response_bytes as f64 / self.request_bytes as f64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Info {
     /// Protocol version used by the server.
@@ -157,7 +379,8 @@ pub struct Info {
     /// Number of players on the server.
     pub players: u8,
 
-    /// Maximum number of players the server reports it can hold.
+    /// Maximum number of players the server reports it can hold. 255 may mean there's no
+    /// limit, see [`max_players_display`](Self::max_players_display).
     pub max_players: u8,
 
     /// Number of bots on the server.
@@ -167,15 +390,27 @@ pub struct Info {
     /// Rag Doll Kung Fu servers always return 0 for "Server type."
     pub server_type: ServerType,
 
+    /// The byte `server_type` was parsed from.
+    pub server_type_raw: u8,
+
     /// Indicates the operating system of the server
     pub server_os: ServerOS,
 
+    /// The byte `server_os` was parsed from, e.g. `b'o'` for servers reporting the legacy Mac byte.
+    pub server_os_raw: u8,
+
     /// Indicates whether the server requires a password
     pub visibility: bool,
 
+    /// The byte `visibility` was parsed from. Some servers use values other than 0 and 1.
+    pub visibility_raw: u8,
+
     /// Specifies whether the server uses VAC
     pub vac: bool,
 
+    /// The byte `vac` was parsed from. Some servers use values other than 0 and 1.
+    pub vac_raw: u8,
+
     /// These fields only exist in a response if the server is running The Ship
     pub the_ship: Option<TheShip>,
 
@@ -187,12 +422,513 @@ pub struct Info {
 
     pub extended_server_info: ExtendedServerInfo,
 
-    /// Available if edf & 0x40 is true
+    /// Available if edf & 0x40 is true. Usually a single relay, see
+    /// [`source_tv`](Self::source_tv). Some servers send further port and name blocks right
+    /// after the first, which are read best-effort: only if the response doesn't parse cleanly
+    /// with one block but does with more.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub source_tvs: Vec<SourceTVInfo>,
+
+    /// Any bytes left over after the known EDF fields.
+    /// Servers may append game-specific extensions here, which this crate doesn't decode.
+    pub extra: Vec<u8>,
+
+    /// Address of the server as reported by a legacy GoldSource response.
+    /// Always `None` for Source responses.
+    pub gold_source_address: Option<String>,
+
+    /// Mod details of a legacy GoldSource response, present if the server runs a mod.
+    pub gold_source_mod: Option<GoldSourceMod>,
+
+    /// Problems met reading the optional EDF fields, which are left `None` rather than failing
+    /// the whole response. Empty for well-formed responses.
+    pub parse_warnings: Vec<String>,
+
+    /// The order the EDF fields were read in. Only other than [`EdfOrder::Standard`] with
+    /// [`A2SConfig::lenient_edf`](crate::A2SConfig::lenient_edf), for responses only parsing
+    /// cleanly in another order. [`to_bytes`](Self::to_bytes) still writes the standard order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub edf_order: EdfOrder,
+}
+
+bitflags! {
+    /// Selects the fields [`Info::project`] keeps.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct InfoFields: u32 {
+        const PROTOCOL = 1 << 0;
+        const NAME = 1 << 1;
+        const MAP = 1 << 2;
+        const FOLDER = 1 << 3;
+        const GAME = 1 << 4;
+        const APP_ID = 1 << 5;
+        const PLAYERS = 1 << 6;
+        const MAX_PLAYERS = 1 << 7;
+        const BOTS = 1 << 8;
+        const SERVER_TYPE = 1 << 9;
+        const SERVER_OS = 1 << 10;
+        const VISIBILITY = 1 << 11;
+        const VAC = 1 << 12;
+        const THE_SHIP = 1 << 13;
+        const VERSION = 1 << 14;
+        const EXTENDED_SERVER_INFO = 1 << 15;
+        const SOURCE_TV = 1 << 16;
+    }
+}
+
+bitflags! {
+    /// The boolean properties of a server, see [`Info::flags`]. Compact to store with
+    /// [`bits`](Self::bits) and to compare across polls.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ServerFlags: u8 {
+        /// The server requires a password, [`Info::visibility`].
+        const PASSWORD = 1 << 0;
+        /// The server uses VAC, [`Info::vac`].
+        const VAC = 1 << 1;
+        /// The server reports a SourceTV relay, [`Info::source_tvs`].
+        const SOURCE_TV = 1 << 2;
+    }
+}
+
+/// Format of an info response, see [`Info::engine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum Engine {
+    Source,
+
+    /// The obsolete GoldSource response (header `0x6D`). Many GoldSource servers answer in
+    /// the Source format instead, which reports them as [`Engine::Source`].
+    GoldSource,
+}
+
+/// A projection of [`Info`] holding only the selected fields, the others being `None`.
+/// Raw bytes and GoldSource details are never kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PartialInfo {
+    pub protocol: Option<u8>,
+
+    pub name: Option<String>,
+
+    pub map: Option<String>,
+
+    pub folder: Option<String>,
+
+    pub game: Option<String>,
+
+    pub app_id: Option<u16>,
+
+    pub players: Option<u8>,
+
+    pub max_players: Option<u8>,
+
+    pub bots: Option<u8>,
+
+    pub server_type: Option<ServerType>,
+
+    pub server_os: Option<ServerOS>,
+
+    pub visibility: Option<bool>,
+
+    pub vac: Option<bool>,
+
+    pub the_ship: Option<TheShip>,
+
+    pub version: Option<String>,
+
+    pub extended_server_info: Option<ExtendedServerInfo>,
+
+    /// The first SourceTV relay, see [`Info::source_tv`].
     pub source_tv: Option<SourceTVInfo>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct GoldSourceMod {
+    /// URL to mod website.
+    pub link: String,
+
+    /// URL to download the mod.
+    pub download_link: String,
+
+    /// Version of mod installed on server.
+    pub version: u32,
+
+    /// Space (in bytes) the mod takes up.
+    pub size: u32,
+
+    /// Whether the mod is multiplayer only, rather than single and multiplayer.
+    pub multiplayer_only: bool,
+
+    /// Whether the mod uses its own DLL rather than the Half-Life DLL.
+    pub custom_dll: bool,
+}
+
+/// Quotes and escapes a string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// The byte to encode a flag as: the raw byte if it still agrees with `flag`, otherwise 0 or 1.
+fn flag_byte(flag: bool, raw: u8) -> u8 {
+    if (raw != 0) == flag {
+        raw
+    } else {
+        flag as u8
+    }
+}
+
 impl Info {
+    /// Flattens the info into string keys and values, e.g. for metric labels or structured logs.
+    /// Optional fields are only included when present, nested ones under dotted keys such as
+    /// `source_tv.port`. SourceTV relays after the first are numbered, as in `source_tv.1.port`.
+    pub fn to_map(&self) -> BTreeMap<String, String> {
+        let mut map = BTreeMap::new();
+        let mut insert = |key: &str, value: String| {
+            map.insert(key.to_owned(), value);
+        };
+
+        insert("protocol", self.protocol.to_string());
+        insert("name", self.name.clone());
+        insert("map", self.map.clone());
+        insert("folder", self.folder.clone());
+        insert("game", self.game.clone());
+        insert("app_id", self.app_id.to_string());
+        insert("players", self.players.to_string());
+        insert("max_players", self.max_players.to_string());
+        insert("bots", self.bots.to_string());
+        insert("server_type", format!("{:?}", self.server_type));
+        insert("server_os", format!("{:?}", self.server_os));
+        insert("visibility", self.visibility.to_string());
+        insert("vac", self.vac.to_string());
+        insert("version", self.version.clone());
+        insert("edf", self.edf.to_string());
+
+        if let Some(the_ship) = &self.the_ship {
+            insert("the_ship.mode", format!("{:?}", the_ship.mode));
+            insert("the_ship.witnesses", the_ship.witnesses.to_string());
+            insert("the_ship.duration", the_ship.duration.to_string());
+        }
+        if let Some(port) = self.extended_server_info.port {
+            insert("port", port.to_string());
+        }
+        if let Some(steam_id) = self.extended_server_info.steam_id {
+            insert("steam_id", steam_id.to_string());
+        }
+        if let Some(keywords) = &self.extended_server_info.keywords {
+            insert("keywords", keywords.clone());
+        }
+        if let Some(game_id) = self.extended_server_info.game_id {
+            insert("game_id", game_id.to_string());
+        }
+        for (i, source_tv) in self.source_tvs.iter().enumerate() {
+            let prefix = match i {
+                0 => "source_tv".to_owned(),
+                i => format!("source_tv.{}", i),
+            };
+            insert(&format!("{}.port", prefix), source_tv.port.to_string());
+            insert(&format!("{}.name", prefix), source_tv.name.clone());
+        }
+        if let Some(address) = &self.gold_source_address {
+            insert("gold_source_address", address.clone());
+        }
+
+        map
+    }
+
+    /// The keywords (tags) as a map, for games packing `key:value` pairs in them. Bare tags are
+    /// keys with an empty value. Entries without a key are skipped, and the first of duplicate
+    /// keys wins.
+    pub fn keyword_map(&self) -> BTreeMap<String, String> {
+        let mut map = BTreeMap::new();
+
+        let keywords = match &self.extended_server_info.keywords {
+            Some(keywords) => keywords,
+            None => return map,
+        };
+
+        for entry in keywords.split(',') {
+            let (key, value) = match entry.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (entry.trim(), ""),
+            };
+
+            if !key.is_empty() {
+                map.entry(key.to_owned())
+                    .or_insert_with(|| value.to_owned());
+            }
+        }
+
+        map
+    }
+
+    /// The name without control characters, see [`clean_name`](crate::clean_name) to also strip
+    /// color markers.
+    pub fn clean_name(&self) -> String {
+        crate::clean_name(&self.name, false)
+    }
+
+    /// The port players connect to. `queried` is the address the info was queried from, which
+    /// is the query endpoint: behind query proxies or with a separate query port it differs
+    /// from the game's, which the server then reports in its EDF port. Without one, the game is
+    /// assumed to listen on the queried port.
+    pub fn game_port(&self, queried: SocketAddr) -> u16 {
+        self.extended_server_info
+            .port
+            .unwrap_or_else(|| queried.port())
+    }
+
+    /// Whether the server is behind Steam Datagram Relay (SDR), told by a FakeIP (see
+    /// [`is_fake_ip`](crate::is_fake_ip)) as the address it was queried at (`queried`) or
+    /// reports. Source responses don't carry the server's address, so those are only detected
+    /// by `queried`. SDR servers queried directly at their real address can't be told apart,
+    /// and the address they report doesn't reflect their actual game endpoint.
+    pub fn is_sdr(&self, queried: SocketAddr) -> bool {
+        let reported = self
+            .gold_source_address
+            .as_deref()
+            .and_then(|address| address.parse::<SocketAddr>().ok());
+
+        core::iter::once(queried)
+            .chain(reported)
+            .any(|addr| crate::is_fake_ip(addr.ip()))
+    }
+
+    /// The player limit, or `None` if the server reports 255, which some use to mean unlimited
+    /// or unknown rather than an actual limit.
+    pub fn max_players_display(&self) -> Option<u8> {
+        match self.max_players {
+            u8::MAX => None,
+            max_players => Some(max_players),
+        }
+    }
+
+    /// Whether the server has no free player slots. Never true for servers without a limit,
+    /// see [`max_players_display`](Self::max_players_display).
+    pub fn is_full(&self) -> bool {
+        self.max_players_display()
+            .is_some_and(|max_players| self.players >= max_players)
+    }
+
+    /// Whether the player counts agree with each other, i.e. neither:
+    ///
+    /// - more players than the player limit, unless the limit is 255, see
+    ///   [`max_players_display`](Self::max_players_display),
+    /// - more bots than players, bots being counted among players.
+    ///
+    /// Servers faking their population to rank higher in server browsers often fail this, but
+    /// so do a few modded ones counting bots or reserved slots their own way. See
+    /// [`A2SConfig::reject_inconsistent`](crate::A2SConfig::reject_inconsistent) to reject
+    /// such responses.
+    pub fn is_consistent(&self) -> bool {
+        let overfull = self
+            .max_players_display()
+            .is_some_and(|max_players| self.players > max_players);
+
+        !overfull && self.bots <= self.players
+    }
+
+    /// Keeps only the selected fields, e.g. to forward a smaller payload.
+    pub fn project(&self, fields: InfoFields) -> PartialInfo {
+        let keep = |field| fields.contains(field);
+
+        PartialInfo {
+            protocol: keep(InfoFields::PROTOCOL).then_some(self.protocol),
+            name: keep(InfoFields::NAME).then(|| self.name.clone()),
+            map: keep(InfoFields::MAP).then(|| self.map.clone()),
+            folder: keep(InfoFields::FOLDER).then(|| self.folder.clone()),
+            game: keep(InfoFields::GAME).then(|| self.game.clone()),
+            app_id: keep(InfoFields::APP_ID).then_some(self.app_id),
+            players: keep(InfoFields::PLAYERS).then_some(self.players),
+            max_players: keep(InfoFields::MAX_PLAYERS).then_some(self.max_players),
+            bots: keep(InfoFields::BOTS).then_some(self.bots),
+            server_type: keep(InfoFields::SERVER_TYPE).then_some(self.server_type),
+            server_os: keep(InfoFields::SERVER_OS).then_some(self.server_os),
+            visibility: keep(InfoFields::VISIBILITY).then_some(self.visibility),
+            vac: keep(InfoFields::VAC).then_some(self.vac),
+            the_ship: keep(InfoFields::THE_SHIP)
+                .then(|| self.the_ship.clone())
+                .flatten(),
+            version: keep(InfoFields::VERSION).then(|| self.version.clone()),
+            extended_server_info: keep(InfoFields::EXTENDED_SERVER_INFO)
+                .then(|| self.extended_server_info.clone()),
+            source_tv: keep(InfoFields::SOURCE_TV)
+                .then(|| self.source_tv().cloned())
+                .flatten(),
+        }
+    }
+
+    /// A compact JSON object of the common scalar fields, written by hand so that small tools
+    /// can print info as JSON without the `serialization` feature.
+    pub fn to_json_minimal(&self) -> String {
+        format!(
+            concat!(
+                r#"{{"name":{},"map":{},"folder":{},"game":{},"app_id":{},"players":{},"#,
+                r#""max_players":{},"bots":{},"server_type":"{:?}","server_os":"{:?}","#,
+                r#""visibility":{},"vac":{},"version":{}}}"#,
+            ),
+            json_string(&self.name),
+            json_string(&self.map),
+            json_string(&self.folder),
+            json_string(&self.game),
+            self.app_id,
+            self.players,
+            self.max_players,
+            self.bots,
+            self.server_type,
+            self.server_os,
+            self.visibility,
+            self.vac,
+            json_string(&self.version),
+        )
+    }
+
+    /// The name of the server's game, for display. Minimally configured servers may leave the
+    /// `game` field empty, so this falls back to the name of the app ID as known by
+    /// [`app_name`](crate::apps::app_name), then to the game's `folder`. `None` if all of them
+    /// are unknown or blank.
+    pub fn game_name(&self) -> Option<&str> {
+        let game = self.game.trim();
+        if !game.is_empty() {
+            return Some(game);
+        }
+
+        crate::apps::app_name(self.app_id).or_else(|| {
+            let folder = self.folder.trim();
+            (!folder.is_empty()).then_some(folder)
+        })
+    }
+
+    /// The server's real Steam Application ID. The `app_id` field is forced into 16 bits and may
+    /// have been truncated, so the low 24 bits of the EDF game ID are preferred when present.
+    pub fn true_app_id(&self) -> u32 {
+        match self.extended_server_info.game_id {
+            Some(game_id) => (game_id & 0xFFFFFF) as u32,
+            None => self.app_id.into(),
+        }
+    }
+
+    /// A hash of the fields identifying the server, to recognize the same server answering on
+    /// several addresses (DNS round-robin, proxies). Only the name, game, app ID, Steam ID and
+    /// SourceTV details contribute, so the hash doesn't change with the map or players, but it
+    /// does for servers that put those in their name.
+    ///
+    /// The hash is a CRC-64 (ECMA), stable across versions of Rust and of this crate, so it can be
+    /// stored.
+    pub fn fingerprint(&self) -> u64 {
+        // Strings are length-prefixed so moving bytes between fields changes the hash
+        let mut bytes = Vec::new();
+        for field in [&self.name, &self.game] {
+            bytes.extend((field.len() as u64).to_le_bytes());
+            bytes.extend(field.as_bytes());
+        }
+        bytes.extend(self.app_id.to_le_bytes());
+
+        match self.extended_server_info.steam_id {
+            Some(steam_id) => {
+                bytes.push(1);
+                bytes.extend(steam_id.to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+
+        // Only the first relay, so hashes stored before further ones were read still match
+        match self.source_tv() {
+            Some(source_tv) => {
+                bytes.push(1);
+                bytes.extend(source_tv.port.to_le_bytes());
+                bytes.extend((source_tv.name.len() as u64).to_le_bytes());
+                bytes.extend(source_tv.name.as_bytes());
+            }
+            None => bytes.push(0),
+        }
+
+        crc64::checksum_ecma(&bytes)
+    }
+
+    /// The boolean fields gathered in one value.
+    pub fn flags(&self) -> ServerFlags {
+        let mut flags = ServerFlags::empty();
+        flags.set(ServerFlags::PASSWORD, self.visibility);
+        flags.set(ServerFlags::VAC, self.vac);
+        flags.set(ServerFlags::SOURCE_TV, !self.source_tvs.is_empty());
+        flags
+    }
+
+    /// The first SourceTV relay, the only one most servers report, see
+    /// [`source_tvs`](Self::source_tvs).
+    pub fn source_tv(&self) -> Option<&SourceTVInfo> {
+        self.source_tvs.first()
+    }
+
+    /// Ping to the server some query proxies measure and append after the known fields, in
+    /// milliseconds, read from [`extra`](Self::extra). Proxy-specific and best-effort: only
+    /// leftovers of exactly 2 bytes (`u16`) or 4 bytes (`u32` no larger than `u16::MAX`) are
+    /// taken for one, so another extension of that size would be misread as a ping. Always
+    /// `None` for GoldSource responses.
+    pub fn proxy_ping(&self) -> Option<u16> {
+        if self.engine() == Engine::GoldSource {
+            return None;
+        }
+
+        match *self.extra {
+            [a, b] => Some(u16::from_le_bytes([a, b])),
+            [a, b, c, d] => u16::try_from(u32::from_le_bytes([a, b, c, d])).ok(),
+            _ => None,
+        }
+    }
+
+    /// Format the server answered in, only GoldSource responses carry the server's address.
+    pub fn engine(&self) -> Engine {
+        if self.gold_source_address.is_some() {
+            Engine::GoldSource
+        } else {
+            Engine::Source
+        }
+    }
+
+    /// Whether the server runs the app `app_id`, by either its 16-bit `app_id` or its
+    /// [`true_app_id`](Self::true_app_id).
+    pub fn matches_app(&self, app_id: u32) -> bool {
+        u32::from(self.app_id) == app_id || self.true_app_id() == app_id
+    }
+
+    /// The server type byte to encode: the raw byte if it still maps to `server_type`, otherwise
+    /// the canonical byte of `server_type`.
+    fn server_type_byte(&self) -> u8 {
+        match ServerType::try_from(self.server_type_raw) {
+            Ok(server_type) if server_type == self.server_type => self.server_type_raw,
+            _ => self.server_type as u8,
+        }
+    }
+
+    /// The OS byte to encode: the raw byte if it still maps to `server_os`, otherwise the
+    /// canonical byte of `server_os`.
+    fn server_os_byte(&self) -> u8 {
+        match ServerOS::try_from(self.server_os_raw) {
+            Ok(server_os) if server_os == self.server_os => self.server_os_raw,
+            _ => self.server_os as u8,
+        }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
+        if let Some(address) = &self.gold_source_address {
+            return self.to_gold_source_bytes(address);
+        }
+
         let mut bytes = Vec::new();
         bytes.extend(&[0xff, 0xff, 0xff, 0xff, 0x49]);
         bytes.push(self.protocol);
@@ -208,10 +944,10 @@ impl Info {
         bytes.push(self.players);
         bytes.push(self.max_players);
         bytes.push(self.bots);
-        bytes.push(self.server_type as u8);
-        bytes.push(self.server_os as u8);
-        bytes.push(if self.visibility { 1 } else { 0 });
-        bytes.push(if self.vac { 1 } else { 0 });
+        bytes.push(self.server_type_byte());
+        bytes.push(self.server_os_byte());
+        bytes.push(flag_byte(self.visibility, self.visibility_raw));
+        bytes.push(flag_byte(self.vac, self.vac_raw));
 
         if let Some(the_ship) = &self.the_ship {
             bytes.push(the_ship.mode as u8);
@@ -232,6 +968,11 @@ impl Info {
         if let Some(steam_id) = &self.extended_server_info.steam_id {
             bytes.extend(steam_id.to_le_bytes());
         }
+        for source_tv in &self.source_tvs {
+            bytes.extend(source_tv.port.to_le_bytes());
+            bytes.extend(source_tv.name.as_bytes());
+            bytes.push(0);
+        }
         if let Some(keywords) = &self.extended_server_info.keywords {
             bytes.extend(keywords.as_bytes());
             bytes.push(0);
@@ -240,18 +981,78 @@ impl Info {
             bytes.extend(game_id.to_le_bytes());
         }
 
-        if let Some(source_tv) = &self.source_tv {
-            bytes.extend(source_tv.port.to_le_bytes());
-            bytes.extend(source_tv.name.as_bytes());
+        bytes.extend(&self.extra);
+
+        bytes
+    }
+
+    fn to_gold_source_bytes(&self, address: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(&[0xff, 0xff, 0xff, 0xff, 0x6d]);
+        bytes.extend(address.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.name.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.map.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.folder.as_bytes());
+        bytes.push(0);
+        bytes.extend(self.game.as_bytes());
+        bytes.push(0);
+        bytes.push(self.players);
+        bytes.push(self.max_players);
+        bytes.push(self.protocol);
+        bytes.push(self.server_type_raw);
+        bytes.push(self.server_os_raw);
+        bytes.push(flag_byte(self.visibility, self.visibility_raw));
+
+        if let Some(gold_source_mod) = &self.gold_source_mod {
+            bytes.push(1);
+            bytes.extend(gold_source_mod.link.as_bytes());
+            bytes.push(0);
+            bytes.extend(gold_source_mod.download_link.as_bytes());
+            bytes.push(0);
+            bytes.push(0);
+            bytes.extend(gold_source_mod.version.to_le_bytes());
+            bytes.extend(gold_source_mod.size.to_le_bytes());
+            bytes.push(gold_source_mod.multiplayer_only as u8);
+            bytes.push(gold_source_mod.custom_dll as u8);
+        } else {
             bytes.push(0);
         }
 
+        bytes.push(flag_byte(self.vac, self.vac_raw));
+        bytes.push(self.bots);
+        bytes.extend(&self.extra);
+
         bytes
     }
 
-    pub fn from_cursor(mut data: Cursor<Vec<u8>>) -> Result<Self> {
-        if data.read_u8()? != 0x49u8 {
-            return Err(Error::InvalidResponse);
+    /// Parses an info payload, skipping a leading single packet header if a relay left one.
+    #[cfg(feature = "codec")]
+    pub fn from_cursor(data: Cursor<Vec<u8>>) -> Result<Self> {
+        Self::from_reader(&mut BytesReader::from_cursor(&data))
+    }
+
+    /// Parses an info payload like [`from_cursor`](Self::from_cursor), from a borrowed buffer.
+    pub fn from_reader(data: &mut BytesReader) -> Result<Self> {
+        Self::parse_reader(data, true)
+    }
+
+    /// Parses like [`from_reader`](Self::from_reader), skipping The Ship fields unless
+    /// `the_ship` is set.
+    fn parse_reader(data: &mut BytesReader, the_ship: bool) -> Result<Self> {
+        Self::parse_reader_in(data, the_ship, EdfOrder::Standard)
+    }
+
+    /// Parses like [`parse_reader`](Self::parse_reader), reading the EDF fields in `order`.
+    fn parse_reader_in(data: &mut BytesReader, the_ship: bool, order: EdfOrder) -> Result<Self> {
+        data.skip_packet_header();
+
+        match data.read_u8()? {
+            0x49 => {}
+            0x6d => return Self::from_gold_source_reader(data),
+            _ => return Err(Error::InvalidResponse),
         }
 
         let protocol = data.read_u8()?;
@@ -259,63 +1060,58 @@ impl Info {
         let map = data.read_cstring()?;
         let folder = data.read_cstring()?;
         let game = data.read_cstring()?;
-        let app_id = data.read_u16::<LittleEndian>()?;
+        let app_id = data.read_u16_le()?;
         let players = data.read_u8()?;
         let max_players = data.read_u8()?;
         let bots = data.read_u8()?;
-        let server_type = ServerType::try_from(data.read_u8()?)?;
-        let server_os = ServerOS::try_from(data.read_u8()?)?;
-        let visibility = data.read_u8()? != 0;
-        let vac = data.read_u8()? != 0;
-        let the_ship = if app_id == 2400 {
-            Some(TheShip {
+        let server_type_raw = data.read_u8()?;
+        let server_type = ServerType::try_from(server_type_raw)?;
+        let server_os_raw = data.read_u8()?;
+        let server_os = ServerOS::try_from(server_os_raw)?;
+        let visibility_raw = data.read_u8()?;
+        let visibility = visibility_raw != 0;
+        let vac_raw = data.read_u8()?;
+        let vac = vac_raw != 0;
+        let the_ship = match the_ship_detection(data, protocol, app_id, the_ship) {
+            Some(detection) => Some(TheShip {
                 mode: TheShipMode::from(data.read_u8()?),
                 witnesses: data.read_u8()?,
                 duration: data.read_u8()?,
-            })
-        } else {
-            None
+                detection,
+            }),
+            None => None,
         };
         let version = data.read_cstring()?;
-        let edf = match data.read_u8() {
-            Ok(val) => val,
-            Err(err) => {
-                if err.kind() != ErrorKind::UnexpectedEof {
-                    return Err(Error::Io(err));
-                } else {
-                    0
+        let edf = if data.is_empty() { 0 } else { data.read_u8()? };
+        // A failed EDF field leaves the cursor somewhere unknown, so every field after it is
+        // skipped too
+        let edf_start = data.clone();
+        let mut parse_warnings = Vec::new();
+        let (mut extended_server_info, mut source_tvs) =
+            read_extended_server_info(data, edf, order, 1, &mut parse_warnings);
+
+        // Bytes after the first SourceTV name may be another relay rather than the next field,
+        // which is only trusted if the response then parses cleanly with nothing left over
+        if edf & 0x40 != 0 && (!parse_warnings.is_empty() || !data.is_empty()) {
+            for blocks in 2..=MAX_SOURCE_TV_BLOCKS {
+                let mut retry = edf_start.clone();
+                let mut warnings = Vec::new();
+                let (other, other_source_tvs) =
+                    read_extended_server_info(&mut retry, edf, order, blocks, &mut warnings);
+                if warnings.is_empty() && retry.is_empty() {
+                    *data = retry;
+                    parse_warnings = warnings;
+                    extended_server_info = other;
+                    source_tvs = other_source_tvs;
+                    break;
                 }
             }
-        };
-        let extended_server_info = ExtendedServerInfo {
-            port: if edf & 0x80 != 0 {
-                Some(data.read_u16::<LittleEndian>()?)
-            } else {
-                None
-            },
-            steam_id: if edf & 0x10 != 0 {
-                Some(data.read_u64::<LittleEndian>()?)
-            } else {
-                None
-            },
-            keywords: if edf & 0x20 != 0 {
-                Some(data.read_cstring()?)
-            } else {
-                None
-            },
-            game_id: if edf & 0x01 != 0 {
-                Some(data.read_u64::<LittleEndian>()?)
-            } else {
-                None
-            },
-        };
-        let source_tv = if edf & 0x40 != 0 {
-            Some(SourceTVInfo {
-                port: data.read_u16::<LittleEndian>()?,
-                name: data.read_cstring()?,
-            })
+        }
+
+        let extra = if parse_warnings.is_empty() {
+            data.rest().to_vec()
         } else {
-            None
+            Vec::new()
         };
 
         Ok(Info {
@@ -329,58 +1125,673 @@ impl Info {
             max_players,
             bots,
             server_type,
+            server_type_raw,
             server_os,
+            server_os_raw,
             visibility,
+            visibility_raw,
             vac,
+            vac_raw,
             the_ship,
             version,
             edf,
             extended_server_info,
-            source_tv,
+            source_tvs,
+            extra,
+            gold_source_address: None,
+            gold_source_mod: None,
+            parse_warnings,
+            edf_order: order,
+        })
+    }
+
+    /// Parses the obsolete GoldSource response, which servers still running on that engine send.
+    fn from_gold_source_reader(data: &mut BytesReader) -> Result<Self> {
+        let address = data.read_cstring()?;
+        let name = data.read_cstring()?;
+        let map = data.read_cstring()?;
+        let folder = data.read_cstring()?;
+        let game = data.read_cstring()?;
+        let players = data.read_u8()?;
+        let max_players = data.read_u8()?;
+        let protocol = data.read_u8()?;
+        // GoldSource reports types and environments as uppercase letters
+        let server_type_raw = data.read_u8()?;
+        let server_type = match server_type_raw.to_ascii_lowercase() {
+            b'l' => ServerType::NonDedicated,
+            other => ServerType::try_from(other)?,
+        };
+        let server_os_raw = data.read_u8()?;
+        let server_os = ServerOS::try_from(server_os_raw.to_ascii_lowercase())?;
+        let visibility_raw = data.read_u8()?;
+        let visibility = visibility_raw != 0;
+        let gold_source_mod = if data.read_u8()? == 1 {
+            let link = data.read_cstring()?;
+            let download_link = data.read_cstring()?;
+            // Unused null byte
+            data.read_u8()?;
+
+            Some(GoldSourceMod {
+                link,
+                download_link,
+                version: data.read_u32_le()?,
+                size: data.read_u32_le()?,
+                multiplayer_only: data.read_u8()? != 0,
+                custom_dll: data.read_u8()? != 0,
+            })
+        } else {
+            None
+        };
+        let vac_raw = data.read_u8()?;
+        let vac = vac_raw != 0;
+        let bots = data.read_u8()?;
+
+        let extra = data.rest().to_vec();
+
+        Ok(Info {
+            protocol,
+            name,
+            map,
+            folder,
+            game,
+            app_id: 0,
+            players,
+            max_players,
+            bots,
+            server_type,
+            server_type_raw,
+            server_os,
+            server_os_raw,
+            visibility,
+            visibility_raw,
+            vac,
+            vac_raw,
+            the_ship: None,
+            version: String::new(),
+            edf: 0,
+            extended_server_info: ExtendedServerInfo {
+                port: None,
+                steam_id: None,
+                keywords: None,
+                game_id: None,
+            },
+            source_tvs: Vec::new(),
+            extra,
+            gold_source_address: Some(address),
+            gold_source_mod,
+            parse_warnings: Vec::new(),
+            edf_order: EdfOrder::Standard,
         })
     }
 }
 
+/// Whether a query's outcome shows the server answered, see [`A2SClient::is_alive`]. Errors
+/// that aren't about the server's answer, such as failing to resolve it, are returned as is.
+#[cfg(feature = "std")]
+fn answered<T>(result: Result<T>) -> Result<bool> {
+    match result {
+        Ok(_)
+        | Err(Error::IncompleteResponse { .. })
+        | Err(Error::MismatchID)
+        | Err(Error::InvalidBz2Size)
+        | Err(Error::CheckSumMismatch)
+        | Err(Error::ChallengeRequired) => Ok(true),
+        Err(err) if err.is_invalid_response() => Ok(true),
+        Err(Error::ConnectionRefused) | Err(Error::EmptyResponse) => Ok(false),
+        Err(err) if err.is_timeout() => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Most SourceTV relays [`Info::source_tvs`] are read from one response.
+const MAX_SOURCE_TV_BLOCKS: usize = 4;
+
+/// What identifies an info response, read up to `vac`, as one with The Ship's fields, if
+/// `enabled`.
+fn the_ship_detection(
+    data: &BytesReader,
+    protocol: u8,
+    app_id: u16,
+    enabled: bool,
+) -> Option<TheShipDetection> {
+    if !enabled {
+        None
+    } else if app_id != THE_SHIP_APP_ID {
+        the_ship_game_id(data)
+    } else if protocol == THE_SHIP_PROTOCOL {
+        Some(TheShipDetection::AppIdAndProtocol)
+    } else {
+        // Builds with another protocol may leave the fields out, in which case the version
+        // follows `vac` directly. A game mode byte alone doesn't tell, an empty version starts
+        // with 0 too, so the rest must only parse cleanly with the fields.
+        let clean = |peek| matches!(peek_tail(peek), Some((_, true)));
+        (clean(skip_the_ship(data)?) && !clean(data.clone())).then_some(TheShipDetection::Layout)
+    }
+}
+
+/// `data`, read up to `vac`, past The Ship's fields, if the first of them is a game mode rather
+/// than the printable start of the version.
+fn skip_the_ship<'a>(data: &BytesReader<'a>) -> Option<BytesReader<'a>> {
+    let mut peek = data.clone();
+    if *peek.read_bytes(3).ok()?.first()? > 5 {
+        return None;
+    }
+    Some(peek)
+}
+
+/// Reads the version and EDF fields of an info response ahead, returning the EDF fields if they
+/// parse without warnings, along with whether that leaves nothing over.
+fn peek_tail(mut peek: BytesReader) -> Option<(ExtendedServerInfo, bool)> {
+    peek.read_cstr().ok()?;
+    let edf = if peek.is_empty() {
+        0
+    } else {
+        peek.read_u8().ok()?
+    };
+
+    let mut warnings = Vec::new();
+    let (extended_server_info, _) =
+        read_extended_server_info(&mut peek, edf, EdfOrder::Standard, 1, &mut warnings);
+    warnings
+        .is_empty()
+        .then(|| (extended_server_info, peek.is_empty()))
+}
+
+/// Whether an info response, read up to `vac`, whose 16-bit app ID isn't The Ship's has its
+/// fields anyway, as told by the EDF game ID. That comes after the fields, so the rest of the
+/// response is read ahead assuming they're there, and they are if it then parses into a game ID
+/// of The Ship.
+fn the_ship_game_id(data: &BytesReader) -> Option<TheShipDetection> {
+    let game_id = peek_tail(skip_the_ship(data)?)?.0.game_id?;
+    (game_id & 0xFFFFFF == u64::from(THE_SHIP_APP_ID)).then_some(TheShipDetection::GameId)
+}
+
+/// Reads the EDF fields flagged in `edf` in `order`, see [`read_edf`], with `source_tv_blocks`
+/// SourceTV relays back to back.
+fn read_extended_server_info(
+    data: &mut BytesReader,
+    edf: u8,
+    order: EdfOrder,
+    source_tv_blocks: usize,
+    warnings: &mut Vec<String>,
+) -> (ExtendedServerInfo, Vec<SourceTVInfo>) {
+    let mut extended_server_info = ExtendedServerInfo {
+        port: None,
+        steam_id: None,
+        keywords: None,
+        game_id: None,
+    };
+    let mut source_tvs = Vec::new();
+
+    for flag in order.flags() {
+        let present = edf & flag;
+        match flag {
+            0x80 => {
+                extended_server_info.port =
+                    read_edf(data, present, "port", warnings, |data| data.read_u16_le())
+            }
+            0x10 => {
+                extended_server_info.steam_id =
+                    read_edf(data, present, "steam_id", warnings, |data| {
+                        data.read_u64_le()
+                    })
+            }
+            0x40 => {
+                for _ in 0..source_tv_blocks {
+                    source_tvs.extend(read_edf(data, present, "source_tv", warnings, |data| {
+                        Ok(SourceTVInfo {
+                            port: data.read_u16_le()?,
+                            name: data.read_cstring()?,
+                        })
+                    }))
+                }
+            }
+            0x20 => {
+                extended_server_info.keywords =
+                    read_edf(data, present, "keywords", warnings, |data| {
+                        data.read_cstring()
+                    })
+            }
+            _ => {
+                extended_server_info.game_id =
+                    read_edf(data, present, "game_id", warnings, |data| {
+                        data.read_u64_le()
+                    })
+            }
+        }
+    }
+
+    (extended_server_info, source_tvs)
+}
+
+/// Reads an optional EDF field if its `flag` is set. A failure is recorded in `warnings` rather
+/// than returned, and skips the field, as well as any after it.
+fn read_edf<T>(
+    data: &mut BytesReader,
+    flag: u8,
+    field: &str,
+    warnings: &mut Vec<String>,
+    read: impl FnOnce(&mut BytesReader) -> Result<T>,
+) -> Option<T> {
+    if flag == 0 {
+        return None;
+    }
+    if !warnings.is_empty() {
+        warnings.push(format!("{} skipped after an earlier failure", field));
+        return None;
+    }
+
+    match read(data) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warnings.push(format!("failed to read {}: {}", field, err));
+            None
+        }
+    }
+}
+
+impl ParseResponse for Info {
+    fn parse(bytes: &[u8], ctx: &ParseContext) -> Result<Self> {
+        let parse =
+            |order| Self::parse_reader_in(&mut BytesReader::new(bytes), ctx.parse_the_ship, order);
+        let info = parse(EdfOrder::Standard)
+            .map(|info| {
+                if !ctx.lenient_edf || info.parse_warnings.is_empty() && info.extra.is_empty() {
+                    return info;
+                }
+                // Only an order the fields parse cleanly in, with nothing left over, is trusted
+                EdfOrder::ALTERNATIVES
+                    .iter()
+                    .filter_map(|&order| parse(order).ok())
+                    .find(|other| other.parse_warnings.is_empty() && other.extra.is_empty())
+                    .unwrap_or(info)
+            })
+            .and_then(|info| {
+                if ctx.reject_inconsistent && !info.is_consistent() {
+                    Err(Error::InvalidResponse)
+                } else {
+                    Ok(info)
+                }
+            });
+        with_dump(bytes, info, ctx)
+    }
+}
+
+impl ParseResponse for ExtendedServerInfo {
+    /// Parses only the EDF fields of an info payload. The fields before them are skipped
+    /// without being decoded or copied, and EDF fields that fail to read are left `None`, as in
+    /// [`Info::extended_server_info`]. GoldSource responses have none.
+    fn parse(bytes: &[u8], ctx: &ParseContext) -> Result<Self> {
+        with_dump(
+            bytes,
+            Self::parse_reader(&mut BytesReader::new(bytes), ctx.parse_the_ship),
+            ctx,
+        )
+    }
+}
+
+impl From<&Info> for BTreeMap<String, String> {
+    fn from(info: &Info) -> Self {
+        info.to_map()
+    }
+}
+
+#[cfg(feature = "std")]
 impl A2SClient {
+    /// The exact datagram [`info`](Self::info) sends, or with `challenge` the one answering a
+    /// server's challenge, e.g. to replay the query with other tools.
+    pub fn info_request_bytes(&self, challenge: Option<i32>) -> Vec<u8> {
+        request_packet(&INFO_REQUEST, challenge)
+    }
+
+    /// Queries info. Servers answering in the obsolete GoldSource format are parsed into the
+    /// same [`Info`], see [`Info::engine`] to tell them apart.
+    ///
+    /// If the server requires a challenge but answers the challenged request with something
+    /// other than info, such as an echo of the request or a fresh challenge, the request is
+    /// resent once (with the fresh challenge, if any) before the query fails.
     #[cfg(feature = "async")]
     pub async fn info<A: ToSocketAddrs>(&self, addr: A) -> Result<Info> {
-        let response = self.send(&INFO_REQUEST, &addr).await?;
+        self.info_until(addr, None).await
+    }
+
+    /// Queries info like [`info`](Self::info), but fails with [`Error::ErrTimeout`] once
+    /// `deadline` passes, no matter how many round trips (challenge, fragments) are left.
+    /// This bounds the worst-case latency of the whole query rather than of each operation.
+    #[cfg(feature = "async")]
+    pub async fn info_by<A: ToSocketAddrs>(&self, addr: A, deadline: Instant) -> Result<Info> {
+        self.info_until(addr, Some(deadline)).await
+    }
 
-        let mut packet = Cursor::new(&response);
+    /// Queries info along with the time the server took to answer it, which saves a separate
+    /// (and deprecated) A2S_PING query. The challenge round trip some servers require first is
+    /// left out unless
+    /// [`A2SConfig::ping_includes_challenge`](crate::A2SConfig::ping_includes_challenge) is set.
+    #[cfg(feature = "async")]
+    pub async fn info_and_ping<A: ToSocketAddrs>(&self, addr: A) -> Result<(Info, Duration)> {
+        let (info, meta) = self.info_timed(addr, None).await?;
+        Ok((info, meta.ping))
+    }
 
-        let header = packet.read_u8()?;
-        if header == b'A' {
-            let challenge = packet.read_i32::<LittleEndian>()?;
+    /// Queries info along with what was learned about the server while doing so.
+    #[cfg(feature = "async")]
+    pub async fn info_with_meta<A: ToSocketAddrs>(&self, addr: A) -> Result<(Info, InfoMeta)> {
+        self.info_timed(addr, None).await
+    }
+
+    #[cfg(feature = "async")]
+    async fn info_until<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        deadline: Option<Instant>,
+    ) -> Result<Info> {
+        Ok(self.info_timed(addr, deadline).await?.0)
+    }
 
-            let mut query = Vec::with_capacity(29);
-            query.write_all(&INFO_REQUEST)?;
-            query.write_i32::<LittleEndian>(challenge)?;
+    /// Queries info, also collecting its [`InfoMeta`].
+    #[cfg(feature = "async")]
+    async fn info_timed<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        deadline: Option<Instant>,
+    ) -> Result<(Info, InfoMeta)> {
+        let deadline = self.query_deadline(deadline);
+        let start = self.clock.now();
+        let received = self
+            .send_counted(&INFO_REQUEST, &addr, deadline, &self.config)
+            .await?;
 
-            let data = self.send(&query, addr).await?;
-            Info::from_cursor(Cursor::new(data))
+        let challenge = read_challenge(&received.payload, self.config.challenge_header);
+        let challenge_required = challenge.is_some();
+        let (received, request_bytes, ping) = if let Some(challenge) = challenge {
+            let sent = self.clock.now();
+            let (received, _) = self
+                .send_challenged(&INFO_REQUEST, challenge, &addr, deadline)
+                .await?;
+            let since = if self.config.ping_includes_challenge {
+                start
+            } else {
+                sent
+            };
+            (
+                received,
+                INFO_REQUEST.len() + 4,
+                self.clock.now().saturating_duration_since(since),
+            )
         } else {
-            Info::from_cursor(Cursor::new(response))
-        }
+            (
+                received,
+                INFO_REQUEST.len(),
+                self.clock.now().saturating_duration_since(start),
+            )
+        };
+
+        let ctx = self.parse_context(self.config.app_id);
+        let info = self
+            .parse_from(&addr, QueryKind::Info, &received.payload, &ctx)
+            .await?;
+        let meta = InfoMeta {
+            ping,
+            challenge_required,
+            packets: received.packets,
+            request_bytes,
+            response_bytes: received.bytes,
+            payload: if self.config.keep_payload {
+                Some(received.payload)
+            } else {
+                None
+            },
+        };
+        Ok((info, meta))
+    }
+
+    /// Queries info with a challenge obtained earlier, sending none if `challenge` is `None`.
+    /// With a valid challenge, servers requiring one answer right away instead of after a
+    /// handshake, which only happens if the server asks for a (fresh) challenge. Returns the
+    /// challenge the server accepted if it required one, to reuse for the next query.
+    #[cfg(feature = "async")]
+    pub async fn info_with_challenge<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        challenge: Option<i32>,
+    ) -> Result<(Info, Option<i32>)> {
+        let (data, challenge) = self
+            .do_optional_challenge_request(&addr, &INFO_REQUEST, challenge)
+            .await?;
+
+        let ctx = self.parse_context(self.config.app_id);
+        let info = self.parse_from(&addr, QueryKind::Info, &data, &ctx).await?;
+        Ok((info, challenge))
     }
 
+    /// Queries info, parsing only its [`ExtendedServerInfo`], for tools keyed on the SteamID or
+    /// game port that need nothing else. The rest of the response is skipped rather than
+    /// parsed.
+    #[cfg(feature = "async")]
+    pub async fn extended_info<A: ToSocketAddrs>(&self, addr: A) -> Result<ExtendedServerInfo> {
+        let (data, _) = self
+            .do_optional_challenge_request(&addr, &INFO_REQUEST, None)
+            .await?;
+
+        let ctx = self.parse_context(self.config.app_id);
+        self.parse_from(&addr, QueryKind::Info, &data, &ctx).await
+    }
+
+    /// Queries info. Servers answering in the obsolete GoldSource format are parsed into the
+    /// same [`Info`], see [`Info::engine`] to tell them apart.
+    ///
+    /// If the server requires a challenge but answers the challenged request with something
+    /// other than info, such as an echo of the request or a fresh challenge, the request is
+    /// resent once (with the fresh challenge, if any) before the query fails.
     #[cfg(not(feature = "async"))]
     pub fn info<A: ToSocketAddrs>(&self, addr: A) -> Result<Info> {
-        let response = self.send(&INFO_REQUEST, &addr)?;
+        self.info_until(addr, None)
+    }
+
+    /// Queries info like [`info`](Self::info), but fails with [`Error::ErrTimeout`] once
+    /// `deadline` passes, no matter how many round trips (challenge, fragments) are left.
+    /// This bounds the worst-case latency of the whole query rather than of each operation.
+    #[cfg(not(feature = "async"))]
+    pub fn info_by<A: ToSocketAddrs>(&self, addr: A, deadline: Instant) -> Result<Info> {
+        self.info_until(addr, Some(deadline))
+    }
 
-        let mut packet = Cursor::new(&response);
+    /// Queries info along with the time the server took to answer it, which saves a separate
+    /// (and deprecated) A2S_PING query. The challenge round trip some servers require first is
+    /// left out unless
+    /// [`A2SConfig::ping_includes_challenge`](crate::A2SConfig::ping_includes_challenge) is set.
+    #[cfg(not(feature = "async"))]
+    pub fn info_and_ping<A: ToSocketAddrs>(&self, addr: A) -> Result<(Info, Duration)> {
+        let (info, meta) = self.info_timed(addr, None)?;
+        Ok((info, meta.ping))
+    }
+
+    /// Queries info along with what was learned about the server while doing so.
+    #[cfg(not(feature = "async"))]
+    pub fn info_with_meta<A: ToSocketAddrs>(&self, addr: A) -> Result<(Info, InfoMeta)> {
+        self.info_timed(addr, None)
+    }
 
-        let header = packet.read_u8()?;
-        if header == b'A' {
-            let challenge = packet.read_i32::<LittleEndian>()?;
+    #[cfg(not(feature = "async"))]
+    fn info_until<A: ToSocketAddrs>(&self, addr: A, deadline: Option<Instant>) -> Result<Info> {
+        Ok(self.info_timed(addr, deadline)?.0)
+    }
 
-            let mut query = Vec::with_capacity(29);
-            query.write_all(&INFO_REQUEST)?;
-            query.write_i32::<LittleEndian>(challenge)?;
+    /// Queries info, also collecting its [`InfoMeta`].
+    #[cfg(not(feature = "async"))]
+    fn info_timed<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        deadline: Option<Instant>,
+    ) -> Result<(Info, InfoMeta)> {
+        let deadline = self.query_deadline(deadline);
+        let start = self.clock.now();
+        let received = self.send_counted(&INFO_REQUEST, &addr, deadline, &self.config)?;
 
-            let data = self.send(&query, addr)?;
-            Info::from_cursor(Cursor::new(data))
+        let challenge = read_challenge(&received.payload, self.config.challenge_header);
+        let challenge_required = challenge.is_some();
+        let (received, request_bytes, ping) = if let Some(challenge) = challenge {
+            let sent = self.clock.now();
+            let (received, _) = self.send_challenged(&INFO_REQUEST, challenge, &addr, deadline)?;
+            let since = if self.config.ping_includes_challenge {
+                start
+            } else {
+                sent
+            };
+            (
+                received,
+                INFO_REQUEST.len() + 4,
+                self.clock.now().saturating_duration_since(since),
+            )
         } else {
-            Info::from_cursor(Cursor::new(response))
+            (
+                received,
+                INFO_REQUEST.len(),
+                self.clock.now().saturating_duration_since(start),
+            )
+        };
+
+        let ctx = self.parse_context(self.config.app_id);
+        let info = self.parse_from(&addr, QueryKind::Info, &received.payload, &ctx)?;
+        let meta = InfoMeta {
+            ping,
+            challenge_required,
+            packets: received.packets,
+            request_bytes,
+            response_bytes: received.bytes,
+            payload: if self.config.keep_payload {
+                Some(received.payload)
+            } else {
+                None
+            },
+        };
+        Ok((info, meta))
+    }
+
+    /// Queries info with a challenge obtained earlier, sending none if `challenge` is `None`.
+    /// With a valid challenge, servers requiring one answer right away instead of after a
+    /// handshake, which only happens if the server asks for a (fresh) challenge. Returns the
+    /// challenge the server accepted if it required one, to reuse for the next query.
+    #[cfg(not(feature = "async"))]
+    pub fn info_with_challenge<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        challenge: Option<i32>,
+    ) -> Result<(Info, Option<i32>)> {
+        let (data, challenge) =
+            self.do_optional_challenge_request(&addr, &INFO_REQUEST, challenge)?;
+
+        let ctx = self.parse_context(self.config.app_id);
+        let info = self.parse_from(&addr, QueryKind::Info, &data, &ctx)?;
+        Ok((info, challenge))
+    }
+
+    /// Queries info, parsing only its [`ExtendedServerInfo`], for tools keyed on the SteamID or
+    /// game port that need nothing else. The rest of the response is skipped rather than
+    /// parsed.
+    #[cfg(not(feature = "async"))]
+    pub fn extended_info<A: ToSocketAddrs>(&self, addr: A) -> Result<ExtendedServerInfo> {
+        let (data, _) = self.do_optional_challenge_request(&addr, &INFO_REQUEST, None)?;
+
+        let ctx = self.parse_context(self.config.app_id);
+        self.parse_from(&addr, QueryKind::Info, &data, &ctx)
+    }
+
+    /// Checks whether the server answers the info request within the timeout, without parsing
+    /// the info body. Any answer counts as alive, even a malformed or incomplete one. Only a
+    /// timeout, a refused request ([`Error::ConnectionRefused`]) or an empty datagram
+    /// ([`Error::EmptyResponse`]) don't.
+    #[cfg(feature = "async")]
+    pub async fn is_alive<A: ToSocketAddrs>(&self, addr: A) -> Result<bool> {
+        answered(self.send(&INFO_REQUEST, &addr).await)
+    }
+
+    /// Checks whether the server answers the info request within the timeout, without parsing
+    /// the info body. Any answer counts as alive, even a malformed or incomplete one. Only a
+    /// timeout, a refused request ([`Error::ConnectionRefused`]) or an empty datagram
+    /// ([`Error::EmptyResponse`]) don't.
+    #[cfg(not(feature = "async"))]
+    pub fn is_alive<A: ToSocketAddrs>(&self, addr: A) -> Result<bool> {
+        answered(self.send(&INFO_REQUEST, &addr))
+    }
+
+    /// Tries querying info on each of `candidate_ports` of `host` in order, returning the info
+    /// along with the first port that answered. Fails with the last port's error if none did.
+    #[cfg(feature = "async")]
+    pub async fn info_with_port_fallback(
+        &self,
+        host: &str,
+        candidate_ports: &[u16],
+    ) -> Result<(Info, u16)> {
+        let mut last_err = Error::Other("No candidate ports");
+
+        for &port in candidate_ports {
+            match self.info((host, port)).await {
+                Ok(info) => return Ok((info, port)),
+                Err(err) => last_err = err,
+            }
+        }
+
+        Err(last_err)
+    }
+
+    /// Tries querying info on each of `candidate_ports` of `host` in order, returning the info
+    /// along with the first port that answered. Fails with the last port's error if none did.
+    #[cfg(not(feature = "async"))]
+    pub fn info_with_port_fallback(
+        &self,
+        host: &str,
+        candidate_ports: &[u16],
+    ) -> Result<(Info, u16)> {
+        let mut last_err = Error::Other("No candidate ports");
+
+        for &port in candidate_ports {
+            match self.info((host, port)) {
+                Ok(info) => return Ok((info, port)),
+                Err(err) => last_err = err,
+            }
+        }
+
+        Err(last_err)
+    }
+
+    /// Queries info of a server only known by its game (connect) address, looking for its query
+    /// port on the ports [`A2SConfig::query_ports`](crate::A2SConfig::query_ports) points at, in
+    /// order, see [`info_with_port_fallback`](Self::info_with_port_fallback). Returns the info
+    /// along with the query port that answered.
+    #[cfg(feature = "async")]
+    pub async fn info_with_query_port_discovery(
+        &self,
+        game_addr: SocketAddr,
+    ) -> Result<(Info, u16)> {
+        let ports = self.discovery_ports(game_addr.port());
+        self.info_with_port_fallback(&game_addr.ip().to_string(), &ports)
+            .await
+    }
+
+    /// Queries info of a server only known by its game (connect) address, looking for its query
+    /// port on the ports [`A2SConfig::query_ports`](crate::A2SConfig::query_ports) points at, in
+    /// order, see [`info_with_port_fallback`](Self::info_with_port_fallback). Returns the info
+    /// along with the query port that answered.
+    #[cfg(not(feature = "async"))]
+    pub fn info_with_query_port_discovery(&self, game_addr: SocketAddr) -> Result<(Info, u16)> {
+        let ports = self.discovery_ports(game_addr.port());
+        self.info_with_port_fallback(&game_addr.ip().to_string(), &ports)
+    }
+
+    /// The ports to look for the query port of a server on `game_port` on, without repeats.
+    fn discovery_ports(&self, game_port: u16) -> Vec<u16> {
+        let mut ports = Vec::new();
+        for port in self
+            .config
+            .query_ports
+            .iter()
+            .filter_map(|query_port| query_port.resolve(game_port))
+        {
+            if !ports.contains(&port) {
+                ports.push(port);
+            }
         }
+        ports
     }
 }
@@ -1,110 +1,1121 @@
+//! Queries game servers over the Steam server query protocol (A2S).
+//!
+//! Without the default `std` feature only the parsing core is built, on `core` and `alloc`: the
+//! [`info`], [`players`] and [`rules`] parsers (see [`ParseResponse`]), the [`reader`] and
+//! the [`master`] filter, for environments receiving responses by other means. Payloads are
+//! parsed without their packet header, and multi-packet responses must be reassembled first.
+//!
+//! The `codec` feature adds what needs `std` but no socket: the `fragment` reassembly, BZip2
+//! decompression included, [`A2SConfig`] and `parse_response`. The `wasm` feature builds on it
+//! with the `transport` module, querying over a datagram transport the application supplies.
+//! Neither pulls in a socket crate or a C library, so both build for `wasm32-unknown-unknown`.
+//! The default `std` feature adds `A2SClient` and everything built on its socket.
+
+#![cfg_attr(not(feature = "codec"), no_std)]
+
+extern crate alloc;
+
+pub mod apps;
+#[cfg(feature = "std")]
+mod buffers;
+#[cfg(feature = "std")]
+mod dns;
 pub mod errors;
+#[cfg(feature = "codec")]
+pub mod fragment;
+#[cfg(feature = "std")]
+pub mod histogram;
 pub mod info;
+#[cfg(feature = "std")]
+mod malformed;
+pub mod master;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+#[cfg(feature = "std")]
+pub mod ping;
 pub mod players;
+#[cfg(feature = "std")]
+pub mod pool;
+#[cfg(feature = "std")]
+pub mod probe;
+#[cfg(feature = "codec")]
+pub mod query;
+pub mod reader;
+#[cfg(feature = "replay")]
+pub mod replay;
 pub mod rules;
+#[cfg(feature = "std")]
+mod scan;
+#[cfg(feature = "std")]
+pub mod state;
+pub mod tags;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(any(
+    feature = "wasm",
+    feature = "replay",
+    feature = "tcp",
+    feature = "async"
+))]
+pub mod transport;
 
-use std::io::{Cursor, Read, Write};
-#[cfg(not(feature = "async"))]
+#[cfg(feature = "async")]
+mod router;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "codec")]
+use core::convert::TryFrom;
+use core::net::{IpAddr, SocketAddr};
+#[cfg(feature = "codec")]
+use core::ops::Deref;
+#[cfg(feature = "codec")]
+use std::io::Read;
+#[cfg(all(feature = "std", not(feature = "async")))]
 use std::net::{ToSocketAddrs, UdpSocket};
-use std::ops::Deref;
+#[cfg(feature = "codec")]
+use std::path::PathBuf;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "codec")]
 use std::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 #[cfg(feature = "async")]
-use tokio::net::{ToSocketAddrs, UdpSocket};
+use tokio::net::{lookup_host, ToSocketAddrs, UdpSocket};
+#[cfg(feature = "async")]
+use tokio::sync::Semaphore;
 #[cfg(feature = "async")]
 use tokio::time;
 
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "std")]
+use byteorder::{LittleEndian, WriteBytesExt};
+#[cfg(feature = "codec")]
 use bzip2::read::BzDecoder;
+#[cfg(feature = "codec")]
 use crc::crc32;
+#[cfg(feature = "std")]
+use socket2::SockRef;
 
+#[cfg(feature = "std")]
+use crate::buffers::BufferPool;
+#[cfg(feature = "std")]
+use crate::dns::DnsCache;
 use crate::errors::{Error, Result};
+#[cfg(feature = "std")]
+use crate::histogram::{ResponseSize, SizeCollector};
+use crate::info::Info;
+#[cfg(feature = "std")]
+use crate::info::INFO_REQUEST;
+use crate::players::Player;
+#[cfg(feature = "async")]
+use crate::router::Router;
+use crate::rules::Rule;
+#[cfg(feature = "std")]
+use crate::trace::{Direction, Recorder};
+
+/// Largest datagram a response may arrive in.
+#[cfg(feature = "std")]
+const MAX_DATAGRAM_SIZE: usize = 65535;
 
+#[cfg(feature = "codec")]
 const SINGLE_PACKET: i32 = -1;
+#[cfg(feature = "codec")]
 const MULTI_PACKET: i32 = -2;
 
 // Offsets
+#[cfg(feature = "codec")]
 const OFS_HEADER: usize = 0;
+#[cfg(feature = "codec")]
 const OFS_SP_PAYLOAD: usize = 4;
+#[cfg(feature = "codec")]
 const OFS_MP_ID: usize = 4;
+#[cfg(feature = "codec")]
 const OFS_MP_SS_TOTAL: usize = 8;
+#[cfg(feature = "codec")]
 const OFS_MP_SS_NUMBER: usize = 9;
+#[cfg(feature = "codec")]
 const OFS_MP_SS_SIZE: usize = 10;
+#[cfg(feature = "codec")]
 const OFS_MP_SS_BZ2_SIZE: usize = 12;
+#[cfg(feature = "codec")]
 const OFS_MP_SS_BZ2_CRC: usize = 16;
+#[cfg(feature = "codec")]
 const OFS_MP_SS_PAYLOAD: usize = OFS_MP_SS_BZ2_SIZE;
+#[cfg(feature = "codec")]
 const OFS_MP_SS_PAYLOAD_BZ2: usize = OFS_MP_SS_BZ2_CRC + 4;
-
+#[cfg(feature = "codec")]
+const OFS_MP_GS_PACKET: usize = 8;
+#[cfg(feature = "codec")]
+const OFS_MP_GS_PAYLOAD: usize = 9;
+
+/// Reads a little endian integer at an offset of a buffer, failing with
+/// [`Error::InvalidResponse`] if the buffer is too short.
+#[cfg(feature = "codec")]
 macro_rules! read_buffer_offset {
     ($buf:expr, $offset:expr, i8) => {
-        $buf[$offset].into()
+        read_buffer_offset!(@le $buf, $offset, i8, 1)
     };
     ($buf:expr, $offset:expr, u8) => {
-        $buf[$offset].into()
+        read_buffer_offset!(@le $buf, $offset, u8, 1)
     };
     ($buf:expr, $offset:expr, i16) => {
-        i16::from_le_bytes([$buf[$offset], $buf[$offset + 1]])
+        read_buffer_offset!(@le $buf, $offset, i16, 2)
     };
     ($buf:expr, $offset:expr, u16) => {
-        u16::from_le_bytes([$buf[$offset], $buf[$offset + 1]])
+        read_buffer_offset!(@le $buf, $offset, u16, 2)
     };
     ($buf:expr, $offset:expr, i32) => {
-        i32::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-        ])
+        read_buffer_offset!(@le $buf, $offset, i32, 4)
     };
     ($buf:expr, $offset:expr, u32) => {
-        u32::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-        ])
+        read_buffer_offset!(@le $buf, $offset, u32, 4)
     };
     ($buf:expr, $offset:expr, i64) => {
-        i64::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-            $buf[$offset + 4],
-            $buf[$offset + 5],
-            $buf[$offset + 6],
-            $buf[$offset + 7],
-        ])
+        read_buffer_offset!(@le $buf, $offset, i64, 8)
     };
     ($buf:expr, $offset:expr, u64) => {
-        u64::from_le_bytes([
-            $buf[$offset],
-            $buf[$offset + 1],
-            $buf[$offset + 2],
-            $buf[$offset + 3],
-            $buf[$offset + 4],
-            $buf[$offset + 5],
-            $buf[$offset + 6],
-            $buf[$offset + 7],
-        ])
+        read_buffer_offset!(@le $buf, $offset, u64, 8)
+    };
+    (@le $buf:expr, $offset:expr, $ty:ty, $len:expr) => {
+        match $buf.get($offset..$offset + $len) {
+            Some(bytes) => {
+                let mut array = [0; $len];
+                array.copy_from_slice(bytes);
+                Ok(<$ty>::from_le_bytes(array))
+            }
+            None => Err(Error::InvalidResponse),
+        }
     };
 }
 
+/// Reports a compressed payload ending before the size it announced like any other truncated
+/// response, rather than as an IO error.
+#[cfg(feature = "codec")]
+fn decompression_error(err: std::io::Error) -> Error {
+    match err.kind() {
+        std::io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+        _ => Error::Io(err),
+    }
+}
+
+/// Copies the payload starting at an offset of a datagram, failing with
+/// [`Error::InvalidResponse`] if the datagram is too short.
+#[cfg(feature = "codec")]
+fn payload_from(data: &[u8], offset: usize) -> Result<Vec<u8>> {
+    data.get(offset..)
+        .map(Vec::from)
+        .ok_or(Error::InvalidResponse)
+}
+
+/// The payload of a response's first packet starting at `offset`, without the single packet
+/// header it carries. Source servers normally repeat that header in the first fragment of a
+/// multi-packet response, and GoldSource ones always do (it's how their layout is detected),
+/// but it's only skipped if actually there so a server leaving it out loses no payload bytes.
+#[cfg(feature = "codec")]
+fn first_payload(data: &[u8], offset: usize) -> Result<Vec<u8>> {
+    match data.get(offset..offset + 4) {
+        Some(header) if header == [0xff; 4] => payload_from(data, offset + 4),
+        _ => payload_from(data, offset),
+    }
+}
+
+/// Whether the first fragment of a multi-packet response uses the GoldSource layout, told
+/// apart by its payload's single packet header right after the packed number and total, or the
+/// BZip2 stream header of a compressed one. In the Source layout those bytes hold the fragment
+/// number and switching size, which can never be all 0xFF nor spell out a switching size over
+/// 26000 like a stream header does.
+#[cfg(feature = "codec")]
+fn is_gold_source_fragment(data: &[u8]) -> bool {
+    match data.get(OFS_MP_GS_PAYLOAD..OFS_MP_GS_PAYLOAD + 4) {
+        Some([0xff, 0xff, 0xff, 0xff]) => true,
+        Some([b'B', b'Z', b'h', level]) => is_compressed(data) && level.is_ascii_digit(),
+        _ => false,
+    }
+}
+
+/// Whether a multi-packet response was BZip2 compressed before being split, flagged by the high
+/// bit of its ID in both layouts.
+#[cfg(feature = "codec")]
+fn is_compressed(data: &[u8]) -> bool {
+    matches!(read_buffer_offset!(data, OFS_MP_ID, i32), Ok(id) if id as u32 & 0x80000000 != 0)
+}
+
+/// How the payload of a multi-packet response was compressed before being split.
+#[cfg(feature = "codec")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    /// Source BZip2, with the decompressed size and CRC32 checksum in the first fragment
+    Bz2 {
+        size: u32,
+        checksum: u32,
+    },
+    /// GoldSource BZip2, a bare stream without size nor checksum
+    Bz2Stream,
+}
+
+#[cfg(feature = "codec")]
 #[derive(Debug)]
 struct PacketFragment {
     number: u8,
     payload: Vec<u8>,
 }
 
+#[cfg(feature = "codec")]
+enum Response {
+    Complete(Vec<u8>),
+    Partial(Reassembly),
+}
+
+#[cfg(feature = "codec")]
+impl Response {
+    /// Handles the first datagram of a response, which either carries the whole payload or
+    /// starts the reassembly of a multi-packet response.
+    fn start(data: Vec<u8>, config: &A2SConfig) -> Result<Self> {
+        // Servers mid-restart may answer with an empty datagram before their query handler is up
+        if data.is_empty() {
+            return Err(Error::EmptyResponse);
+        }
+
+        let header = read_buffer_offset!(&data, OFS_HEADER, i32)?;
+
+        if header == SINGLE_PACKET {
+            Ok(Self::Complete(payload_from(&data, OFS_SP_PAYLOAD)?))
+        } else if header == MULTI_PACKET && is_gold_source_fragment(&data) {
+            // ID - long (4 bytes)
+            // Packet - byte (1 byte), number in the upper nibble and total in the lower one
+
+            let id = read_buffer_offset!(&data, OFS_MP_ID, i32)?;
+            let packet = read_buffer_offset!(&data, OFS_MP_GS_PACKET, u8)?;
+            let total_packets = usize::from(packet & 0x0f);
+
+            let compression = if is_compressed(&data) {
+                Compression::Bz2Stream
+            } else {
+                Compression::None
+            };
+
+            if total_packets > config.max_packets {
+                return Err(Error::InvalidResponse);
+            }
+
+            let mut packets: Vec<PacketFragment> = Vec::with_capacity(0);
+            packets.try_reserve(total_packets)?;
+
+            let mut reassembly = Reassembly {
+                id,
+                total_packets,
+                // GoldSource doesn't send its switching size, but keeps fragments within 1400
+                switching_size: config.max_size,
+                max_bz2_size: config.max_bz2_size,
+                verify_checksum: config.verify_checksum,
+                gold_source: true,
+                compression,
+                packets,
+                seen: [0; 4],
+            };
+            reassembly.add(PacketFragment {
+                number: packet >> 4,
+                payload: payload_from(&data, OFS_MP_GS_PAYLOAD)?,
+            })?;
+
+            Ok(Self::Partial(reassembly))
+        } else if header == MULTI_PACKET {
+            // ID - long (4 bytes)
+            // Total - byte (1 byte)
+            // Number - byte (1 byte)
+            // Size - short (2 bytes)
+
+            let id = read_buffer_offset!(&data, OFS_MP_ID, i32)?;
+            let total_packets: usize = read_buffer_offset!(&data, OFS_MP_SS_TOTAL, u8)?.into();
+            let switching_size: usize = read_buffer_offset!(&data, OFS_MP_SS_SIZE, u16)?.into();
+
+            // Sanity check
+            if (switching_size > config.max_size) || (total_packets > config.max_packets) {
+                return Err(Error::InvalidResponse);
+            }
+
+            let compressed = is_compressed(&data);
+            let (fragment, bz2) = source_fragment(&data, compressed)?;
+            // Only the first fragment carries the size and checksum, they're filled in once it
+            // arrives, which completing the response requires
+            let compression = match bz2 {
+                Some(compression) => compression,
+                None if compressed => Compression::Bz2 {
+                    size: 0,
+                    checksum: 0,
+                },
+                None => Compression::None,
+            };
+
+            let mut packets: Vec<PacketFragment> = Vec::with_capacity(0);
+            packets.try_reserve(total_packets)?;
+
+            let mut reassembly = Reassembly {
+                id,
+                total_packets,
+                switching_size,
+                max_bz2_size: config.max_bz2_size,
+                verify_checksum: config.verify_checksum,
+                gold_source: false,
+                compression,
+                packets,
+                seen: [0; 4],
+            };
+            reassembly.add(fragment)?;
+
+            Ok(Self::Partial(reassembly))
+        } else {
+            Err(Error::InvalidResponse)
+        }
+    }
+}
+
+/// Reads a fragment in the Source layout. The first fragment of a compressed response also
+/// carries the decompressed size and checksum, returned along with it.
+#[cfg(feature = "codec")]
+fn source_fragment(data: &[u8], compressed: bool) -> Result<(PacketFragment, Option<Compression>)> {
+    let number = read_buffer_offset!(data, OFS_MP_SS_NUMBER, u8)?;
+
+    if compressed && number == 0 {
+        let compression = Compression::Bz2 {
+            size: read_buffer_offset!(data, OFS_MP_SS_BZ2_SIZE, u32)?,
+            checksum: read_buffer_offset!(data, OFS_MP_SS_BZ2_CRC, u32)?,
+        };
+        let payload = payload_from(data, OFS_MP_SS_PAYLOAD_BZ2)?;
+        Ok((PacketFragment { number, payload }, Some(compression)))
+    } else {
+        let payload = payload_from(data, OFS_MP_SS_PAYLOAD)?;
+        Ok((PacketFragment { number, payload }, None))
+    }
+}
+
+/// A whole response as received by [`A2SClient::send_counted`].
+#[cfg(feature = "std")]
+pub(crate) struct Received {
+    /// Reassembled payload, without the packet header
+    pub(crate) payload: Vec<u8>,
+    /// Number of datagrams the response arrived in
+    pub(crate) packets: usize,
+    /// Size of those datagrams, headers included
+    pub(crate) bytes: usize,
+    /// Size of the largest of those datagrams
+    pub(crate) largest: usize,
+}
+
+/// State of a multi-packet response whose fragments are still being received.
+#[cfg(feature = "codec")]
+#[derive(Debug)]
+struct Reassembly {
+    id: i32,
+    total_packets: usize,
+    switching_size: usize,
+    max_bz2_size: u32,
+    verify_checksum: bool,
+    /// Whether fragments use the GoldSource layout
+    gold_source: bool,
+    compression: Compression,
+    packets: Vec<PacketFragment>,
+    /// Bitset of the fragment numbers received so far
+    seen: [u64; 4],
+}
+
+#[cfg(feature = "codec")]
+impl Reassembly {
+    fn push(&mut self, mut data: Vec<u8>, config: &A2SConfig) -> Result<()> {
+        // Stray empty datagrams (keepalives, middlebox artifacts) carry no fragment
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let packet_id = read_buffer_offset!(&data, OFS_MP_ID, i32)?;
+
+        if packet_id != self.id {
+            // Only the ID of the first fragment ties a response to its query, so a fragment of
+            // another one can't be told to be the real reply and is never taken over it
+            if !config.resync_fragments {
+                return Err(Error::MismatchID);
+            }
+            return Ok(());
+        }
+
+        // Fragments are never meant to exceed the switching size
+        data.truncate(self.switching_size);
+
+        let fragment = if self.gold_source {
+            PacketFragment {
+                number: read_buffer_offset!(&data, OFS_MP_GS_PACKET, u8)? >> 4,
+                payload: payload_from(&data, OFS_MP_GS_PAYLOAD)?,
+            }
+        } else {
+            let compressed = self.compression != Compression::None;
+            let (fragment, bz2) = source_fragment(&data, compressed)?;
+            if let Some(compression) = bz2 {
+                self.compression = compression;
+            }
+
+            fragment
+        };
+
+        self.add(fragment)
+    }
+
+    /// Records a fragment, rejecting numbers outside `0..total_packets` as they could never
+    /// complete the response.
+    fn add(&mut self, fragment: PacketFragment) -> Result<()> {
+        if usize::from(fragment.number) >= self.total_packets {
+            return Err(Error::InvalidResponse);
+        }
+
+        // Servers may retransmit fragments, only the first copy counts
+        if !self.has_seen(fragment.number) {
+            self.seen[usize::from(fragment.number / 64)] |= 1 << (fragment.number % 64);
+            self.packets.push(fragment);
+        }
+
+        Ok(())
+    }
+
+    /// Turns a timeout while waiting for fragments into [`Error::IncompleteResponse`], so that
+    /// a server that stopped sending midway can be told apart from one that never answered.
+    fn timed_out(&self, err: Error) -> Error {
+        if err.is_timeout() {
+            Error::IncompleteResponse {
+                received: self.packets.len(),
+                expected: self.total_packets,
+            }
+        } else {
+            err
+        }
+    }
+
+    /// Whether every fragment number in `0..total_packets` has been received. Duplicates and
+    /// out of range numbers never count, so a retransmitted fragment can't stand in for a
+    /// missing one.
+    fn is_complete(&self) -> bool {
+        (0..self.total_packets).all(|number| self.has_seen(number as u8))
+    }
+
+    fn has_seen(&self, number: u8) -> bool {
+        self.seen[usize::from(number / 64)] & (1 << (number % 64)) != 0
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>> {
+        self.packets.sort_by_key(|p| p.number);
+
+        // Reserve exactly what was received rather than a worst case per fragment. Each fragment
+        // is freed once copied, so peak memory is about twice the response's size. The sum is
+        // checked rather than left to wrap, as `usize` may only be 32 bits wide.
+        let size = self
+            .packets
+            .iter()
+            .try_fold(0usize, |size, p| size.checked_add(p.payload.len()))
+            .ok_or(Error::InvalidResponse)?;
+        let mut aggregation = Vec::with_capacity(0);
+        aggregation.try_reserve(size)?;
+
+        for p in self.packets {
+            // The first fragment is told by its number rather than arrival, as fragments may be
+            // reordered. Compressed responses carry the single packet header inside the
+            // compressed data, it's skipped once decompressed.
+            let header = p.number == 0
+                && self.compression == Compression::None
+                && p.payload.starts_with(&[0xff; 4]);
+            aggregation.extend(&p.payload[if header { 4 } else { 0 }..]);
+        }
+
+        match self.compression {
+            Compression::None => Ok(aggregation),
+            Compression::Bz2 { size, checksum } => {
+                if size > self.max_bz2_size {
+                    return Err(Error::InvalidBz2Size);
+                }
+
+                let size = usize::try_from(size).map_err(|_| Error::InvalidBz2Size)?;
+
+                let mut decompressed = Vec::with_capacity(0);
+                decompressed.try_reserve(size)?;
+                decompressed.resize(size, 0);
+
+                BzDecoder::new(aggregation.deref())
+                    .read_exact(&mut decompressed)
+                    .map_err(decompression_error)?;
+
+                // Skipped entirely when disabled, the checksum is the costly part for big
+                // responses
+                if self.verify_checksum && crc32::checksum_ieee(&decompressed) != checksum {
+                    return Err(Error::CheckSumMismatch);
+                }
+
+                first_payload(&decompressed, OFS_HEADER)
+            }
+            Compression::Bz2Stream => {
+                // Without a size to check upfront, decompression stops one byte past the limit
+                let mut decompressed = Vec::new();
+                BzDecoder::new(aggregation.deref())
+                    .take(u64::from(self.max_bz2_size) + 1)
+                    .read_to_end(&mut decompressed)
+                    .map_err(decompression_error)?;
+                if decompressed.len() > self.max_bz2_size as usize {
+                    return Err(Error::InvalidBz2Size);
+                }
+
+                first_payload(&decompressed, OFS_HEADER)
+            }
+        }
+    }
+}
+
+/// Limits and knobs of an [`A2SClient`], with the defaults [`A2SClient::new`] uses.
+#[cfg(feature = "codec")]
+#[derive(Debug, Clone)]
+pub struct A2SConfig {
+    /// Largest switching size (size of each fragment) accepted for multi-packet responses.
+    /// Single packet responses are never truncated to it. Defaults to 1400.
+    pub max_size: usize,
+
+    /// Most fragments a multi-packet response may be split into. Defaults to 32.
+    ///
+    /// Together with `max_size` this bounds the size of a reassembled response, 44.8 KB with the
+    /// defaults. Memory is only allocated for the fragments actually received, so small
+    /// responses don't pay for the limit.
+    pub max_packets: usize,
+
+    /// Largest decompressed size accepted for BZip2 compressed responses. Defaults to 1 MiB.
+    pub max_bz2_size: u32,
+
+    /// Whether to verify the CRC32 checksum of BZip2 compressed responses. When off, the
+    /// checksum isn't computed at all, which saves CPU when scanning many servers sending large
+    /// compressed responses. GoldSource compressed responses carry no checksum, only the BZip2
+    /// stream's own. Defaults to true.
+    pub verify_checksum: bool,
+
+    /// How long to wait on each socket operation. Defaults to 5 seconds in the sync build and
+    /// 15 seconds in the async build.
+    pub timeout: Duration,
+
+    /// Local port the socket is bound to, 0 for an ephemeral port. Defaults to 0.
+    pub bind_port: u16,
+
+    /// Application ID used to decide whether player responses carry The Ship fields.
+    /// Defaults to 0.
+    pub app_id: u16,
+
+    /// Number of times a request is retransmitted after the server fails to answer in time.
+    /// Defaults to 0 (no retries).
+    pub retries: usize,
+
+    /// Delay to wait before each retransmission. Defaults to no delay.
+    pub retry_delay: Duration,
+
+    /// Randomizes the retry delay by up to this factor in either direction (clamped to
+    /// `0.0..=1.0`), so that a scanner retrying many servers at once spreads its retransmissions
+    /// out instead of sending them in bursts. Defaults to 0.0 (no jitter).
+    pub retry_jitter: f32,
+
+    /// Whether to parse The Ship fields of info and player responses for servers reporting app
+    /// ID 2400. Info responses of a protocol other than The Ship's are only parsed with them if
+    /// their layout matches, see [`TheShipDetection`](crate::info::TheShipDetection). Turn off if
+    /// you never query The Ship, so a server or proxy misreporting that app ID can't throw off
+    /// parsing. Defaults to true.
+    pub parse_the_ship: bool,
+
+    /// Whether to reject info responses whose player counts contradict each other, as listed
+    /// in [`Info::is_consistent`](crate::info::Info::is_consistent), with
+    /// [`Error::InvalidResponse`]. Meant for scanners filtering out fake servers, it's off by
+    /// default so that quirky but genuine servers still parse. Defaults to false.
+    pub reject_inconsistent: bool,
+
+    /// Whether to retry reading the EDF fields of info responses in the other orders some mods
+    /// write them in, listed in [`EdfOrder`](crate::info::EdfOrder), when the standard order
+    /// leaves parse warnings or unread bytes. The first order reading them cleanly is kept and
+    /// recorded in [`Info::edf_order`](crate::info::Info::edf_order), otherwise the standard
+    /// parse is. A heuristic for nonconforming servers, off by default as a response parsing
+    /// cleanly in the wrong order would be misread. Defaults to false.
+    pub lenient_edf: bool,
+
+    /// Whether responses that fail to parse are reported as [`Error::InvalidResponseWithDump`],
+    /// carrying their first bytes for bug reports, rather than as the error the parser met.
+    /// Defaults to false.
+    pub dump_invalid_responses: bool,
+
+    /// Most receive buffers the client keeps for reuse once a query is done with them, so that
+    /// queries don't each allocate one large enough for any datagram (64 KiB). Responses are
+    /// copied out of them at their actual size. The sync build uses one buffer per query in
+    /// flight, the async one one per socket. 0 allocates a buffer every time. Defaults to 4.
+    pub buffer_pool_size: usize,
+
+    /// Whether the ping measured by [`A2SClient::info_and_ping`] includes the challenge round
+    /// trip of servers requiring one, i.e. covers the whole query rather than just the request
+    /// that returned the info. Defaults to false.
+    pub ping_includes_challenge: bool,
+
+    /// Whether [`A2SClient::info_with_meta`] keeps the payload the info was parsed from in
+    /// [`InfoMeta::payload`](crate::info::InfoMeta::payload), to cache it as received. Defaults
+    /// to false.
+    pub keep_payload: bool,
+
+    /// Header byte of challenge responses. Some Source forks use another byte than the standard
+    /// one, which must not clash with the header of the response being queried. Defaults to
+    /// `b'A'`.
+    pub challenge_header: u8,
+
+    /// How players and rules queries obtain a challenge. Defaults to
+    /// [`ChallengeMode::OnDemand`].
+    pub challenge_mode: ChallengeMode,
+
+    /// Size of the socket's receive buffer, `None` to keep the OS default. The OS may cap it
+    /// (`net.core.rmem_max` on Linux). Defaults to `None`.
+    pub recv_buffer_size: Option<usize>,
+
+    /// How long to wait for each fragment after the first of a multi-packet response, `None`
+    /// to wait as long as for the first one (`timeout`). A shorter wait fails fast against
+    /// servers that start a response but never finish it. Defaults to `None`.
+    pub fragment_timeout: Option<Duration>,
+
+    /// Longest a whole query may take, retries, challenge handshake and fragments included,
+    /// `None` for no bound beyond each operation's `timeout`. Once it's exceeded the query fails
+    /// with [`Error::ErrTimeout`], however many retries are left. Defaults to `None`.
+    pub max_total_duration: Option<Duration>,
+
+    /// Whether to send each request from a fresh socket bound to an ephemeral port instead of
+    /// the client's, so that stateful middleboxes (NAT, conntrack) pinning state on the source
+    /// port see every request as a new flow, and a scanner's NAT table entries expire one by one
+    /// rather than piling up on a single port. Such requests ignore `bind_port` and
+    /// [`A2SClient::connect`]. Defaults to false.
+    ///
+    /// Binding costs a few system calls per request, plus spawning a receive task in the async
+    /// build, and each socket takes one of the host's ephemeral ports until the request
+    /// completes. Only worth it for scans where middleboxes are the bottleneck.
+    pub randomize_source_port: bool,
+
+    /// Whether a fragment of another multi-packet response than the one being received, such as
+    /// a late one from an earlier query, is discarded rather than failing the query with
+    /// [`Error::MismatchID`]. The query then keeps waiting for the rest of its own response
+    /// within its timeout. The sync client also discards datagrams already queued on its socket
+    /// before sending each request, so a response that arrived after its query gave up is not
+    /// taken for the reply. Defaults to false.
+    pub resync_fragments: bool,
+
+    /// Which datagrams are taken as the server's reply. Defaults to [`SourceMatch::Strict`].
+    pub source_match: SourceMatch,
+
+    /// Where [`A2SClient::info_with_query_port_discovery`] looks for the query port of a server
+    /// known by its game address, in order. Defaults to [`QueryPort::DEFAULTS`]: the game port
+    /// itself, the port after it, then 27015.
+    pub query_ports: Vec<QueryPort>,
+
+    /// How long [`A2SClient::resolve`] reuses the address a name resolved to, `None` to resolve
+    /// on every call. Shorter TTLs are raised to [`MIN_DNS_CACHE_TTL`], so a misconfigured TTL
+    /// can't turn the cache into a lookup per query. Defaults to 60 seconds.
+    pub dns_cache_ttl: Option<Duration>,
+
+    /// Directory to write every response that fails to parse to, one file per failure named
+    /// after the time, query kind and server (`1700000000123-info-10.0.0.1_27015-0.bin`) and
+    /// holding the response's reassembled payload, for reporting malformed responses met in
+    /// the field. The directory is created if missing, and failing to write to it doesn't fail
+    /// the query. Defaults to `None`, never writing anything.
+    pub malformed_dump_dir: Option<PathBuf>,
+
+    /// Most queries awaiting a response at once on the client's socket, `None` for no limit.
+    /// Queries beyond it wait for a slot within their timeout, which keeps a client shared by
+    /// many tasks from overflowing the socket's receive buffer. Defaults to `None`.
+    #[cfg(feature = "async")]
+    pub max_concurrent: Option<usize>,
+}
+
+#[cfg(feature = "codec")]
+impl Default for A2SConfig {
+    fn default() -> Self {
+        A2SConfig {
+            max_size: 1400,
+            max_packets: 32,
+            max_bz2_size: 1024 * 1024,
+            verify_checksum: true,
+            #[cfg(not(feature = "async"))]
+            timeout: Duration::new(5, 0),
+            #[cfg(feature = "async")]
+            timeout: Duration::new(15, 0),
+            bind_port: 0,
+            app_id: 0,
+            retries: 0,
+            retry_delay: Duration::from_millis(0),
+            retry_jitter: 0.0,
+            parse_the_ship: true,
+            reject_inconsistent: false,
+            lenient_edf: false,
+            dump_invalid_responses: false,
+            buffer_pool_size: 4,
+            ping_includes_challenge: false,
+            keep_payload: false,
+            challenge_header: b'A',
+            challenge_mode: ChallengeMode::OnDemand,
+            recv_buffer_size: None,
+            fragment_timeout: None,
+            max_total_duration: None,
+            randomize_source_port: false,
+            resync_fragments: false,
+            source_match: SourceMatch::Strict,
+            query_ports: QueryPort::DEFAULTS.to_vec(),
+            dns_cache_ttl: Some(Duration::from_secs(60)),
+            malformed_dump_dir: None,
+            #[cfg(feature = "async")]
+            max_concurrent: None,
+        }
+    }
+}
+
+#[cfg(feature = "codec")]
+impl A2SConfig {
+    /// The wait for each fragment after the first, see [`A2SConfig::fragment_timeout`].
+    #[cfg(feature = "std")]
+    fn fragment_wait(&self) -> Duration {
+        self.fragment_timeout.unwrap_or(self.timeout)
+    }
+
+    /// Defaults for scanning many servers at once, where waiting on dead servers costs more
+    /// than occasionally missing a slow one. They differ from [`Default`] in:
+    ///
+    /// - `timeout`: 1 second
+    /// - `retries`: 2, so a lost datagram doesn't count the server as down
+    /// - `retry_jitter`: 0.25
+    /// - `recv_buffer_size`: 4 MiB, so bursts of responses aren't dropped
+    ///
+    /// The socket is bound to an ephemeral IPv4 port, as with the defaults.
+    pub fn scanner() -> Self {
+        A2SConfig {
+            timeout: Duration::from_secs(1),
+            retries: 2,
+            retry_jitter: 0.25,
+            recv_buffer_size: Some(4 * 1024 * 1024),
+            ..Default::default()
+        }
+    }
+}
+
+/// Shortest time [`A2SClient::resolve`] caches an address for, see [`A2SConfig::dns_cache_ttl`].
+#[cfg(feature = "codec")]
+pub const MIN_DNS_CACHE_TTL: Duration = Duration::from_secs(1);
+
+/// How queries that may require a challenge (players and rules) obtain one. A challenge already
+/// known, such as one passed to [`A2SClient::players_with_challenge`], is always sent.
+#[cfg(feature = "codec")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChallengeMode {
+    /// Probe for a challenge (`-1`) first. Costs a round trip against servers that don't
+    /// require one, but some old servers ignore requests lacking a challenge.
+    Always,
+
+    /// Send the request without a challenge, and only do the handshake if the server answers
+    /// with one, like info queries do.
+    #[default]
+    OnDemand,
+
+    /// Never do the handshake: the request is sent once without a challenge and whatever comes
+    /// back is parsed. A server that answers with a challenge instead fails the query with
+    /// [`Error::ChallengeRequired`], discarding that challenge. To do the handshake yourself,
+    /// query with the `*_with_challenge` methods, such as
+    /// [`A2SClient::players_with_challenge`].
+    Never,
+}
+
+/// Which datagrams are taken as a server's reply, see [`A2SConfig::source_match`]. Datagrams from
+/// any other source are discarded.
+#[cfg(feature = "codec")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourceMatch {
+    /// Datagrams from the IP and port the request was sent to.
+    #[default]
+    Strict,
+
+    /// Datagrams from the IP the request was sent to, on any port. Some firewalls, NATs and
+    /// relays rewrite the source port of replies, which strict matching discards, failing the
+    /// query with a timeout. Any other service on the server's host can then answer in its
+    /// place, and concurrent queries to several ports of a host may take each other's replies.
+    Lenient,
+}
+
+#[cfg(feature = "std")]
+impl SourceMatch {
+    /// Whether a datagram from `source` replies to a request sent to `destination`.
+    pub(crate) fn matches(self, destination: SocketAddr, source: SocketAddr) -> bool {
+        match self {
+            SourceMatch::Strict => source == destination,
+            SourceMatch::Lenient => source.ip() == destination.ip(),
+        }
+    }
+}
+
+/// Where a server's query port is looked for relative to its game port, see
+/// [`A2SConfig::query_ports`].
+#[cfg(feature = "codec")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryPort {
+    /// The game port moved by this many ports, as servers started with `-port` and a separate
+    /// query port often are.
+    Offset(i32),
+
+    /// This port whatever the game port, such as the default query port of a game.
+    Fixed(u16),
+}
+
+#[cfg(feature = "codec")]
+impl QueryPort {
+    /// The game port, the port after it, then 27015, the default port of Source servers.
+    pub const DEFAULTS: [QueryPort; 3] = [
+        QueryPort::Offset(0),
+        QueryPort::Offset(1),
+        QueryPort::Fixed(27015),
+    ];
+
+    /// The port to try for a server on `game_port`, `None` if an offset leaves the valid ports.
+    pub fn resolve(self, game_port: u16) -> Option<u16> {
+        match self {
+            QueryPort::Offset(offset) => u16::try_from(i64::from(game_port) + i64::from(offset))
+                .ok()
+                .filter(|&port| port != 0),
+            QueryPort::Fixed(port) => Some(port),
+        }
+    }
+}
+
+/// What a response's parser may need to know besides the response itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseContext {
+    /// Application ID the response is parsed for, deciding whether player responses carry The
+    /// Ship fields.
+    pub app_id: u16,
+
+    /// See [`A2SConfig::parse_the_ship`].
+    pub parse_the_ship: bool,
+
+    /// See [`A2SConfig::reject_inconsistent`].
+    pub reject_inconsistent: bool,
+
+    /// See [`A2SConfig::lenient_edf`].
+    pub lenient_edf: bool,
+
+    /// See [`A2SConfig::dump_invalid_responses`].
+    pub dump_invalid_responses: bool,
+}
+
+impl Default for ParseContext {
+    fn default() -> Self {
+        ParseContext {
+            app_id: 0,
+            parse_the_ship: true,
+            reject_inconsistent: false,
+            lenient_edf: false,
+            dump_invalid_responses: false,
+        }
+    }
+}
+
+/// A response that can be parsed from its payload, so that generic code can handle every query
+/// type the same way.
+pub trait ParseResponse: Sized {
+    /// Parses a complete payload, i.e. a reassembled response without its packet header.
+    fn parse(bytes: &[u8], ctx: &ParseContext) -> Result<Self>;
+}
+
+/// Bytes of a malformed payload kept in [`Error::InvalidResponseWithDump`].
+const MAX_DUMP_SIZE: usize = 64;
+
+/// Attaches the first bytes of `payload` to a failure to parse it, if `ctx` asks for it.
+fn with_dump<T>(payload: &[u8], result: Result<T>, ctx: &ParseContext) -> Result<T> {
+    match result {
+        Err(err) if ctx.dump_invalid_responses && err.is_invalid_response() => {
+            let len = payload.len().min(MAX_DUMP_SIZE);
+            Err(Error::InvalidResponseWithDump(payload[..len].to_vec()))
+        }
+        result => result,
+    }
+}
+
+/// The kind of query a response answers, used to pick its parser in [`parse_response`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    Info,
+    Players,
+    Rules,
+}
+
+/// A response parsed by [`parse_response`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedResponse {
+    Info(Box<Info>),
+    Players(Vec<Player>),
+    Rules(Vec<Rule>),
+}
+
+/// Parses a response datagram, header included, as the client would after receiving it.
+/// Multi-packet responses must fit in this single fragment. Player responses are parsed for
+/// `app_id`.
+///
+/// Meant as the entry point for fuzzing and for parsing captured traffic: no input makes it
+/// panic, malformed data only ever results in an error.
+#[cfg(feature = "codec")]
+pub fn parse_response(bytes: &[u8], kind: QueryKind, app_id: u16) -> Result<ParsedResponse> {
+    let payload = match Response::start(bytes.to_vec(), &A2SConfig::default())? {
+        Response::Complete(payload) => payload,
+        Response::Partial(reassembly) if reassembly.is_complete() => reassembly.finish()?,
+        Response::Partial(_) => return Err(Error::InvalidResponse),
+    };
+    let ctx = ParseContext {
+        app_id,
+        ..Default::default()
+    };
+
+    Ok(match kind {
+        QueryKind::Info => ParsedResponse::Info(Box::new(Info::parse(&payload, &ctx)?)),
+        QueryKind::Players => ParsedResponse::Players(Vec::parse(&payload, &ctx)?),
+        QueryKind::Rules => ParsedResponse::Rules(Vec::parse(&payload, &ctx)?),
+    })
+}
+
+/// Strips control characters, which Source games use as color codes, from a server or player
+/// name for display in plain text. With `color_codes`, Quake-style `^0` to `^9` color markers
+/// are stripped too.
+pub fn clean_name(name: &str, color_codes: bool) -> String {
+    let mut clean = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_control() {
+            continue;
+        }
+        if color_codes && c == '^' && chars.peek().is_some_and(char::is_ascii_digit) {
+            chars.next();
+            continue;
+        }
+        clean.push(c);
+    }
+
+    clean
+}
+
+/// The canonical form of an address, for keying caches such as [`ServerSession`]s or a
+/// [`ConnectedClientPool`]: IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) become plain IPv4 ones,
+/// and IPv6 flow info and scope IDs are dropped. Nothing is resolved.
+///
+/// [`ServerSession`]: crate::state::ServerSession
+/// [`ConnectedClientPool`]: crate::pool::ConnectedClientPool
+pub fn canonical_addr(addr: SocketAddr) -> SocketAddr {
+    match addr {
+        SocketAddr::V4(_) => addr,
+        SocketAddr::V6(v6) => match v6.ip().to_ipv4_mapped() {
+            Some(ip) => SocketAddr::new(ip.into(), v6.port()),
+            None => SocketAddr::new((*v6.ip()).into(), v6.port()),
+        },
+    }
+}
+
+/// Whether `ip` is a Steam FakeIP (in `169.254.0.0/16`), which Steam Datagram Relay (SDR) servers
+/// are reached at instead of their real address. IPv4-mapped IPv6 addresses are unmapped first.
+pub fn is_fake_ip(ip: IpAddr) -> bool {
+    match canonical_addr(SocketAddr::new(ip, 0)).ip() {
+        IpAddr::V4(ip) => ip.octets()[..2] == [169, 254],
+        IpAddr::V6(_) => false,
+    }
+}
+
+/// Resolves an address (e.g. `"host:27015"`) to the [`canonical_addr`] of the first address it
+/// resolves to, the one queries are sent to, so that a server reached by name and by IP shares
+/// cache entries.
+///
+/// Resolving a name is a DNS lookup, blocking the thread until the resolver answers, and
+/// nothing is cached: only the OS resolver may. Resolve once and keep the result, or use
+/// [`A2SClient::resolve`], rather than resolving before every query. Names resolving to several addresses
+/// (round-robin DNS) may also yield a different key on each call.
+#[cfg(all(feature = "std", not(feature = "async")))]
+pub fn resolve_addr<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr> {
+    first_addr(addr.to_socket_addrs()?)
+}
+
+/// Resolves an address (e.g. `"host:27015"`) to the [`canonical_addr`] of the first address it
+/// resolves to, the one queries are sent to, so that a server reached by name and by IP shares
+/// cache entries.
+///
+/// Resolving a name is a DNS lookup, run on a blocking thread until the resolver answers, and
+/// nothing is cached: only the OS resolver may. Resolve once and keep the result, or use
+/// [`A2SClient::resolve`], rather than resolving before every query. Names resolving to several addresses
+/// (round-robin DNS) may also yield a different key on each call.
+#[cfg(feature = "async")]
+pub async fn resolve_addr<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr> {
+    first_addr(lookup_host(addr).await?)
+}
+
+#[cfg(feature = "std")]
+fn first_addr(mut addrs: impl Iterator<Item = SocketAddr>) -> Result<SocketAddr> {
+    let addr = addrs.next().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "no addresses to send data to",
+        )
+    })?;
+
+    Ok(canonical_addr(addr))
+}
+
+/// Source of the time used to measure pings, replaceable to make them deterministic in tests.
+/// Timeouts and deadlines always follow the real time.
+#[cfg(feature = "std")]
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The real time, as told by [`Instant::now`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A client for querying servers. It is `Send + Sync`, so one client can be shared across
+/// threads or tasks.
+///
+/// The protocol has no transaction ID: single packet responses carry nothing tying them to a
+/// request, and the ID of multi-packet ones is chosen by the server. Responses are therefore
+/// matched by source address only, and datagrams from any other address are discarded.
+/// Multi-packet fragments must also carry the ID of the response's first fragment. In the async
+/// build, queries to the same server are serialized, so concurrent queries never swap replies.
+/// The sync build receives on the calling thread, so concurrent queries sharing a client may
+/// discard each other's replies and time out; use a client per thread instead.
+#[cfg(feature = "std")]
 pub struct A2SClient {
     #[cfg(not(feature = "async"))]
-    socket: UdpSocket,
+    socket: Arc<UdpSocket>,
     #[cfg(feature = "async")]
-    timeout: Duration,
-    max_size: usize,
-    app_id: u16,
+    router: Arc<Router>,
+    config: A2SConfig,
+    clock: Arc<dyn Clock>,
+    /// Set on the short-lived clients of logged queries only
+    recorder: Option<Arc<Recorder>>,
+    dns_cache: Arc<DnsCache>,
+    /// Set on the short-lived clients of [`with_request_id`](Self::with_request_id) only
+    request_id: Option<Arc<str>>,
+    size_collector: Option<Arc<dyn SizeCollector>>,
+    buffers: Arc<BufferPool>,
+    /// Slots of queries awaiting a response, see [`A2SConfig::max_concurrent`]
+    #[cfg(feature = "async")]
+    limiter: Option<Arc<Semaphore>>,
 }
 
+// Sharing a client is part of its API, so adding state that isn't thread-safe must fail to compile
+#[cfg(feature = "std")]
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<A2SClient>();
+};
+
 #[cfg(feature = "async")]
 macro_rules! future_timeout {
     ($timeout:expr, $future:expr) => {
@@ -115,334 +1126,1056 @@ macro_rules! future_timeout {
     };
 }
 
+#[cfg(feature = "std")]
 impl A2SClient {
     #[cfg(not(feature = "async"))]
     pub fn new() -> Result<A2SClient> {
-        let socket = UdpSocket::bind("0.0.0.0:0")?;
-        let timeout = Duration::new(5, 0);
+        Self::with_config(A2SConfig::default())
+    }
+
+    #[cfg(feature = "async")]
+    pub async fn new() -> Result<A2SClient> {
+        Self::with_config(A2SConfig::default()).await
+    }
+
+    /// Creates a client with [`A2SConfig::scanner`] defaults, for querying many servers.
+    #[cfg(not(feature = "async"))]
+    pub fn scanner() -> Result<A2SClient> {
+        Self::with_config(A2SConfig::scanner())
+    }
 
-        socket.set_read_timeout(Some(timeout))?;
-        socket.set_write_timeout(Some(timeout))?;
+    /// Creates a client with [`A2SConfig::scanner`] defaults, for querying many servers.
+    #[cfg(feature = "async")]
+    pub async fn scanner() -> Result<A2SClient> {
+        Self::with_config(A2SConfig::scanner()).await
+    }
 
+    #[cfg(not(feature = "async"))]
+    pub fn with_config(config: A2SConfig) -> Result<A2SClient> {
         Ok(A2SClient {
-            socket,
-            max_size: 1400,
-            app_id: 0,
+            socket: Arc::new(Self::bind(config.bind_port, &config)?),
+            config,
+            clock: Arc::new(SystemClock),
+            recorder: None,
+            dns_cache: Arc::default(),
+            request_id: None,
+            size_collector: None,
+            buffers: Arc::default(),
         })
     }
 
     #[cfg(feature = "async")]
-    pub async fn new() -> Result<A2SClient> {
+    pub async fn with_config(config: A2SConfig) -> Result<A2SClient> {
+        let buffers = Arc::<BufferPool>::default();
+        let socket = Self::bind(config.bind_port, &config).await?;
+
         Ok(A2SClient {
-            timeout: Duration::new(15, 0),
-            max_size: 1400,
-            app_id: 0,
+            router: Arc::new(Router::new(socket, buffers.get(config.buffer_pool_size))),
+            limiter: limiter(config.max_concurrent),
+            config,
+            clock: Arc::new(SystemClock),
+            recorder: None,
+            dns_cache: Arc::default(),
+            request_id: None,
+            size_collector: None,
+            buffers,
         })
     }
 
+    #[cfg(not(feature = "async"))]
+    fn bind(port: u16, config: &A2SConfig) -> Result<UdpSocket> {
+        let socket = UdpSocket::bind(("0.0.0.0", port)).map_err(|err| match err.kind() {
+            std::io::ErrorKind::AddrInUse => Error::AddrInUse(port),
+            _ => Error::Io(err),
+        })?;
+
+        socket.set_read_timeout(Some(config.timeout))?;
+        socket.set_write_timeout(Some(config.timeout))?;
+        if let Some(size) = config.recv_buffer_size {
+            SockRef::from(&socket).set_recv_buffer_size(size)?;
+        }
+
+        Ok(socket)
+    }
+
+    #[cfg(feature = "async")]
+    async fn bind(port: u16, config: &A2SConfig) -> Result<UdpSocket> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))
+            .await
+            .map_err(|err| match err.kind() {
+                std::io::ErrorKind::AddrInUse => Error::AddrInUse(port),
+                _ => Error::Io(err),
+            })?;
+
+        if let Some(size) = config.recv_buffer_size {
+            SockRef::from(&socket).set_recv_buffer_size(size)?;
+        }
+
+        Ok(socket)
+    }
+
+    /// Rebinds the client to a fixed source port, for firewalls that only accept queries from a
+    /// specific port. 0 binds an ephemeral port, like [`new`](Self::new) does.
+    #[cfg(not(feature = "async"))]
+    pub fn bind_port(&mut self, port: u16) -> Result<&mut Self> {
+        self.socket = Arc::new(Self::bind(port, &self.config)?);
+        self.config.bind_port = port;
+        Ok(self)
+    }
+
+    /// Rebinds the client to a fixed source port, for firewalls that only accept queries from a
+    /// specific port. 0 binds an ephemeral port, like [`new`](Self::new) does.
+    #[cfg(feature = "async")]
+    pub async fn bind_port(&mut self, port: u16) -> Result<&mut Self> {
+        self.router = Arc::new(Router::new(
+            Self::bind(port, &self.config).await?,
+            self.buffers.get(self.config.buffer_pool_size),
+        ));
+        self.config.bind_port = port;
+        Ok(self)
+    }
+
+    /// Replaces the socket with a fresh one bound the same way, to recover from socket errors
+    /// that won't go away (e.g. its interface went down) without recreating the client. A
+    /// [`connect`](Self::connect)ed client has to be connected again.
+    #[cfg(not(feature = "async"))]
+    pub fn reconnect(&mut self) -> Result<()> {
+        self.socket = Arc::new(Self::bind(self.config.bind_port, &self.config)?);
+        Ok(())
+    }
+
+    /// Replaces the socket with a fresh one bound the same way, to recover from socket errors
+    /// that won't go away (e.g. its interface went down) without recreating the client. A
+    /// [`connect`](Self::connect)ed client has to be connected again. Queries still in flight
+    /// fail.
+    #[cfg(feature = "async")]
+    pub async fn reconnect(&mut self) -> Result<()> {
+        self.router = Arc::new(Router::new(
+            Self::bind(self.config.bind_port, &self.config).await?,
+            self.buffers.get(self.config.buffer_pool_size),
+        ));
+        Ok(())
+    }
+
+    /// Stops the task receiving datagrams for the client's queries and waits for it to finish,
+    /// so that its socket is closed once this returns. Dropping the client stops the task as
+    /// well, without waiting. Clients sharing the socket, such as those of
+    /// [`with_request_id`](Self::with_request_id), keep the task running until the last of them
+    /// is shut down or dropped.
+    #[cfg(feature = "async")]
+    pub async fn shutdown(self) {
+        if let Ok(router) = Arc::try_unwrap(self.router) {
+            router.shutdown().await;
+        }
+    }
+
+    /// Connects the socket to `addr`, so that the OS drops datagrams from anyone else. Queries
+    /// to other servers time out afterwards, use one client per server.
+    ///
+    /// Connected sockets are also the only ones most platforms relay ICMP port unreachable
+    /// messages to, reporting a server whose port is closed as [`Error::ConnectionRefused`]
+    /// rather than letting the query time out.
+    #[cfg(not(feature = "async"))]
+    pub fn connect(&self, addr: SocketAddr) -> Result<()> {
+        Ok(self.socket.connect(addr)?)
+    }
+
+    /// Connects the socket to `addr`, so that the OS drops datagrams from anyone else. Queries
+    /// to other servers time out afterwards, use one client per server.
+    ///
+    /// Connected sockets are also the only ones most platforms relay ICMP port unreachable
+    /// messages to, reporting a server whose port is closed as [`Error::ConnectionRefused`]
+    /// rather than letting the query time out.
+    #[cfg(feature = "async")]
+    pub async fn connect(&self, addr: SocketAddr) -> Result<()> {
+        Ok(self.router.socket().connect(addr).await?)
+    }
+
+    /// The local address the socket is bound to, e.g. to allow its source port in a firewall.
+    #[cfg(not(feature = "async"))]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// The local address the socket is bound to, e.g. to allow its source port in a firewall.
+    #[cfg(feature = "async")]
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.router.socket().local_addr()?)
+    }
+
+    /// The current configuration, including changes made through the setters.
+    pub fn config(&self) -> &A2SConfig {
+        &self.config
+    }
+
     pub fn max_size(&mut self, size: usize) -> &mut Self {
-        self.max_size = size;
+        self.config.max_size = size;
         self
     }
 
     pub fn app_id(&mut self, app_id: u16) -> &mut Self {
-        self.app_id = app_id;
+        self.config.app_id = app_id;
         self
     }
 
-    #[cfg(feature = "async")]
-    async fn send<A: ToSocketAddrs>(&self, payload: &[u8], addr: A) -> Result<Vec<u8>> {
-        let socket = UdpSocket::bind("0.0.0.0:0").await?;
-        future_timeout!(self.timeout, socket.send_to(payload, addr))?;
+    /// See [`A2SConfig::verify_checksum`].
+    pub fn verify_checksum(&mut self, verify: bool) -> &mut Self {
+        self.config.verify_checksum = verify;
+        self
+    }
 
-        let mut data = vec![0; self.max_size];
+    /// See [`A2SConfig::retries`].
+    pub fn retries(&mut self, retries: usize) -> &mut Self {
+        self.config.retries = retries;
+        self
+    }
 
-        let read = future_timeout!(self.timeout, socket.recv(&mut data))?;
-        data.truncate(read);
+    /// See [`A2SConfig::retry_delay`].
+    pub fn retry_delay(&mut self, delay: Duration) -> &mut Self {
+        self.config.retry_delay = delay;
+        self
+    }
 
-        let header = read_buffer_offset!(&data, OFS_HEADER, i32);
+    /// See [`A2SConfig::retry_jitter`].
+    pub fn retry_jitter(&mut self, jitter: f32) -> &mut Self {
+        self.config.retry_jitter = jitter;
+        self
+    }
 
-        if header == SINGLE_PACKET {
-            Ok(data[OFS_SP_PAYLOAD..].to_vec())
-        } else if header == MULTI_PACKET {
-            // ID - long (4 bytes)
-            // Total - byte (1 byte)
-            // Number - byte (1 byte)
-            // Size - short (2 bytes)
+    /// See [`A2SConfig::parse_the_ship`].
+    pub fn parse_the_ship(&mut self, parse: bool) -> &mut Self {
+        self.config.parse_the_ship = parse;
+        self
+    }
 
-            let id = read_buffer_offset!(&data, OFS_MP_ID, i32);
-            let total_packets: usize = data[OFS_MP_SS_TOTAL].into();
-            let switching_size: usize = read_buffer_offset!(&data, OFS_MP_SS_SIZE, u16).into();
+    /// See [`A2SConfig::reject_inconsistent`].
+    pub fn reject_inconsistent(&mut self, reject: bool) -> &mut Self {
+        self.config.reject_inconsistent = reject;
+        self
+    }
 
-            // Sanity check
-            if (switching_size > self.max_size) || (total_packets > 32) {
-                return Err(Error::InvalidResponse);
-            }
+    /// See [`A2SConfig::lenient_edf`].
+    pub fn lenient_edf(&mut self, lenient: bool) -> &mut Self {
+        self.config.lenient_edf = lenient;
+        self
+    }
 
-            let mut packets: Vec<PacketFragment> = Vec::with_capacity(0);
-            packets.try_reserve(total_packets)?;
-            packets.push(PacketFragment {
-                number: data[OFS_MP_SS_NUMBER],
-                // The first packet seems to include a single packet header (0xFFFFFFFF) for some
-                // reason, so we'd rather skip that (hence +4)
-                payload: Vec::from(&data[OFS_MP_SS_PAYLOAD + 4..]),
-            });
+    /// See [`A2SConfig::dump_invalid_responses`].
+    pub fn dump_invalid_responses(&mut self, dump: bool) -> &mut Self {
+        self.config.dump_invalid_responses = dump;
+        self
+    }
 
-            loop {
-                let mut data: Vec<u8> = Vec::with_capacity(0);
-                data.try_reserve(switching_size)?;
-                data.resize(switching_size, 0);
+    /// See [`A2SConfig::buffer_pool_size`].
+    pub fn buffer_pool_size(&mut self, size: usize) -> &mut Self {
+        self.config.buffer_pool_size = size;
+        self
+    }
+
+    /// See [`A2SConfig::ping_includes_challenge`].
+    pub fn ping_includes_challenge(&mut self, include: bool) -> &mut Self {
+        self.config.ping_includes_challenge = include;
+        self
+    }
 
-                let read = future_timeout!(self.timeout, socket.recv(&mut data))?;
-                data.truncate(read);
+    /// See [`A2SConfig::keep_payload`].
+    pub fn keep_payload(&mut self, keep: bool) -> &mut Self {
+        self.config.keep_payload = keep;
+        self
+    }
 
-                if data.len() <= 9 {
-                    Err(Error::InvalidResponse)?
-                }
+    /// See [`A2SConfig::challenge_header`].
+    pub fn challenge_header(&mut self, header: u8) -> &mut Self {
+        self.config.challenge_header = header;
+        self
+    }
 
-                let packet_id = read_buffer_offset!(&data, OFS_MP_ID, i32);
+    /// See [`A2SConfig::challenge_mode`].
+    pub fn challenge_mode(&mut self, mode: ChallengeMode) -> &mut Self {
+        self.config.challenge_mode = mode;
+        self
+    }
 
-                if packet_id != id {
-                    return Err(Error::MismatchID);
-                }
+    /// See [`A2SConfig::resync_fragments`].
+    pub fn resync_fragments(&mut self, resync: bool) -> &mut Self {
+        self.config.resync_fragments = resync;
+        self
+    }
 
-                if id as u32 & 0x80000000 == 0 {
-                    // Uncompressed packet
-                    packets.push(PacketFragment {
-                        number: data[OFS_MP_SS_NUMBER],
-                        payload: Vec::from(&data[OFS_MP_SS_PAYLOAD..]),
-                    });
-                } else {
-                    // BZip2 compressed packet
-                    packets.push(PacketFragment {
-                        number: data[OFS_MP_SS_NUMBER],
-                        payload: Vec::from(&data[OFS_MP_SS_PAYLOAD_BZ2..]),
-                    });
-                }
+    /// See [`A2SConfig::source_match`].
+    pub fn source_match(&mut self, source_match: SourceMatch) -> &mut Self {
+        self.config.source_match = source_match;
+        self
+    }
 
-                if packets.len() == total_packets {
-                    break;
+    /// See [`A2SConfig::query_ports`].
+    pub fn query_ports(&mut self, query_ports: Vec<QueryPort>) -> &mut Self {
+        self.config.query_ports = query_ports;
+        self
+    }
+
+    /// See [`A2SConfig::malformed_dump_dir`].
+    pub fn malformed_dump_dir(&mut self, dir: Option<PathBuf>) -> &mut Self {
+        self.config.malformed_dump_dir = dir;
+        self
+    }
+
+    /// See [`A2SConfig::dns_cache_ttl`].
+    pub fn dns_cache_ttl(&mut self, ttl: Option<Duration>) -> &mut Self {
+        self.config.dns_cache_ttl = ttl;
+        self
+    }
+
+    /// See [`A2SConfig::fragment_timeout`].
+    pub fn fragment_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.config.fragment_timeout = Some(timeout);
+        self
+    }
+
+    /// See [`A2SConfig::max_total_duration`].
+    pub fn max_total_duration(&mut self, duration: Duration) -> &mut Self {
+        self.config.max_total_duration = Some(duration);
+        self
+    }
+
+    /// See [`A2SConfig::randomize_source_port`].
+    pub fn randomize_source_port(&mut self, randomize: bool) -> &mut Self {
+        self.config.randomize_source_port = randomize;
+        self
+    }
+
+    /// See [`A2SConfig::max_concurrent`]. Queries already waiting for a slot keep waiting under
+    /// the previous limit.
+    #[cfg(feature = "async")]
+    pub fn max_concurrent(&mut self, limit: usize) -> &mut Self {
+        self.config.max_concurrent = Some(limit);
+        self.limiter = limiter(self.config.max_concurrent);
+        self
+    }
+
+    /// The configuration with `max_size` replaced for a single query.
+    fn sized_config(&self, max_size: usize) -> A2SConfig {
+        A2SConfig {
+            max_size,
+            ..self.config.clone()
+        }
+    }
+
+    /// The context responses to this client are parsed in, for `app_id`.
+    pub(crate) fn parse_context(&self, app_id: u16) -> ParseContext {
+        ParseContext {
+            app_id,
+            parse_the_ship: self.config.parse_the_ship,
+            reject_inconsistent: self.config.reject_inconsistent,
+            lenient_edf: self.config.lenient_edf,
+            dump_invalid_responses: self.config.dump_invalid_responses,
+        }
+    }
+
+    /// Replaces the [`Clock`] pings are measured with.
+    pub fn clock<C: Clock + 'static>(&mut self, clock: C) -> &mut Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the [`SizeCollector`] the size of every response is recorded in, such as a
+    /// [`SizeHistogram`](histogram::SizeHistogram) shared by a fleet's clients. `None`, the
+    /// default, records nothing.
+    pub fn size_collector(&mut self, collector: Option<Arc<dyn SizeCollector>>) -> &mut Self {
+        self.size_collector = collector;
+        self
+    }
+
+    /// Reports the outcome of a query to the size collector and, with the `tracing` feature, as
+    /// an event.
+    fn report(&self, result: &Result<Received>) {
+        match result {
+            Ok(received) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    packets = received.packets,
+                    bytes = received.bytes,
+                    "response received"
+                );
+
+                if let Some(collector) = &self.size_collector {
+                    collector.record(ResponseSize {
+                        payload: received.payload.len(),
+                        packets: received.packets,
+                        largest_datagram: received.largest,
+                        bytes: received.bytes,
+                    });
                 }
             }
+            #[cfg(feature = "tracing")]
+            Err(err) => tracing::debug!(error = %err, "query failed"),
+            #[cfg(not(feature = "tracing"))]
+            Err(_) => {}
+        }
+    }
 
-            packets.sort_by_key(|p| p.number);
+    fn next_retry_delay(&self) -> Duration {
+        let jitter = self.config.retry_jitter;
+        if jitter.is_nan() || jitter <= 0.0 {
+            return self.config.retry_delay;
+        }
 
-            let mut aggregation = Vec::with_capacity(0);
-            aggregation.try_reserve(total_packets * self.max_size)?;
+        let factor = 1.0 + jitter.min(1.0) * (random_f32() * 2.0 - 1.0);
+        self.config.retry_delay.mul_f32(factor)
+    }
 
-            for p in packets {
-                aggregation.extend(p.payload);
-            }
+    /// The deadline of a query starting now: `deadline`, brought forward to the end of
+    /// [`A2SConfig::max_total_duration`] if that comes first.
+    fn query_deadline(&self, deadline: Option<Instant>) -> Option<Instant> {
+        let bound = self
+            .config
+            .max_total_duration
+            .and_then(|duration| Instant::now().checked_add(duration));
+
+        match (deadline, bound) {
+            (Some(deadline), Some(bound)) => Some(deadline.min(bound)),
+            (deadline, bound) => deadline.or(bound),
+        }
+    }
 
-            if id as u32 & 0x80000000 != 0 {
-                let decompressed_size = read_buffer_offset!(&data, OFS_MP_SS_BZ2_SIZE, u32);
-                let checksum = read_buffer_offset!(&data, OFS_MP_SS_BZ2_CRC, u32);
+    /// Time left to wait for the next operation, bounded by both the timeout and the deadline.
+    fn time_left(&self, deadline: Option<Instant>) -> Result<Duration> {
+        time_left_within(deadline, self.config.timeout)
+    }
 
-                if decompressed_size > (1024 * 1024) {
-                    return Err(Error::InvalidBz2Size);
+    #[cfg(feature = "async")]
+    async fn send<A: ToSocketAddrs>(&self, payload: &[u8], addr: A) -> Result<Vec<u8>> {
+        self.send_by(payload, addr, self.query_deadline(None)).await
+    }
+
+    #[cfg(feature = "async")]
+    async fn send_by<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u8>> {
+        Ok(self
+            .send_counted(payload, addr, deadline, &self.config)
+            .await?
+            .payload)
+    }
+
+    /// Sends like [`send_by`](Self::send_by) with the response limits of `config`, also
+    /// returning how many packets and bytes the response arrived in.
+    #[cfg(feature = "async")]
+    pub(crate) async fn send_counted<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        deadline: Option<Instant>,
+        config: &A2SConfig,
+    ) -> Result<Received> {
+        // The retries and outcome are part of the query's span too
+        let send = async {
+            let mut attempt = 0;
+            loop {
+                match self.send_once(payload, &addr, deadline, config).await {
+                    Err(err)
+                        if err.is_timeout()
+                            && attempt < config.retries
+                            && deadline.is_none_or(|deadline| Instant::now() < deadline) =>
+                    {
+                        attempt += 1;
+                        #[cfg(feature = "tracing")]
+                        tracing::debug!(attempt, "timed out, retrying");
+                        time::sleep(self.next_retry_delay()).await;
+                    }
+                    result => {
+                        self.report(&result);
+                        return result;
+                    }
                 }
+            }
+        };
 
-                let mut decompressed = Vec::with_capacity(0);
-                decompressed.try_reserve(decompressed_size as usize)?;
-                decompressed.resize(decompressed_size as usize, 0);
+        #[cfg(feature = "tracing")]
+        let send = tracing::Instrument::instrument(send, self.query_span());
+        send.await
+    }
 
-                BzDecoder::new(aggregation.deref()).read_exact(&mut decompressed)?;
+    #[cfg(not(feature = "async"))]
+    fn send<A: ToSocketAddrs>(&self, payload: &[u8], addr: A) -> Result<Vec<u8>> {
+        self.send_by(payload, addr, self.query_deadline(None))
+    }
 
-                if crc32::checksum_ieee(&decompressed) != checksum {
-                    return Err(Error::CheckSumMismatch);
-                }
+    #[cfg(not(feature = "async"))]
+    fn send_by<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u8>> {
+        Ok(self
+            .send_counted(payload, addr, deadline, &self.config)?
+            .payload)
+    }
 
-                Ok(decompressed)
-            } else {
-                Ok(aggregation)
+    /// Sends like [`send_by`](Self::send_by) with the response limits of `config`, also
+    /// returning how many packets and bytes the response arrived in.
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn send_counted<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        deadline: Option<Instant>,
+        config: &A2SConfig,
+    ) -> Result<Received> {
+        #[cfg(feature = "tracing")]
+        let _span = self.query_span().entered();
+        let mut attempt = 0;
+        loop {
+            match self.send_once(payload, &addr, deadline, config) {
+                Err(err)
+                    if err.is_timeout()
+                        && attempt < config.retries
+                        && deadline.is_none_or(|deadline| Instant::now() < deadline) =>
+                {
+                    attempt += 1;
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(attempt, "timed out, retrying");
+                    std::thread::sleep(self.next_retry_delay());
+                }
+                result => {
+                    self.report(&result);
+                    return result;
+                }
             }
-        } else {
-            Err(Error::InvalidResponse)
         }
     }
 
+    // The route registered for the destination is released when this future completes or is
+    // dropped. A cancelled query therefore stops receiving immediately, and any of its fragments
+    // arriving afterwards are discarded by the receive task rather than buffered for the next
+    // query. Queries to the same destination are serialized by the router, so the only way a late
+    // fragment can reach another query is if that query was sent to the same server before the
+    // fragment arrived.
     #[cfg(feature = "async")]
-    async fn do_challenge_request<A: ToSocketAddrs>(
+    async fn send_once<A: ToSocketAddrs>(
         &self,
+        payload: &[u8],
         addr: A,
-        header: &[u8],
-    ) -> Result<Vec<u8>> {
-        let packet = Vec::with_capacity(9);
-        let mut packet = Cursor::new(packet);
+        deadline: Option<Instant>,
+        config: &A2SConfig,
+    ) -> Result<Received> {
+        // Resolution is bounded by the timeout too, so a slow resolver can't stall the query
+        let addr = future_timeout!(self.time_left(deadline)?, lookup_host(addr))?
+            .next()
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "no addresses to send data to",
+                )
+            })?;
+
+        // Held until the response is complete, or the query fails
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(
+                future_timeout!(self.time_left(deadline)?, limiter.clone().acquire_owned())
+                    .expect("the semaphore is never closed"),
+            ),
+            None => None,
+        };
+
+        let router = if config.randomize_source_port {
+            Arc::new(Router::new(
+                Self::bind(0, config).await?,
+                self.buffers.get(config.buffer_pool_size),
+            ))
+        } else {
+            self.router.clone()
+        };
+        let mut route = router
+            .register(addr, config.source_match, config.max_packets)
+            .await;
+        future_timeout!(
+            self.time_left(deadline)?,
+            router.socket().send_to(payload, addr)
+        )
+        .map_err(Error::from_socket)?;
+        self.record(Direction::Sent, payload);
+
+        let data = future_timeout!(self.time_left(deadline)?, route.recv())?;
+        self.record(Direction::Received, &data);
+        let mut bytes = data.len();
+        let mut largest = bytes;
+
+        let mut reassembly = match Response::start(data, config)? {
+            Response::Complete(payload) => {
+                return Ok(Received {
+                    payload,
+                    packets: 1,
+                    bytes,
+                    largest: bytes,
+                })
+            }
+            Response::Partial(reassembly) => reassembly,
+        };
+
+        while !reassembly.is_complete() {
+            let timeout = time_left_within(deadline, config.fragment_wait())
+                .map_err(|err| reassembly.timed_out(err))?;
+            let data = match time::timeout(timeout, route.recv()).await {
+                Ok(data) => data?,
+                Err(_) => return Err(reassembly.timed_out(Error::ErrTimeout)),
+            };
+            self.record(Direction::Received, &data);
+            bytes += data.len();
+            largest = largest.max(data.len());
+            reassembly.push(data, config)?;
+        }
 
-        packet.write_all(header)?;
-        packet.write_i32::<LittleEndian>(-1)?;
+        let packets = reassembly.total_packets;
+        Ok(Received {
+            payload: reassembly.finish()?,
+            packets,
+            bytes,
+            largest,
+        })
+    }
 
-        let data = self.send(packet.get_ref(), &addr).await?;
-        let mut data = Cursor::new(data);
+    /// Sends a request that may require a challenge, appending `challenge` if set. The
+    /// handshake only happens if the server asks for a (fresh) challenge. Returns the response
+    /// along with the challenge it was accepted with, if one was required.
+    #[cfg(feature = "async")]
+    async fn do_optional_challenge_request<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        request: &[u8],
+        challenge: Option<i32>,
+    ) -> Result<(Vec<u8>, Option<i32>)> {
+        let deadline = self.query_deadline(None);
+        let packet = request_packet(request, challenge);
+
+        let data = self.send_by(&packet, &addr, deadline).await?;
+        let challenge = match read_challenge(&data, self.config.challenge_header) {
+            Some(challenge) => challenge,
+            None => return Ok((data, challenge)),
+        };
+
+        let (received, challenge) = self
+            .send_challenged(request, challenge, &addr, deadline)
+            .await?;
+
+        Ok((received.payload, Some(challenge)))
+    }
 
-        let header = data.read_u8()?;
-        if header != 'A' as u8 {
-            return Err(Error::InvalidResponse);
+    /// Sends `request` followed by the `challenge` the server handed out, returning the response
+    /// along with the challenge it was accepted with. Info queries are resent up to
+    /// [`INFO_CHALLENGE_RETRIES`] times if the server answers with something other than info, as
+    /// some echo the probe or repeat the challenge mid-handshake; with the fresh challenge, if
+    /// one was handed out. The last answer is returned either way, for the parser to reject.
+    #[cfg(feature = "async")]
+    pub(crate) async fn send_challenged<A: ToSocketAddrs>(
+        &self,
+        request: &[u8],
+        mut challenge: i32,
+        addr: A,
+        deadline: Option<Instant>,
+    ) -> Result<(Received, i32)> {
+        let mut packet = request_packet(request, Some(challenge));
+        let mut received = self
+            .send_counted(&packet, &addr, deadline, &self.config)
+            .await?;
+
+        let info = request.get(4) == Some(&INFO_REQUEST[4]);
+        for _ in 0..INFO_CHALLENGE_RETRIES {
+            if !info || is_info_response(&received.payload) {
+                break;
+            }
+            if let Some(fresh) = read_challenge(&received.payload, self.config.challenge_header) {
+                challenge = fresh;
+                packet.truncate(request.len());
+                packet.write_i32::<LittleEndian>(challenge)?;
+            }
+            received = self
+                .send_counted(&packet, &addr, deadline, &self.config)
+                .await?;
         }
 
-        let challenge = data.read_i32::<LittleEndian>()?;
+        Ok((received, challenge))
+    }
 
-        packet.set_position(5);
+    /// Sends a request that may require a challenge, with the response limits and
+    /// [`ChallengeMode`] of `config`. With `challenge` set, the handshake is skipped unless the
+    /// server rejects it with a fresh one. Returns the response along with the challenge it was
+    /// accepted with, if one was required.
+    #[cfg(feature = "async")]
+    async fn do_challenge_request<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        header: &[u8],
+        challenge: Option<i32>,
+        config: &A2SConfig,
+    ) -> Result<(Vec<u8>, Option<i32>)> {
+        let deadline = self.query_deadline(None);
+        let mut packet = challenge_packet(header, challenge, config);
+
+        let data = self
+            .send_counted(&packet, &addr, deadline, config)
+            .await?
+            .payload;
+        let challenge = match server_challenge(&data, challenge, config)? {
+            Some(challenge) => challenge,
+            None => return Ok((data, challenge)),
+        };
+
+        packet.truncate(header.len());
         packet.write_i32::<LittleEndian>(challenge)?;
-        let data = self.send(packet.get_ref(), &addr).await?;
+        let data = self
+            .send_counted(&packet, &addr, deadline, config)
+            .await?
+            .payload;
 
-        Ok(data)
+        Ok((data, Some(challenge)))
     }
 
     #[cfg(not(feature = "async"))]
-    fn send<A: ToSocketAddrs>(&self, payload: &[u8], addr: A) -> Result<Vec<u8>> {
-        self.socket.send_to(payload, addr)?;
-
-        let mut data = vec![0; self.max_size];
-
-        let read = self.socket.recv(&mut data)?;
-        data.truncate(read);
+    fn send_once<A: ToSocketAddrs>(
+        &self,
+        payload: &[u8],
+        addr: A,
+        deadline: Option<Instant>,
+        config: &A2SConfig,
+    ) -> Result<Received> {
+        let addr = first_addr(addr.to_socket_addrs()?)?;
 
-        let header = read_buffer_offset!(&data, OFS_HEADER, i32);
+        // A retry starting past the deadline isn't sent at all
+        self.time_left(deadline)?;
 
-        if header == SINGLE_PACKET {
-            Ok(data[OFS_SP_PAYLOAD..].to_vec())
-        } else if header == MULTI_PACKET {
-            // ID - long (4 bytes)
-            // Total - byte (1 byte)
-            // Number - byte (1 byte)
-            // Size - short (2 bytes)
+        // A single packet response may be larger than the switching size of multi-packet ones
+        let mut buf = self.buffers.get(config.buffer_pool_size);
 
-            let id = read_buffer_offset!(&data, OFS_MP_ID, i32);
-            let total_packets: usize = data[OFS_MP_SS_TOTAL].into();
-            let switching_size: usize = read_buffer_offset!(&data, OFS_MP_SS_SIZE, u16).into();
+        let fresh;
+        let socket = if config.randomize_source_port {
+            fresh = Self::bind(0, config)?;
+            &fresh
+        } else {
+            if config.resync_fragments {
+                Self::discard_queued(&self.socket, &mut buf)?;
+            }
+            &*self.socket
+        };
+        socket.send_to(payload, addr).map_err(Error::from_socket)?;
+        self.record(Direction::Sent, payload);
 
-            // Sanity check
-            if (switching_size > self.max_size) || (total_packets > 32) {
-                return Err(Error::InvalidResponse);
+        let read = self.recv(
+            socket,
+            &mut buf,
+            addr,
+            config.source_match,
+            deadline,
+            config.timeout,
+        )?;
+        let data = buf[..read].to_vec();
+        self.record(Direction::Received, &data);
+        let mut bytes = read;
+        let mut largest = read;
+
+        let mut reassembly = match Response::start(data, config)? {
+            Response::Complete(payload) => {
+                return Ok(Received {
+                    payload,
+                    packets: 1,
+                    bytes,
+                    largest: bytes,
+                })
             }
+            Response::Partial(reassembly) => reassembly,
+        };
 
-            let mut packets: Vec<PacketFragment> = Vec::with_capacity(0);
-            packets.try_reserve(total_packets)?;
-            packets.push(PacketFragment {
-                number: data[OFS_MP_SS_NUMBER],
-                // The first packet seems to include a single packet header (0xFFFFFFFF) for some
-                // reason, so we'd rather skip that (hence +4)
-                payload: Vec::from(&data[OFS_MP_SS_PAYLOAD + 4..]),
-            });
+        while !reassembly.is_complete() {
+            // Fragments of another response may be larger than this one's
+            let size = if config.resync_fragments {
+                buf.len()
+            } else {
+                reassembly.switching_size.min(buf.len())
+            };
+            let read = self
+                .recv(
+                    socket,
+                    &mut buf[..size],
+                    addr,
+                    config.source_match,
+                    deadline,
+                    config.fragment_wait(),
+                )
+                .map_err(|err| reassembly.timed_out(err))?;
+            let data = buf[..read].to_vec();
+            self.record(Direction::Received, &data);
+            bytes += read;
+            largest = largest.max(read);
+
+            reassembly.push(data, config)?;
+        }
 
-            loop {
-                let mut data: Vec<u8> = Vec::with_capacity(0);
-                data.try_reserve(switching_size)?;
-                data.resize(switching_size, 0);
+        let packets = reassembly.total_packets;
+        Ok(Received {
+            payload: reassembly.finish()?,
+            packets,
+            bytes,
+            largest,
+        })
+    }
 
-                let read = self.socket.recv(&mut data)?;
-                data.truncate(read);
+    #[cfg(not(feature = "async"))]
+    /// Receives the next datagram from `from` (as matched by `source_match`) on `socket` within
+    /// `timeout`, discarding any other, such as late replies of servers queried earlier.
+    fn recv(
+        &self,
+        socket: &UdpSocket,
+        buf: &mut [u8],
+        from: SocketAddr,
+        source_match: SourceMatch,
+        deadline: Option<Instant>,
+        timeout: Duration,
+    ) -> Result<usize> {
+        // Stray datagrams don't extend the wait, which stays bounded by the timeout
+        let wait_until = Instant::now() + timeout;
+        let deadline = deadline.map_or(wait_until, |deadline| deadline.min(wait_until));
+
+        let read = loop {
+            let timeout = match time_left_within(Some(deadline), timeout) {
+                Ok(timeout) => timeout,
+                Err(err) => break Err(err),
+            };
+            if let Err(err) = socket.set_read_timeout(Some(timeout)) {
+                break Err(err.into());
+            }
 
-                if data.len() <= 9 {
-                    Err(Error::InvalidResponse)?
+            match socket.recv_from(buf) {
+                Ok((read, source)) if source_match.matches(from, canonical_addr(source)) => {
+                    break Ok(read)
                 }
+                Ok(_) => continue,
+                Err(err) => break Err(Error::from_socket(err)),
+            }
+        };
+        socket.set_read_timeout(Some(self.config.timeout))?;
 
-                let packet_id = read_buffer_offset!(&data, OFS_MP_ID, i32);
+        // Report timeouts like the async build does, rather than as the platform's IO error
+        match read {
+            Err(err) if err.is_timeout() => Err(Error::ErrTimeout),
+            read => read,
+        }
+    }
 
-                if packet_id != id {
-                    return Err(Error::MismatchID);
-                }
+    #[cfg(not(feature = "async"))]
+    /// Discards the datagrams already queued on `socket`, such as responses that arrived after
+    /// their query gave up, so none can be taken for the reply to the next request.
+    fn discard_queued(socket: &UdpSocket, buf: &mut [u8]) -> Result<()> {
+        socket.set_nonblocking(true)?;
+        let drained = loop {
+            match socket.recv_from(buf) {
+                Ok(_) => continue,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break Ok(()),
+                // Errors queued by earlier sends, such as ICMP port unreachable, are stale too
+                Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => continue,
+                Err(err) => break Err(err.into()),
+            }
+        };
+        socket.set_nonblocking(false)?;
+        drained
+    }
 
-                if id as u32 & 0x80000000 == 0 {
-                    // Uncompressed packet
-                    packets.push(PacketFragment {
-                        number: data[OFS_MP_SS_NUMBER],
-                        payload: Vec::from(&data[OFS_MP_SS_PAYLOAD..]),
-                    });
-                } else {
-                    // BZip2 compressed packet
-                    packets.push(PacketFragment {
-                        number: data[OFS_MP_SS_NUMBER],
-                        payload: Vec::from(&data[OFS_MP_SS_PAYLOAD_BZ2..]),
-                    });
-                }
+    /// Sends a request that may require a challenge, appending `challenge` if set. The
+    /// handshake only happens if the server asks for a (fresh) challenge. Returns the response
+    /// along with the challenge it was accepted with, if one was required.
+    #[cfg(not(feature = "async"))]
+    fn do_optional_challenge_request<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        request: &[u8],
+        challenge: Option<i32>,
+    ) -> Result<(Vec<u8>, Option<i32>)> {
+        let deadline = self.query_deadline(None);
+        let packet = request_packet(request, challenge);
 
-                if packets.len() == total_packets {
-                    break;
-                }
-            }
+        let data = self.send_by(&packet, &addr, deadline)?;
+        let challenge = match read_challenge(&data, self.config.challenge_header) {
+            Some(challenge) => challenge,
+            None => return Ok((data, challenge)),
+        };
 
-            packets.sort_by_key(|p| p.number);
+        let (received, challenge) = self.send_challenged(request, challenge, &addr, deadline)?;
 
-            let mut aggregation = Vec::with_capacity(0);
-            aggregation.try_reserve(total_packets * self.max_size)?;
+        Ok((received.payload, Some(challenge)))
+    }
 
-            for p in packets {
-                aggregation.extend(p.payload);
+    /// Sends `request` followed by the `challenge` the server handed out, returning the response
+    /// along with the challenge it was accepted with. Info queries are resent up to
+    /// [`INFO_CHALLENGE_RETRIES`] times if the server answers with something other than info, as
+    /// some echo the probe or repeat the challenge mid-handshake; with the fresh challenge, if
+    /// one was handed out. The last answer is returned either way, for the parser to reject.
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn send_challenged<A: ToSocketAddrs>(
+        &self,
+        request: &[u8],
+        mut challenge: i32,
+        addr: A,
+        deadline: Option<Instant>,
+    ) -> Result<(Received, i32)> {
+        let mut packet = request_packet(request, Some(challenge));
+        let mut received = self.send_counted(&packet, &addr, deadline, &self.config)?;
+
+        let info = request.get(4) == Some(&INFO_REQUEST[4]);
+        for _ in 0..INFO_CHALLENGE_RETRIES {
+            if !info || is_info_response(&received.payload) {
+                break;
+            }
+            if let Some(fresh) = read_challenge(&received.payload, self.config.challenge_header) {
+                challenge = fresh;
+                packet.truncate(request.len());
+                packet.write_i32::<LittleEndian>(challenge)?;
             }
+            received = self.send_counted(&packet, &addr, deadline, &self.config)?;
+        }
 
-            if id as u32 & 0x80000000 != 0 {
-                let decompressed_size = read_buffer_offset!(&data, OFS_MP_SS_BZ2_SIZE, u32);
-                let checksum = read_buffer_offset!(&data, OFS_MP_SS_BZ2_CRC, u32);
+        Ok((received, challenge))
+    }
 
-                if decompressed_size > (1024 * 1024) {
-                    return Err(Error::InvalidBz2Size);
-                }
+    /// Sends a request that may require a challenge, with the response limits and
+    /// [`ChallengeMode`] of `config`. With `challenge` set, the handshake is skipped unless the
+    /// server rejects it with a fresh one. Returns the response along with the challenge it was
+    /// accepted with, if one was required.
+    #[cfg(not(feature = "async"))]
+    fn do_challenge_request<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        header: &[u8],
+        challenge: Option<i32>,
+        config: &A2SConfig,
+    ) -> Result<(Vec<u8>, Option<i32>)> {
+        let deadline = self.query_deadline(None);
+        let mut packet = challenge_packet(header, challenge, config);
+
+        let data = self.send_counted(&packet, &addr, deadline, config)?.payload;
+        let challenge = match server_challenge(&data, challenge, config)? {
+            Some(challenge) => challenge,
+            None => return Ok((data, challenge)),
+        };
+
+        packet.truncate(header.len());
+        packet.write_i32::<LittleEndian>(challenge)?;
+        let data = self.send_counted(&packet, &addr, deadline, config)?.payload;
 
-                let mut decompressed = Vec::with_capacity(0);
-                decompressed.try_reserve(decompressed_size as usize)?;
-                decompressed.resize(decompressed_size as usize, 0);
+        Ok((data, Some(challenge)))
+    }
+}
 
-                BzDecoder::new(aggregation.deref()).read_exact(&mut decompressed)?;
+/// Builds the first request of a query that may require a challenge: `challenge` if known,
+/// otherwise the probe (`-1`) or nothing depending on [`A2SConfig::challenge_mode`].
+#[cfg(any(feature = "std", feature = "wasm"))]
+pub(crate) fn challenge_packet(
+    header: &[u8],
+    challenge: Option<i32>,
+    config: &A2SConfig,
+) -> Vec<u8> {
+    let challenge = match config.challenge_mode {
+        ChallengeMode::Always => challenge.or(Some(-1)),
+        ChallengeMode::OnDemand | ChallengeMode::Never => challenge,
+    };
 
-                if crc32::checksum_ieee(&decompressed) != checksum {
-                    return Err(Error::CheckSumMismatch);
-                }
+    request_packet(header, challenge)
+}
 
-                Ok(decompressed)
-            } else {
-                Ok(aggregation)
-            }
-        } else {
-            Err(Error::InvalidResponse)
-        }
+/// `request` followed by `challenge`, if any, as a little-endian `i32`.
+#[cfg(any(feature = "std", feature = "wasm"))]
+pub(crate) fn request_packet(request: &[u8], challenge: Option<i32>) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(request.len() + 4);
+    packet.extend_from_slice(request);
+    if let Some(challenge) = challenge {
+        packet.extend_from_slice(&challenge.to_le_bytes());
     }
+    packet
+}
 
-    #[cfg(not(feature = "async"))]
-    fn do_challenge_request<A: ToSocketAddrs>(&self, addr: A, header: &[u8]) -> Result<Vec<u8>> {
-        let packet = Vec::with_capacity(9);
-        let mut packet = Cursor::new(packet);
-
-        packet.write_all(header)?;
-        packet.write_i32::<LittleEndian>(-1)?;
+/// Times an info query is resent after its challenged request was answered with something
+/// other than info, see [`A2SClient::send_challenged`].
+#[cfg(feature = "std")]
+const INFO_CHALLENGE_RETRIES: usize = 1;
+
+/// Whether a payload is an info response, Source (`0x49`) or obsolete GoldSource (`0x6D`), as
+/// told by its header.
+#[cfg(feature = "std")]
+pub(crate) fn is_info_response(data: &[u8]) -> bool {
+    let data = data.strip_prefix(&[0xff; 4]).unwrap_or(data);
+    matches!(data.first(), Some(0x49 | 0x6d))
+}
 
-        let data = self.send(packet.get_ref(), &addr)?;
-        let mut data = Cursor::new(data);
+/// The challenge a response hands out, `None` if it's the actual response. A server that
+/// answers without requiring one is fine in every mode, one that asks for a challenge fails
+/// the query in [`ChallengeMode::Never`].
+#[cfg(any(feature = "std", feature = "wasm"))]
+pub(crate) fn server_challenge(
+    data: &[u8],
+    challenge: Option<i32>,
+    config: &A2SConfig,
+) -> Result<Option<i32>> {
+    let Some(server) = read_challenge(data, config.challenge_header) else {
+        return Ok(None);
+    };
 
-        let header = data.read_u8()?;
-        if header != b'A' {
-            return Err(Error::InvalidResponse);
-        }
+    if config.challenge_mode == ChallengeMode::Never && challenge.is_none() {
+        return Err(Error::ChallengeRequired);
+    }
 
-        let challenge = data.read_i32::<LittleEndian>()?;
+    Ok(Some(server))
+}
 
-        packet.set_position(5);
-        packet.write_i32::<LittleEndian>(challenge)?;
-        let data = self.send(packet.get_ref(), &addr)?;
+/// The challenge a response hands out, `None` if it's the actual response. A challenge response
+/// is exactly `header` followed by the challenge: a payload merely starting with `header` is left
+/// to the response's parser, so that one whose first byte happens to match isn't mistaken for a
+/// challenge, nor its next 4 bytes sent back as one.
+#[cfg(any(feature = "std", feature = "wasm"))]
+pub(crate) fn read_challenge(data: &[u8], header: u8) -> Option<i32> {
+    match *data {
+        [first, a, b, c, d] if first == header => Some(i32::from_le_bytes([a, b, c, d])),
+        _ => None,
+    }
+}
 
-        Ok(data)
+/// Time left to wait for an operation bounded by `timeout`, and by `deadline` if set.
+#[cfg(feature = "std")]
+fn time_left_within(deadline: Option<Instant>, timeout: Duration) -> Result<Duration> {
+    match deadline {
+        None => Ok(timeout),
+        Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if !remaining.is_zero() => Ok(remaining.min(timeout)),
+            _ => Err(Error::ErrTimeout),
+        },
     }
 }
 
-trait ReadCString {
-    fn read_cstring(&mut self) -> Result<String>;
+/// The semaphore enforcing `max_concurrent`, if limited. A limit of 0 would block every query,
+/// so it's raised to 1.
+#[cfg(feature = "async")]
+fn limiter(max_concurrent: Option<usize>) -> Option<Arc<Semaphore>> {
+    max_concurrent.map(|limit| Arc::new(Semaphore::new(limit.clamp(1, Semaphore::MAX_PERMITS))))
 }
 
-impl ReadCString for Cursor<Vec<u8>> {
-    fn read_cstring(&mut self) -> Result<String> {
-        let end = self.get_ref().len() as u64;
-        let mut buf = [0; 1];
-        let mut str_vec = Vec::with_capacity(256);
-        while self.position() < end {
-            self.read_exact(&mut buf)?;
-            if buf[0] == 0 {
-                break;
-            } else {
-                str_vec.push(buf[0]);
-            }
-        }
-        Ok(String::from_utf8_lossy(&str_vec[..]).into_owned())
+/// Propagates the panic of a query task, none of which are ever cancelled.
+#[cfg(feature = "async")]
+pub(crate) fn unwind<T>(joined: std::result::Result<T, tokio::task::JoinError>) -> T {
+    joined.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+}
+
+/// Returns a pseudo-random number in `0.0..1.0`, seeded by std's randomly keyed hasher.
+#[cfg(feature = "std")]
+fn random_f32() -> f32 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::time::SystemTime;
+
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(now) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(now.as_nanos());
     }
+
+    (hasher.finish() >> 40) as f32 / (1u64 << 24) as f32
 }
@@ -0,0 +1,84 @@
+use std::fs;
+use std::net::SocketAddr;
+#[cfg(not(feature = "async"))]
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+#[cfg(feature = "async")]
+use tokio::net::ToSocketAddrs;
+
+use crate::errors::Result;
+use crate::{resolve_addr, A2SClient, ParseContext, ParseResponse, QueryKind};
+
+/// Tells apart the files of failures written within the same millisecond.
+static WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+impl A2SClient {
+    /// Parses the payload of a response from `addr`, writing it to
+    /// [`A2SConfig::malformed_dump_dir`](crate::A2SConfig::malformed_dump_dir) if it's malformed.
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn parse_from<T: ParseResponse, A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        kind: QueryKind,
+        payload: &[u8],
+        ctx: &ParseContext,
+    ) -> Result<T> {
+        let result = T::parse(payload, ctx);
+        if let Some(dir) = self.dump_dir_for(&result) {
+            write_malformed(dir, kind, resolve_addr(addr).ok(), payload);
+        }
+
+        result
+    }
+
+    /// Parses the payload of a response from `addr`, writing it to
+    /// [`A2SConfig::malformed_dump_dir`](crate::A2SConfig::malformed_dump_dir) if it's malformed.
+    #[cfg(feature = "async")]
+    pub(crate) async fn parse_from<T: ParseResponse, A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        kind: QueryKind,
+        payload: &[u8],
+        ctx: &ParseContext,
+    ) -> Result<T> {
+        let result = T::parse(payload, ctx);
+        if let Some(dir) = self.dump_dir_for(&result) {
+            write_malformed(dir, kind, resolve_addr(addr).await.ok(), payload);
+        }
+
+        result
+    }
+
+    /// The directory to write a response to, if configured and `result` failed to parse it.
+    fn dump_dir_for<T>(&self, result: &Result<T>) -> Option<&Path> {
+        match result {
+            Err(err) if err.is_invalid_response() => self.config.malformed_dump_dir.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Writes a malformed payload to a file of its own in `dir`, named after the time, query kind
+/// and server, e.g. `1700000000123-info-10.0.0.1_27015-0.bin`. A failure to write is ignored,
+/// as it must not fail the query the payload was received for.
+fn write_malformed(dir: &Path, kind: QueryKind, addr: Option<SocketAddr>, payload: &[u8]) {
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    let server = match addr {
+        Some(addr) => addr.to_string().replace(':', "_").replace(['[', ']'], ""),
+        None => "unresolved".to_owned(),
+    };
+    let name = format!(
+        "{}-{}-{}-{}.bin",
+        millis,
+        format!("{:?}", kind).to_lowercase(),
+        server,
+        WRITTEN.fetch_add(1, Ordering::Relaxed)
+    );
+
+    let _ = fs::create_dir_all(dir).and_then(|()| fs::write(dir.join(name), payload));
+}
@@ -0,0 +1,174 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A filter for master server queries, written in the backslash-delimited syntax the master
+/// server expects (`\appid\440\dedicated\1`). Conditions are ANDed, in the order added.
+///
+/// The syntax has no escape sequences, so backslashes and null bytes can't be part of a value:
+/// they are removed from the values given.
+///
+/// Only the filter is built here, sending the query and reading the server list is left to the
+/// caller. Valve's master servers list servers as 6-byte entries, an IPv4 address and a
+/// big-endian port, and document no extension listing IPv6 servers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MasterFilter {
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    Value(&'static str, String),
+    /// `nor` or `nand`, followed by the number of conditions of the nested filter
+    Group(&'static str, MasterFilter),
+}
+
+impl MasterFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Servers running the app with ID `app_id`.
+    pub fn app_id(&mut self, app_id: u32) -> &mut Self {
+        self.push("appid", app_id.to_string())
+    }
+
+    /// Servers not running the app with ID `app_id`.
+    pub fn not_app_id(&mut self, app_id: u32) -> &mut Self {
+        self.push("napp", app_id.to_string())
+    }
+
+    /// Servers with all of `tags` in their keywords (`sv_tags`).
+    pub fn game_type(&mut self, tags: &[&str]) -> &mut Self {
+        self.push("gametype", tags.join(","))
+    }
+
+    /// Servers with all of `tags` in their hidden tags (L4D2).
+    pub fn game_data(&mut self, tags: &[&str]) -> &mut Self {
+        self.push("gamedata", tags.join(","))
+    }
+
+    /// Servers with any of `tags` in their hidden tags (L4D2).
+    pub fn game_data_or(&mut self, tags: &[&str]) -> &mut Self {
+        self.push("gamedataor", tags.join(","))
+    }
+
+    /// Servers running the mod in folder `dir`, e.g. `tf`.
+    pub fn game_dir(&mut self, dir: &str) -> &mut Self {
+        self.push("gamedir", dir)
+    }
+
+    /// Servers running `map`.
+    pub fn map(&mut self, map: &str) -> &mut Self {
+        self.push("map", map)
+    }
+
+    /// Servers whose name matches `pattern`, where `*` is a wildcard.
+    pub fn name_match(&mut self, pattern: &str) -> &mut Self {
+        self.push("name_match", pattern)
+    }
+
+    /// Servers whose version matches `pattern`, where `*` is a wildcard.
+    pub fn version_match(&mut self, pattern: &str) -> &mut Self {
+        self.push("version_match", pattern)
+    }
+
+    /// Servers at `addr`, an IP with an optional port (`1.2.3.4:27015`).
+    pub fn game_addr(&mut self, addr: &str) -> &mut Self {
+        self.push("gameaddr", addr)
+    }
+
+    pub fn dedicated(&mut self) -> &mut Self {
+        self.push("dedicated", "1")
+    }
+
+    /// Servers using anti-cheat (VAC).
+    pub fn secure(&mut self) -> &mut Self {
+        self.push("secure", "1")
+    }
+
+    pub fn linux(&mut self) -> &mut Self {
+        self.push("linux", "1")
+    }
+
+    /// Servers requiring a password if `password`, or not requiring one otherwise.
+    pub fn password(&mut self, password: bool) -> &mut Self {
+        self.push("password", if password { "1" } else { "0" })
+    }
+
+    /// Servers with at least one player.
+    pub fn not_empty(&mut self) -> &mut Self {
+        self.push("empty", "1")
+    }
+
+    /// Servers with at least one free slot.
+    pub fn not_full(&mut self) -> &mut Self {
+        self.push("full", "1")
+    }
+
+    /// Servers without players.
+    pub fn no_players(&mut self) -> &mut Self {
+        self.push("noplayers", "1")
+    }
+
+    /// Spectator proxies only.
+    pub fn proxy(&mut self) -> &mut Self {
+        self.push("proxy", "1")
+    }
+
+    /// Whitelisted servers only.
+    pub fn whitelisted(&mut self) -> &mut Self {
+        self.push("white", "1")
+    }
+
+    /// Lists a single server per IP address.
+    pub fn collapse_addr_hash(&mut self) -> &mut Self {
+        self.push("collapse_addr_hash", "1")
+    }
+
+    /// Servers matching none of the conditions of `filter`.
+    pub fn nor(&mut self, filter: MasterFilter) -> &mut Self {
+        self.conditions.push(Condition::Group("nor", filter));
+        self
+    }
+
+    /// Servers not matching all of the conditions of `filter`.
+    pub fn nand(&mut self, filter: MasterFilter) -> &mut Self {
+        self.conditions.push(Condition::Group("nand", filter));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    /// The filter as sent in a master server request, null-terminated.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.to_string().into_bytes();
+        bytes.push(0);
+        bytes
+    }
+
+    fn push<V: Into<String>>(&mut self, key: &'static str, value: V) -> &mut Self {
+        let mut value = value.into();
+        value.retain(|c| c != '\\' && c != '\0');
+
+        self.conditions.push(Condition::Value(key, value));
+        self
+    }
+}
+
+impl fmt::Display for MasterFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for condition in &self.conditions {
+            match condition {
+                Condition::Value(key, value) => write!(f, "\\{}\\{}", key, value)?,
+                Condition::Group(key, filter) => {
+                    write!(f, "\\{}\\{}{}", key, filter.conditions.len(), filter)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
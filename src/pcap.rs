@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::errors::{Error, Result};
+use crate::info::Info;
+use crate::{A2SConfig, ParseContext, ParseResponse, ParsedResponse, Reassembly, Response};
+
+/// Ports servers are usually queried at, 27015 and its neighbours.
+pub const QUERY_PORTS: RangeInclusive<u16> = 27000..=27050;
+
+/// A UDP datagram extracted from a capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedDatagram {
+    /// When the datagram was captured, since the Unix epoch.
+    pub timestamp: Duration,
+
+    pub source: SocketAddr,
+
+    pub destination: SocketAddr,
+
+    pub payload: Vec<u8>,
+}
+
+/// A response reconstructed from the datagrams of a capture.
+#[derive(Debug)]
+pub struct CapturedResponse {
+    /// When the response's last datagram was captured, since the Unix epoch, the last fragment
+    /// received for an incomplete one.
+    pub timestamp: Duration,
+
+    /// The server that sent the response.
+    pub server: SocketAddr,
+
+    /// The client the response was sent to.
+    pub client: SocketAddr,
+
+    /// The parsed response, or why it couldn't be parsed or reassembled.
+    pub response: Result<ParsedResponse>,
+}
+
+/// Reads the A2S responses of a pcap capture file, see [`read_responses`].
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Vec<CapturedResponse>> {
+    read_responses(BufReader::new(File::open(path)?))
+}
+
+/// Reads the A2S responses of a pcap capture, sent from [`QUERY_PORTS`], see [`responses`].
+pub fn read_responses<R: Read>(reader: R) -> io::Result<Vec<CapturedResponse>> {
+    Ok(responses(&read_datagrams(reader)?, QUERY_PORTS))
+}
+
+/// Extracts every UDP datagram of a pcap capture, up to the end of `reader`.
+///
+/// Captures must be in the classic pcap format (not pcapng), of Ethernet, Linux cooked (SLL
+/// and SLL2), BSD loopback or raw IP frames. Only UDP over IPv4 or IPv6 without extension
+/// headers is extracted: other packets, IP fragments and datagrams cut short by the capture's
+/// snapshot length are skipped.
+pub fn read_datagrams<R: Read>(mut reader: R) -> io::Result<Vec<CapturedDatagram>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    let header = data.get(..24).ok_or(io::ErrorKind::UnexpectedEof)?;
+    let (big_endian, nanos) = match header[..4] {
+        [0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+        [0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+        [0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+        [0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+        _ => return Err(invalid_data("not a pcap capture (pcapng isn't supported)")),
+    };
+    let read_u32 = |bytes: &[u8]| {
+        let bytes = bytes[..4].try_into().expect("4 bytes");
+        if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    };
+    let link_type = read_u32(&header[20..]);
+
+    let mut datagrams = Vec::new();
+    let mut records = &data[24..];
+    while !records.is_empty() {
+        let record = records.get(..16).ok_or(io::ErrorKind::UnexpectedEof)?;
+        let len = read_u32(&record[8..]) as usize;
+        let frame = records
+            .get(16..16 + len)
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        records = &records[16 + len..];
+
+        let fraction = u64::from(read_u32(&record[4..]));
+        let timestamp = Duration::from_secs(read_u32(record).into())
+            + if nanos {
+                Duration::from_nanos(fraction)
+            } else {
+                Duration::from_micros(fraction)
+            };
+
+        let packet = match link_type {
+            LINKTYPE_ETHERNET => ethernet_payload(frame),
+            LINKTYPE_NULL => frame.get(4..),
+            LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => Some(frame),
+            LINKTYPE_LINUX_SLL => frame.get(16..),
+            LINKTYPE_LINUX_SLL2 => frame.get(20..),
+            _ => return Err(invalid_data("unsupported link type")),
+        };
+        if let Some(datagram) = packet.and_then(|packet| udp_datagram(packet, timestamp)) {
+            datagrams.push(datagram);
+        }
+    }
+
+    Ok(datagrams)
+}
+
+/// Reconstructs the A2S responses among `datagrams`, those sent from a port in `ports`, in the
+/// order they were completed.
+///
+/// Fragments of multi-packet responses are reassembled per server and client, and player
+/// responses are parsed for the app ID of the server's last info response. Challenges, as
+/// well as datagrams without an A2S packet header or with a header of a response this crate
+/// doesn't parse, are skipped. Multi-packet responses still missing fragments at the end of
+/// the capture, or cut short by another response, are reported as
+/// [`Error::IncompleteResponse`].
+pub fn responses(
+    datagrams: &[CapturedDatagram],
+    ports: RangeInclusive<u16>,
+) -> Vec<CapturedResponse> {
+    let config = A2SConfig::default();
+    let mut pending: HashMap<(SocketAddr, SocketAddr), (Reassembly, Duration)> = HashMap::new();
+    let mut app_ids: HashMap<SocketAddr, u16> = HashMap::new();
+    let mut responses = Vec::new();
+
+    for datagram in datagrams {
+        if !ports.contains(&datagram.source.port()) || !is_a2s(&datagram.payload) {
+            continue;
+        }
+
+        let flow = (datagram.source, datagram.destination);
+        let respond = |timestamp, response| CapturedResponse {
+            timestamp,
+            server: datagram.source,
+            client: datagram.destination,
+            response,
+        };
+
+        let payload = match pending.remove(&flow) {
+            Some((mut reassembly, last)) => {
+                match reassembly.push(datagram.payload.clone(), &config) {
+                    Ok(()) if reassembly.is_complete() => reassembly.finish().map(Some),
+                    Ok(()) => {
+                        pending.insert(flow, (reassembly, datagram.timestamp));
+                        continue;
+                    }
+                    // A datagram that isn't a fragment of this response starts another
+                    Err(_) => {
+                        responses.push(respond(last, Err(incomplete(&reassembly))));
+                        start(
+                            &datagram.payload,
+                            &config,
+                            &mut pending,
+                            flow,
+                            datagram.timestamp,
+                        )
+                    }
+                }
+            }
+            None => start(
+                &datagram.payload,
+                &config,
+                &mut pending,
+                flow,
+                datagram.timestamp,
+            ),
+        };
+
+        let payload = match payload {
+            Ok(Some(payload)) => payload,
+            Ok(None) => continue,
+            Err(err) => {
+                responses.push(respond(datagram.timestamp, Err(err)));
+                continue;
+            }
+        };
+
+        let ctx = ParseContext {
+            app_id: app_ids.get(&datagram.source).copied().unwrap_or(0),
+            ..Default::default()
+        };
+        let response = match payload.first() {
+            Some(0x49) | Some(0x6d) => Info::parse(&payload, &ctx).map(|info| {
+                app_ids.insert(datagram.source, info.app_id);
+                ParsedResponse::Info(Box::new(info))
+            }),
+            Some(0x44) => Vec::parse(&payload, &ctx).map(ParsedResponse::Players),
+            Some(0x45) => Vec::parse(&payload, &ctx).map(ParsedResponse::Rules),
+            _ => continue,
+        };
+        responses.push(respond(datagram.timestamp, response));
+    }
+
+    let mut incomplete_responses: Vec<_> = pending
+        .into_iter()
+        .map(
+            |((server, client), (reassembly, timestamp))| CapturedResponse {
+                timestamp,
+                server,
+                client,
+                response: Err(incomplete(&reassembly)),
+            },
+        )
+        .collect();
+    incomplete_responses.sort_by_key(|response| response.timestamp);
+    responses.extend(incomplete_responses);
+
+    responses
+}
+
+/// Handles the first datagram of a response, returning its payload if complete. A multi-packet
+/// response still missing fragments is kept in `pending`.
+fn start(
+    datagram: &[u8],
+    config: &A2SConfig,
+    pending: &mut HashMap<(SocketAddr, SocketAddr), (Reassembly, Duration)>,
+    flow: (SocketAddr, SocketAddr),
+    timestamp: Duration,
+) -> Result<Option<Vec<u8>>> {
+    match Response::start(datagram.to_vec(), config)? {
+        Response::Complete(payload) => Ok(Some(payload)),
+        Response::Partial(reassembly) if reassembly.is_complete() => reassembly.finish().map(Some),
+        Response::Partial(reassembly) => {
+            pending.insert(flow, (reassembly, timestamp));
+            Ok(None)
+        }
+    }
+}
+
+fn incomplete(reassembly: &Reassembly) -> Error {
+    Error::IncompleteResponse {
+        received: reassembly.packets.len(),
+        expected: reassembly.total_packets,
+    }
+}
+
+/// Whether a datagram starts with a single or multi-packet header.
+fn is_a2s(datagram: &[u8]) -> bool {
+    matches!(datagram.get(..4), Some([0xff | 0xfe, 0xff, 0xff, 0xff]))
+}
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+/// The network layer packet of an Ethernet frame, behind any 802.1Q VLAN tags.
+fn ethernet_payload(frame: &[u8]) -> Option<&[u8]> {
+    let mut offset = 12;
+    while u16::from_be_bytes(frame.get(offset..offset + 2)?.try_into().ok()?) == ETHERTYPE_VLAN {
+        offset += 4;
+    }
+
+    frame.get(offset + 2..)
+}
+
+/// The UDP datagram in an IP packet, if it holds a whole one.
+fn udp_datagram(packet: &[u8], timestamp: Duration) -> Option<CapturedDatagram> {
+    let (source, destination, segment) = match packet.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(packet[0] & 0x0f) * 4;
+            let total_len = usize::from(u16::from_be_bytes(packet.get(2..4)?.try_into().ok()?));
+            let fragment = u16::from_be_bytes(packet.get(6..8)?.try_into().ok()?);
+            // More fragments flag or a fragment offset
+            if fragment & 0x3fff != 0 || *packet.get(9)? != IP_PROTOCOL_UDP {
+                return None;
+            }
+
+            let source: [u8; 4] = packet.get(12..16)?.try_into().ok()?;
+            let destination: [u8; 4] = packet.get(16..20)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv4Addr::from(source)),
+                IpAddr::from(Ipv4Addr::from(destination)),
+                packet.get(header_len..total_len)?,
+            )
+        }
+        6 => {
+            if *packet.get(6)? != IP_PROTOCOL_UDP {
+                return None;
+            }
+
+            let payload_len = usize::from(u16::from_be_bytes(packet.get(4..6)?.try_into().ok()?));
+            let source: [u8; 16] = packet.get(8..24)?.try_into().ok()?;
+            let destination: [u8; 16] = packet.get(24..40)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv6Addr::from(source)),
+                IpAddr::from(Ipv6Addr::from(destination)),
+                packet.get(40..40 + payload_len)?,
+            )
+        }
+        _ => return None,
+    };
+
+    let read_u16 = |offset: usize| {
+        Some(u16::from_be_bytes(
+            segment.get(offset..offset + 2)?.try_into().ok()?,
+        ))
+    };
+    let len = usize::from(read_u16(4)?);
+
+    Some(CapturedDatagram {
+        timestamp,
+        source: SocketAddr::new(source, read_u16(0)?),
+        destination: SocketAddr::new(destination, read_u16(2)?),
+        payload: segment.get(8..len)?.to_vec(),
+    })
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,131 @@
+#[cfg(not(feature = "async"))]
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use tokio::net::ToSocketAddrs;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Result;
+use crate::A2SClient;
+
+/// Round trip times of repeated pings, as measured by [`A2SClient::ping_n`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PingStats {
+    /// Number of pings answered
+    pub received: usize,
+
+    /// Number of pings that timed out
+    pub dropped: usize,
+
+    /// Shortest round trip
+    pub min: Duration,
+
+    /// Longest round trip
+    pub max: Duration,
+
+    /// Average round trip
+    pub mean: Duration,
+
+    /// Standard deviation of the round trips, i.e. the jitter
+    pub stddev: Duration,
+}
+
+impl PingStats {
+    /// Statistics of the round trips `samples` of the pings answered, `dropped` others having
+    /// timed out.
+    pub fn from_samples(samples: &[Duration], dropped: usize) -> Self {
+        if samples.is_empty() {
+            return PingStats {
+                dropped,
+                ..Default::default()
+            };
+        }
+
+        let count = samples.len() as f64;
+        let mean = samples.iter().map(Duration::as_secs_f64).sum::<f64>() / count;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample.as_secs_f64() - mean).powi(2))
+            .sum::<f64>()
+            / count;
+
+        PingStats {
+            received: samples.len(),
+            dropped,
+            min: samples.iter().copied().min().unwrap_or_default(),
+            max: samples.iter().copied().max().unwrap_or_default(),
+            mean: Duration::from_secs_f64(mean),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+        }
+    }
+
+    /// Share of the pings that timed out, from 0.0 to 1.0.
+    pub fn loss(&self) -> f64 {
+        match self.received + self.dropped {
+            0 => 0.0,
+            sent => self.dropped as f64 / sent as f64,
+        }
+    }
+}
+
+impl A2SClient {
+    /// Pings the server at `addr` `count` times in a row, as [`info_and_ping`] does, for the
+    /// spread of its round trips rather than a single measurement. Pings timing out are
+    /// counted as dropped instead of failing the call, which only fails if none was answered
+    /// (with the last timeout) or on any other error.
+    ///
+    /// [`info_and_ping`]: A2SClient::info_and_ping
+    #[cfg(feature = "async")]
+    pub async fn ping_n<A: ToSocketAddrs>(&self, addr: A, count: usize) -> Result<PingStats> {
+        let mut samples = Vec::with_capacity(count);
+        let mut dropped = 0;
+        let mut last_timeout = None;
+        for _ in 0..count {
+            match self.info_and_ping(&addr).await {
+                Ok((_, ping)) => samples.push(ping),
+                Err(err) if err.is_timeout() => {
+                    dropped += 1;
+                    last_timeout = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        match last_timeout {
+            Some(err) if samples.is_empty() => Err(err),
+            _ => Ok(PingStats::from_samples(&samples, dropped)),
+        }
+    }
+
+    /// Pings the server at `addr` `count` times in a row, as [`info_and_ping`] does, for the
+    /// spread of its round trips rather than a single measurement. Pings timing out are
+    /// counted as dropped instead of failing the call, which only fails if none was answered
+    /// (with the last timeout) or on any other error.
+    ///
+    /// [`info_and_ping`]: A2SClient::info_and_ping
+    #[cfg(not(feature = "async"))]
+    pub fn ping_n<A: ToSocketAddrs>(&self, addr: A, count: usize) -> Result<PingStats> {
+        let mut samples = Vec::with_capacity(count);
+        let mut dropped = 0;
+        let mut last_timeout = None;
+        for _ in 0..count {
+            match self.info_and_ping(&addr) {
+                Ok((_, ping)) => samples.push(ping),
+                Err(err) if err.is_timeout() => {
+                    dropped += 1;
+                    last_timeout = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        match last_timeout {
+            Some(err) if samples.is_empty() => Err(err),
+            _ => Ok(PingStats::from_samples(&samples, dropped)),
+        }
+    }
+}
@@ -1,21 +1,28 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+#[cfg(feature = "codec")]
 use std::io::Cursor;
-#[cfg(not(feature = "async"))]
+#[cfg(all(feature = "std", not(feature = "async")))]
 use std::net::ToSocketAddrs;
 
 #[cfg(feature = "async")]
 use tokio::net::ToSocketAddrs;
 
-use byteorder::{LittleEndian, ReadBytesExt};
-
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 use crate::errors::{Error, Result};
-use crate::{A2SClient, ReadCString};
+use crate::info::{Info, THE_SHIP_APP_ID};
+use crate::reader::BytesReader;
+#[cfg(feature = "std")]
+use crate::{challenge_packet, A2SClient, QueryKind};
+use crate::{with_dump, ParseContext, ParseResponse};
 
-const PLAYER_REQUEST: [u8; 5] = [0xff, 0xff, 0xff, 0xff, 0x55];
+#[cfg(feature = "codec")]
+pub(crate) const PLAYER_REQUEST: [u8; 5] = [0xff, 0xff, 0xff, 0xff, 0x55];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Player {
     /// Index of player chunk starting from 0.
@@ -33,9 +40,13 @@ pub struct Player {
 
     /// The Ship additional player info
     pub the_ship: Option<TheShipPlayer>,
+
+    /// Game-specific bytes some mods append to every player record. Only recognized when all
+    /// records carry the same number of them (up to 16), empty otherwise.
+    pub extra: Vec<u8>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct TheShipPlayer {
     pub deaths: u32,
@@ -43,49 +54,338 @@ pub struct TheShipPlayer {
     pub money: u32,
 }
 
+/// A player list checked against the player count of the server's info.
+///
+/// The base protocol has no way to page through players, a server sends what fits in its
+/// response. A few high population servers truncate the list, which this only detects.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PlayerList {
+    pub players: Vec<Player>,
+
+    /// Whether fewer players were listed than the info reports. Servers hiding some players
+    /// (e.g. bots) from the list are reported as truncated too.
+    pub players_truncated: bool,
+}
+
+impl PlayerList {
+    pub fn new(players: Vec<Player>, info: &Info) -> Self {
+        let players_truncated = players.len() < usize::from(info.players);
+
+        PlayerList {
+            players,
+            players_truncated,
+        }
+    }
+}
+
+/// How the players of a server likely split between humans and bots, see [`reconcile_players`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PlayerBreakdown {
+    /// Likely number of humans on the server
+    pub humans: usize,
+
+    /// Number of bots on the server, as the info reports it
+    pub bots: usize,
+
+    /// The info's player count appears to leave bots out, as it's lower than the bot count
+    pub bots_excluded: bool,
+
+    /// The player list has more entries than the info reports players (bots included)
+    pub list_longer_than_info: bool,
+
+    /// Entries without a name, usually players still connecting
+    pub unnamed: usize,
+}
+
+/// Cross-checks the player counts of the info with the player list.
+///
+/// Games disagree on whether the info's `players` includes bots, and adding `bots` to a count
+/// that already includes them counts bots twice. The heuristics used:
+///
+/// - The info's `players` is assumed to include bots, as most Source games do, so humans are
+///   `players - bots`.
+/// - Unless `bots` exceeds `players`, which can only happen if bots were left out: humans are
+///   then `players`.
+/// - The list is compared against the total this yields. It may be shorter, servers hide bots
+///   or truncate the list, but a longer one means the counts are off.
+pub fn reconcile_players(info: &Info, players: &[Player]) -> PlayerBreakdown {
+    let reported = usize::from(info.players);
+    let bots = usize::from(info.bots);
+    let bots_excluded = bots > reported;
+
+    let total = if bots_excluded {
+        reported + bots
+    } else {
+        reported
+    };
+
+    PlayerBreakdown {
+        humans: usize::from(info.humans()),
+        bots,
+        bots_excluded,
+        list_longer_than_info: players.len() > total,
+        unnamed: players.iter().filter(|p| p.name.is_empty()).count(),
+    }
+}
+
+impl Info {
+    /// Likely number of humans on the server, from the info alone, see [`reconcile_players`]
+    /// for the heuristics.
+    pub fn humans(&self) -> u8 {
+        if self.bots > self.players {
+            self.players
+        } else {
+            self.players - self.bots
+        }
+    }
+}
+
+/// Largest number of game-specific bytes per player record looked for, see [`Player::extra`].
+const MAX_PLAYER_EXTRA: usize = 16;
+
+/// Longest player name accepted, in characters. Source caps names at 128 bytes, so anything
+/// longer is the parse running off a record.
+const MAX_PLAYER_NAME_LEN: usize = 128;
+
+/// Sorts players by name, then score and duration, both highest first, so that consecutive
+/// polls of a server compare cleanly: servers list players in an order of their own, which may
+/// change between queries. Players alike on all three keep their relative order.
+///
+/// Sorts in place, keep a copy of the list for the server's order.
+pub fn sort_players(players: &mut [Player]) {
+    players.sort_by(Player::stable_cmp);
+}
+
 impl Player {
-    pub fn from_cursor(mut data: Cursor<Vec<u8>>, app_id: u16) -> Result<Vec<Self>> {
+    /// Orders players as [`sort_players`] does.
+    pub fn stable_cmp(&self, other: &Self) -> Ordering {
+        self.name
+            .cmp(&other.name)
+            .then_with(|| other.score.cmp(&self.score))
+            .then_with(|| other.duration.total_cmp(&self.duration))
+    }
+
+    /// Parses a players payload, skipping a leading single packet header if a relay left one.
+    ///
+    /// Parsing stops cleanly at the first record that can't be read, keeping the players before
+    /// it. This includes the malformed GOTV spectator records of some CS:GO servers, detected by
+    /// a duration that isn't finite or a name longer than Source allows.
+    #[cfg(feature = "codec")]
+    pub fn from_cursor(data: Cursor<Vec<u8>>, app_id: u16) -> Result<Vec<Self>> {
+        Self::from_reader(&mut BytesReader::from_cursor(&data), app_id)
+    }
+
+    /// Parses a players payload like [`from_cursor`](Self::from_cursor), from a borrowed buffer.
+    pub fn from_reader(data: &mut BytesReader, app_id: u16) -> Result<Vec<Self>> {
+        data.skip_packet_header();
+
         if data.read_u8()? != 0x44 {
             return Err(Error::InvalidResponse);
         }
 
         let player_count = data.read_u8()?;
+        let start = data.position();
 
         let mut players: Vec<Self> = Vec::with_capacity(player_count as usize);
+        let standard = Self::read_players(data, player_count, app_id, 0, &mut players);
 
+        if standard.is_ok() && data.is_empty() {
+            return Ok(players);
+        }
+
+        // Some mods append a fixed number of bytes to every record, look for the width that
+        // consumes the response exactly
+        for extra in 1..=MAX_PLAYER_EXTRA {
+            let mut trial = Vec::with_capacity(player_count as usize);
+            data.set_position(start);
+
+            let read = Self::read_players(data, player_count, app_id, extra, &mut trial);
+            if read.is_ok() && data.is_empty() {
+                return Ok(trial);
+            }
+        }
+
+        // Otherwise keep the standard parse, stopping cleanly at the first unreadable record
+        Ok(players)
+    }
+
+    /// The name without control characters, see [`clean_name`](crate::clean_name) to also strip
+    /// color markers.
+    pub fn clean_name(&self) -> String {
+        crate::clean_name(&self.name, false)
+    }
+
+    fn read_players(
+        data: &mut BytesReader,
+        player_count: u8,
+        app_id: u16,
+        extra: usize,
+        players: &mut Vec<Self>,
+    ) -> Result<()> {
         for _ in 0..player_count {
+            let index = data.read_u8()?;
+            let name = data.read_cstring()?;
+            let score = data.read_i32_le()?;
+            let duration = data.read_f32_le()?;
+
+            // CS:GO servers running GOTV sometimes list its spectator with fields that shift the
+            // rest of the record, which shows as a non-finite duration or a runaway name. The
+            // record is dropped and parsing stops there, like for a truncated one.
+            if !duration.is_finite() || name.chars().count() > MAX_PLAYER_NAME_LEN {
+                return Err(Error::InvalidResponse);
+            }
+
             players.push(Self {
-                index: data.read_u8()?,
-                name: data.read_cstring()?,
-                score: data.read_i32::<LittleEndian>()?,
-                duration: data.read_f32::<LittleEndian>()?,
+                index,
+                name,
+                score,
+                duration,
                 the_ship: {
-                    if app_id == 2400 {
+                    if app_id == THE_SHIP_APP_ID {
                         Some(TheShipPlayer {
-                            deaths: data.read_u32::<LittleEndian>()?,
-                            money: data.read_u32::<LittleEndian>()?,
+                            deaths: data.read_u32_le()?,
+                            money: data.read_u32_le()?,
                         })
                     } else {
                         None
                     }
                 },
+                extra: data.read_bytes(extra)?.to_vec(),
             })
         }
 
-        Ok(players)
+        Ok(())
     }
 }
 
+impl ParseResponse for Vec<Player> {
+    fn parse(bytes: &[u8], ctx: &ParseContext) -> Result<Self> {
+        let app_id = if ctx.parse_the_ship { ctx.app_id } else { 0 };
+        with_dump(
+            bytes,
+            Player::from_reader(&mut BytesReader::new(bytes), app_id),
+            ctx,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
 impl A2SClient {
+    /// The exact datagram [`players`](Self::players) sends first, or with `challenge` the one
+    /// answering a server's challenge, e.g. to replay the query with other tools. Without a
+    /// challenge, it carries the `-1` probe if [`ChallengeMode::Always`](crate::ChallengeMode::Always)
+    /// is configured.
+    pub fn players_request_bytes(&self, challenge: Option<i32>) -> Vec<u8> {
+        challenge_packet(&PLAYER_REQUEST, challenge, &self.config)
+    }
+
     #[cfg(feature = "async")]
     pub async fn players<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Player>> {
-        let data = self.do_challenge_request(addr, &PLAYER_REQUEST).await?;
-        Player::from_cursor(Cursor::new(data), self.app_id)
+        Ok(self
+            .players_for_app(addr, self.config.app_id, None)
+            .await?
+            .0)
+    }
+
+    /// Queries players, accepting multi-packet responses split into fragments of up to
+    /// `buf_size` bytes instead of [`A2SConfig::max_size`](crate::A2SConfig::max_size), for
+    /// servers known to send huge player lists. The client's configuration is left untouched.
+    #[cfg(feature = "async")]
+    pub async fn players_with_buffer<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        buf_size: usize,
+    ) -> Result<Vec<Player>> {
+        let config = self.sized_config(buf_size);
+        let (data, _) = self
+            .do_challenge_request(&addr, &PLAYER_REQUEST, None, &config)
+            .await?;
+        let ctx = self.parse_context(self.config.app_id);
+        self.parse_from(&addr, QueryKind::Players, &data, &ctx)
+            .await
+    }
+
+    /// Queries players with a challenge obtained earlier, skipping the handshake unless the
+    /// server rejects it with a fresh one. Returns the challenge the server accepted, to reuse
+    /// for the next query, or `None` if the server didn't require one.
+    #[cfg(feature = "async")]
+    pub async fn players_with_challenge<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        challenge: Option<i32>,
+    ) -> Result<(Vec<Player>, Option<i32>)> {
+        self.players_for_app(addr, self.config.app_id, challenge)
+            .await
+    }
+
+    /// Queries players, parsing them for `app_id` rather than the client's configured app ID.
+    /// The Ship fields are still skipped if
+    /// [`A2SConfig::parse_the_ship`](crate::A2SConfig::parse_the_ship) is off.
+    #[cfg(feature = "async")]
+    pub(crate) async fn players_for_app<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        app_id: u16,
+        challenge: Option<i32>,
+    ) -> Result<(Vec<Player>, Option<i32>)> {
+        let (data, challenge) = self
+            .do_challenge_request(&addr, &PLAYER_REQUEST, challenge, &self.config)
+            .await?;
+        let ctx = self.parse_context(app_id);
+        let players = self
+            .parse_from(&addr, QueryKind::Players, &data, &ctx)
+            .await?;
+        Ok((players, challenge))
     }
 
     #[cfg(not(feature = "async"))]
     pub fn players<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Player>> {
-        let data = self.do_challenge_request(addr, &PLAYER_REQUEST)?;
-        Player::from_cursor(Cursor::new(data), self.app_id)
+        Ok(self.players_for_app(addr, self.config.app_id, None)?.0)
+    }
+
+    /// Queries players, accepting multi-packet responses split into fragments of up to
+    /// `buf_size` bytes instead of [`A2SConfig::max_size`](crate::A2SConfig::max_size), for
+    /// servers known to send huge player lists. The client's configuration is left untouched.
+    #[cfg(not(feature = "async"))]
+    pub fn players_with_buffer<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        buf_size: usize,
+    ) -> Result<Vec<Player>> {
+        let config = self.sized_config(buf_size);
+        let (data, _) = self.do_challenge_request(&addr, &PLAYER_REQUEST, None, &config)?;
+        let ctx = self.parse_context(self.config.app_id);
+        self.parse_from(&addr, QueryKind::Players, &data, &ctx)
+    }
+
+    /// Queries players with a challenge obtained earlier, skipping the handshake unless the
+    /// server rejects it with a fresh one. Returns the challenge the server accepted, to reuse
+    /// for the next query, or `None` if the server didn't require one.
+    #[cfg(not(feature = "async"))]
+    pub fn players_with_challenge<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        challenge: Option<i32>,
+    ) -> Result<(Vec<Player>, Option<i32>)> {
+        self.players_for_app(addr, self.config.app_id, challenge)
+    }
+
+    /// Queries players, parsing them for `app_id` rather than the client's configured app ID.
+    /// The Ship fields are still skipped if
+    /// [`A2SConfig::parse_the_ship`](crate::A2SConfig::parse_the_ship) is off.
+    #[cfg(not(feature = "async"))]
+    pub(crate) fn players_for_app<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        app_id: u16,
+        challenge: Option<i32>,
+    ) -> Result<(Vec<Player>, Option<i32>)> {
+        let (data, challenge) =
+            self.do_challenge_request(&addr, &PLAYER_REQUEST, challenge, &self.config)?;
+        let ctx = self.parse_context(app_id);
+        let players = self.parse_from(&addr, QueryKind::Players, &data, &ctx)?;
+        Ok((players, challenge))
     }
 }
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+#[cfg(not(feature = "async"))]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "async")]
+use tokio::task::JoinSet;
+
+use crate::errors::Result;
+use crate::info::Info;
+use crate::players::Player;
+use crate::rules::Rule;
+use crate::state::{ServerSession, ServerState};
+#[cfg(feature = "async")]
+use crate::unwind;
+use crate::{canonical_addr, A2SClient, A2SConfig};
+
+struct PooledClient {
+    client: Arc<A2SClient>,
+    last_used: Instant,
+}
+
+/// Keeps one client per server, each with its socket connected to that server, so that polling
+/// many servers on a schedule neither rebinds a socket per query nor mixes up their responses.
+/// Clients idle for longer than the TTL are dropped. Clients are keyed by [`canonical_addr`], use
+/// [`resolve_addr`](crate::resolve_addr) to query servers by name.
+///
+/// [`query_all_many`](Self::query_all_many) queries many servers at once. Every server queried
+/// holds a socket until its client expires, so the number of open sockets grows with the number
+/// of distinct servers queried within the TTL rather than with the concurrency. The concurrency
+/// only bounds the queries in flight, while all results are kept in memory until the last
+/// server answers or times out.
+pub struct ConnectedClientPool {
+    config: A2SConfig,
+    ttl: Duration,
+    clients: Mutex<HashMap<SocketAddr, PooledClient>>,
+}
+
+impl ConnectedClientPool {
+    /// Creates an empty pool whose clients use `config`. Its `bind_port` should be 0, as every
+    /// client binds its own socket.
+    pub fn new(config: A2SConfig, ttl: Duration) -> Self {
+        ConnectedClientPool {
+            config,
+            ttl,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of cached clients, idle ones included until the next query evicts them.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<SocketAddr, PooledClient>> {
+        self.clients.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the cached client connected to `addr`, evicting idle clients first.
+    fn cached(&self, addr: SocketAddr) -> Option<Arc<A2SClient>> {
+        let now = Instant::now();
+        let mut clients = self.lock();
+
+        clients.retain(|_, pooled| now.duration_since(pooled.last_used) <= self.ttl);
+
+        clients.get_mut(&canonical_addr(addr)).map(|pooled| {
+            pooled.last_used = now;
+            pooled.client.clone()
+        })
+    }
+
+    /// Caches a freshly connected client, unless another query raced it there.
+    fn insert(&self, addr: SocketAddr, client: A2SClient) -> Arc<A2SClient> {
+        self.lock()
+            .entry(canonical_addr(addr))
+            .or_insert_with(|| PooledClient {
+                client: Arc::new(client),
+                last_used: Instant::now(),
+            })
+            .client
+            .clone()
+    }
+}
+
+#[cfg(not(feature = "async"))]
+impl ConnectedClientPool {
+    /// The client connected to `addr`, created if there's none yet.
+    pub fn client(&self, addr: SocketAddr) -> Result<Arc<A2SClient>> {
+        if let Some(client) = self.cached(addr) {
+            return Ok(client);
+        }
+
+        let client = A2SClient::with_config(self.config.clone())?;
+        client.connect(addr)?;
+
+        Ok(self.insert(addr, client))
+    }
+
+    pub fn info(&self, addr: SocketAddr) -> Result<Info> {
+        self.client(addr)?.info(addr)
+    }
+
+    pub fn players(&self, addr: SocketAddr) -> Result<Vec<Player>> {
+        self.client(addr)?.players(addr)
+    }
+
+    pub fn rules(&self, addr: SocketAddr) -> Result<Vec<Rule>> {
+        self.client(addr)?.rules(addr)
+    }
+
+    /// Queries info, players and rules with a single challenge handshake.
+    pub fn query_all(&self, addr: SocketAddr) -> Result<ServerState> {
+        let client = self.client(addr)?;
+        let mut session = ServerSession::new(&client, addr);
+        session.refresh_all()
+    }
+
+    /// Queries info, players and rules of every server, at most `concurrency` servers at a
+    /// time, each on its own worker thread. Results are returned in the order of `addrs`.
+    pub fn query_all_many<I>(
+        &self,
+        addrs: I,
+        concurrency: usize,
+    ) -> Vec<(SocketAddr, Result<ServerState>)>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        let addrs: Vec<SocketAddr> = addrs.into_iter().collect();
+        let next = AtomicUsize::new(0);
+        let states = Mutex::new(pending(addrs.len()));
+
+        std::thread::scope(|scope| {
+            for _ in 0..concurrency.clamp(1, addrs.len().max(1)) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let addr = match addrs.get(index) {
+                        Some(&addr) => addr,
+                        None => break,
+                    };
+
+                    let state = self.query_all(addr);
+                    states.lock().unwrap_or_else(PoisonError::into_inner)[index] = Some(state);
+                });
+            }
+        });
+
+        finished(
+            addrs,
+            states.into_inner().unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+}
+
+#[cfg(feature = "async")]
+impl ConnectedClientPool {
+    /// The client connected to `addr`, created if there's none yet.
+    pub async fn client(&self, addr: SocketAddr) -> Result<Arc<A2SClient>> {
+        if let Some(client) = self.cached(addr) {
+            return Ok(client);
+        }
+
+        let client = A2SClient::with_config(self.config.clone()).await?;
+        client.connect(addr).await?;
+
+        Ok(self.insert(addr, client))
+    }
+
+    pub async fn info(&self, addr: SocketAddr) -> Result<Info> {
+        self.client(addr).await?.info(addr).await
+    }
+
+    pub async fn players(&self, addr: SocketAddr) -> Result<Vec<Player>> {
+        self.client(addr).await?.players(addr).await
+    }
+
+    pub async fn rules(&self, addr: SocketAddr) -> Result<Vec<Rule>> {
+        self.client(addr).await?.rules(addr).await
+    }
+
+    /// Queries info, players and rules with a single challenge handshake.
+    pub async fn query_all(&self, addr: SocketAddr) -> Result<ServerState> {
+        let client = self.client(addr).await?;
+        let mut session = ServerSession::new(&client, addr);
+        session.refresh_all().await
+    }
+
+    /// Queries info, players and rules of every server, at most `concurrency` servers at a
+    /// time, each in its own task. Results are returned in the order of `addrs`.
+    pub async fn query_all_many<I>(
+        &self,
+        addrs: I,
+        concurrency: usize,
+    ) -> Vec<(SocketAddr, Result<ServerState>)>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+    {
+        let addrs: Vec<SocketAddr> = addrs.into_iter().collect();
+        let mut states = pending(addrs.len());
+        let mut tasks = JoinSet::new();
+
+        for (index, &addr) in addrs.iter().enumerate() {
+            if tasks.len() >= concurrency.max(1) {
+                if let Some(joined) = tasks.join_next().await {
+                    let (index, state) = unwind(joined);
+                    states[index] = Some(state);
+                }
+            }
+
+            let client = self.client(addr).await;
+            tasks.spawn(async move {
+                let state = match client {
+                    Ok(client) => ServerSession::new(&client, addr).refresh_all().await,
+                    Err(err) => Err(err),
+                };
+                (index, state)
+            });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            let (index, state) = unwind(joined);
+            states[index] = Some(state);
+        }
+
+        finished(addrs, states)
+    }
+}
+
+/// A slot per server for its result, filled in as its query finishes.
+fn pending(len: usize) -> Vec<Option<Result<ServerState>>> {
+    (0..len).map(|_| None).collect()
+}
+
+/// Pairs every server with its result, in the order the servers were given.
+fn finished(
+    addrs: Vec<SocketAddr>,
+    states: Vec<Option<Result<ServerState>>>,
+) -> Vec<(SocketAddr, Result<ServerState>)> {
+    addrs
+        .into_iter()
+        .zip(states)
+        .map(|(addr, state)| (addr, state.expect("every server is queried")))
+        .collect()
+}
@@ -0,0 +1,131 @@
+#[cfg(not(feature = "async"))]
+use std::net::ToSocketAddrs;
+
+#[cfg(feature = "async")]
+use tokio::net::ToSocketAddrs;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{Error, Result};
+use crate::info::INFO_REQUEST;
+use crate::players::PLAYER_REQUEST;
+use crate::{is_info_response, read_challenge, request_packet, A2SClient};
+
+/// How a server handles challenges, as observed by [`A2SClient::probe_challenge_behavior`].
+/// Valve changed the handshake several times, so servers of different builds disagree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ChallengeBehavior {
+    /// Answers players queries without a challenge, as some old or custom servers do.
+    /// [`ChallengeMode::OnDemand`](crate::ChallengeMode::OnDemand) saves them a round trip.
+    NoChallenge,
+
+    /// Hands out a challenge once and accepts it, the usual players and rules handshake.
+    SingleChallenge,
+
+    /// Hands out a fresh challenge again when sent the one it just gave, so queries never
+    /// complete. Usually a misbehaving proxy or a server rotating challenges too fast.
+    RepeatedChallenge,
+
+    /// Requires a challenge for info queries too, as Source servers do since the December 2020
+    /// update. [`A2SClient::info`] handles it with an extra round trip.
+    ChallengeOnInfo,
+}
+
+impl A2SClient {
+    /// Classifies how the server at `addr` handles challenges by sending a few probes: an info
+    /// query, then a players query without a challenge, and with the one handed out if any.
+    /// A debugging aid for servers that behave oddly, regular queries don't need it.
+    #[cfg(feature = "async")]
+    pub async fn probe_challenge_behavior<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+    ) -> Result<ChallengeBehavior> {
+        let data = self.send(&INFO_REQUEST, &addr).await?;
+        if self.challenge_of(&data).is_some() {
+            return Ok(ChallengeBehavior::ChallengeOnInfo);
+        }
+
+        let data = self.send(&PLAYER_REQUEST, &addr).await?;
+        let challenge = match self.challenge_of(&data) {
+            Some(challenge) => challenge,
+            None => return Ok(ChallengeBehavior::NoChallenge),
+        };
+
+        let data = self
+            .send(&request_packet(&PLAYER_REQUEST, Some(challenge)), &addr)
+            .await?;
+        Ok(classify(self.challenge_of(&data)))
+    }
+
+    /// Classifies how the server at `addr` handles challenges by sending a few probes: an info
+    /// query, then a players query without a challenge, and with the one handed out if any.
+    /// A debugging aid for servers that behave oddly, regular queries don't need it.
+    #[cfg(not(feature = "async"))]
+    pub fn probe_challenge_behavior<A: ToSocketAddrs>(&self, addr: A) -> Result<ChallengeBehavior> {
+        let data = self.send(&INFO_REQUEST, &addr)?;
+        if self.challenge_of(&data).is_some() {
+            return Ok(ChallengeBehavior::ChallengeOnInfo);
+        }
+
+        let data = self.send(&PLAYER_REQUEST, &addr)?;
+        let challenge = match self.challenge_of(&data) {
+            Some(challenge) => challenge,
+            None => return Ok(ChallengeBehavior::NoChallenge),
+        };
+
+        let data = self.send(&request_packet(&PLAYER_REQUEST, Some(challenge)), &addr)?;
+        Ok(classify(self.challenge_of(&data)))
+    }
+
+    /// Whether the server at `addr` can be abused to reflect traffic, checked by sending a bare
+    /// info request. Servers answering it directly are: anyone spoofing a victim's address
+    /// gets a response several times the size of the request sent to the victim, an
+    /// amplification attack. Servers patched against it (Source servers since the December
+    /// 2020 update) demand a challenge first, which only reaches the real sender.
+    ///
+    /// Fails with [`Error::InvalidResponse`] if the server answers with neither info nor a
+    /// challenge.
+    #[cfg(feature = "async")]
+    pub async fn is_reflection_vulnerable<A: ToSocketAddrs>(&self, addr: A) -> Result<bool> {
+        let data = self.send(&INFO_REQUEST, &addr).await?;
+        answered_directly(self.challenge_of(&data), &data)
+    }
+
+    /// Whether the server at `addr` can be abused to reflect traffic, checked by sending a bare
+    /// info request. Servers answering it directly are: anyone spoofing a victim's address
+    /// gets a response several times the size of the request sent to the victim, an
+    /// amplification attack. Servers patched against it (Source servers since the December
+    /// 2020 update) demand a challenge first, which only reaches the real sender.
+    ///
+    /// Fails with [`Error::InvalidResponse`] if the server answers with neither info nor a
+    /// challenge.
+    #[cfg(not(feature = "async"))]
+    pub fn is_reflection_vulnerable<A: ToSocketAddrs>(&self, addr: A) -> Result<bool> {
+        let data = self.send(&INFO_REQUEST, &addr)?;
+        answered_directly(self.challenge_of(&data), &data)
+    }
+
+    /// The challenge a response hands out, `None` if it's an actual response.
+    fn challenge_of(&self, data: &[u8]) -> Option<i32> {
+        read_challenge(data, self.config.challenge_header)
+    }
+}
+
+/// Whether an info request was answered with info rather than a `challenge`.
+fn answered_directly(challenge: Option<i32>, data: &[u8]) -> Result<bool> {
+    match challenge {
+        Some(_) => Ok(false),
+        None if is_info_response(data) => Ok(true),
+        None => Err(Error::InvalidResponse),
+    }
+}
+
+/// Classifies the answer to a request carrying the challenge the server handed out.
+fn classify(challenge: Option<i32>) -> ChallengeBehavior {
+    match challenge {
+        Some(_) => ChallengeBehavior::RepeatedChallenge,
+        None => ChallengeBehavior::SingleChallenge,
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(all(feature = "std", not(feature = "async")))]
+use std::net::ToSocketAddrs;
+
+#[cfg(feature = "async")]
+use tokio::net::ToSocketAddrs;
+
+use crate::errors::Result;
+use crate::info::{Info, INFO_REQUEST};
+use crate::players::{Player, PLAYER_REQUEST};
+use crate::rules::{Rule, RULES_REQUEST};
+#[cfg(feature = "std")]
+use crate::A2SClient;
+use crate::{ParseContext, ParseResponse};
+
+/// A query sent with [`A2SClient::query`], for plugging in queries this crate doesn't know
+/// about while reusing its transport: retries, multi-packet reassembly and challenges.
+pub trait Query {
+    type Output;
+
+    /// The request, header (`0xFFFFFFFF`) included but without any challenge.
+    fn request_bytes(&self) -> Vec<u8>;
+
+    /// Whether the request may need a challenge, in which case it's obtained as
+    /// [`A2SConfig::challenge_mode`](crate::A2SConfig::challenge_mode) says. Queries that don't
+    /// are still answered if the server asks for one anyway.
+    fn needs_challenge(&self) -> bool;
+
+    /// Parses the reassembled response, without its packet header.
+    fn parse(&self, bytes: &[u8], ctx: &ParseContext) -> Result<Self::Output>;
+}
+
+/// A2S_INFO, see [`A2SClient::info`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InfoQuery;
+
+/// A2S_PLAYER, see [`A2SClient::players`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlayersQuery;
+
+/// A2S_RULES, see [`A2SClient::rules`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RulesQuery;
+
+impl Query for InfoQuery {
+    type Output = Info;
+
+    fn request_bytes(&self) -> Vec<u8> {
+        INFO_REQUEST.to_vec()
+    }
+
+    fn needs_challenge(&self) -> bool {
+        false
+    }
+
+    fn parse(&self, bytes: &[u8], ctx: &ParseContext) -> Result<Info> {
+        Info::parse(bytes, ctx)
+    }
+}
+
+impl Query for PlayersQuery {
+    type Output = Vec<Player>;
+
+    fn request_bytes(&self) -> Vec<u8> {
+        PLAYER_REQUEST.to_vec()
+    }
+
+    fn needs_challenge(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, bytes: &[u8], ctx: &ParseContext) -> Result<Vec<Player>> {
+        Vec::parse(bytes, ctx)
+    }
+}
+
+impl Query for RulesQuery {
+    type Output = Vec<Rule>;
+
+    fn request_bytes(&self) -> Vec<u8> {
+        RULES_REQUEST.to_vec()
+    }
+
+    fn needs_challenge(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, bytes: &[u8], ctx: &ParseContext) -> Result<Vec<Rule>> {
+        Vec::parse(bytes, ctx)
+    }
+}
+
+#[cfg(feature = "std")]
+impl A2SClient {
+    /// Sends `query` and parses its response, in the client's parse context.
+    #[cfg(feature = "async")]
+    pub async fn query<Q: Query, A: ToSocketAddrs>(&self, query: Q, addr: A) -> Result<Q::Output> {
+        let request = query.request_bytes();
+        let data = if query.needs_challenge() {
+            self.do_challenge_request(addr, &request, None, &self.config)
+                .await?
+                .0
+        } else {
+            self.do_optional_challenge_request(addr, &request, None)
+                .await?
+                .0
+        };
+
+        query.parse(&data, &self.parse_context(self.config.app_id))
+    }
+
+    /// Sends `query` and parses its response, in the client's parse context.
+    #[cfg(not(feature = "async"))]
+    pub fn query<Q: Query, A: ToSocketAddrs>(&self, query: Q, addr: A) -> Result<Q::Output> {
+        let request = query.request_bytes();
+        let data = if query.needs_challenge() {
+            self.do_challenge_request(addr, &request, None, &self.config)?
+                .0
+        } else {
+            self.do_optional_challenge_request(addr, &request, None)?.0
+        };
+
+        query.parse(&data, &self.parse_context(self.config.app_id))
+    }
+}
@@ -0,0 +1,138 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+#[cfg(feature = "codec")]
+use core::convert::TryFrom;
+use core::convert::TryInto;
+#[cfg(feature = "codec")]
+use std::io::Cursor;
+
+use crate::errors::{Error, Result};
+
+/// Reads the little-endian fields of a payload from a borrowed buffer, for parsing responses
+/// without copying them, e.g. in a [`Query`](crate::query::Query). Reading past the end fails
+/// with [`Error::UnexpectedEof`].
+#[derive(Debug, Clone)]
+pub struct BytesReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BytesReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        BytesReader { data, position: 0 }
+    }
+
+    /// A reader over the bytes of `cursor`, starting at its position.
+    #[cfg(feature = "codec")]
+    pub fn from_cursor(cursor: &'a Cursor<Vec<u8>>) -> Self {
+        let mut reader = Self::new(cursor.get_ref());
+        reader.set_position(usize::try_from(cursor.position()).unwrap_or(usize::MAX));
+        reader
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Moves to `position`, clamped to the end of the buffer.
+    pub fn set_position(&mut self, position: usize) {
+        self.position = position.min(self.data.len());
+    }
+
+    /// Number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// The next `len` bytes.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            return Err(Error::UnexpectedEof);
+        }
+
+        let bytes = &self.data[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    /// Every byte left.
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+        rest
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self
+            .read_bytes(N)?
+            .try_into()
+            .expect("read_bytes returns exactly N bytes"))
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_u16_le(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i32_le(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32_le(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64_le(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_f32_le(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.read_array()?))
+    }
+
+    /// Reads a null-terminated string, decoding invalid UTF-8 lossily. A string running up to
+    /// the end of the buffer without a terminator is accepted.
+    pub fn read_cstring(&mut self) -> Result<String> {
+        Ok(self.read_cstr()?.into_owned())
+    }
+
+    /// Reads a null-terminated string like [`read_cstring`](Self::read_cstring), borrowing it
+    /// from the buffer. Only invalid UTF-8 is copied, to be decoded lossily.
+    pub fn read_cstr(&mut self) -> Result<Cow<'a, str>> {
+        let bytes = self.read_cstr_bytes();
+        Ok(match core::str::from_utf8(bytes) {
+            Ok(string) => Cow::Borrowed(string),
+            Err(_) => String::from_utf8_lossy(bytes),
+        })
+    }
+
+    /// Skips a null-terminated string without decoding it.
+    pub(crate) fn skip_cstring(&mut self) {
+        self.read_cstr_bytes();
+    }
+
+    /// The bytes of a null-terminated string, without the terminator.
+    fn read_cstr_bytes(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        let len = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+
+        self.position += (len + 1).min(rest.len());
+        &rest[..len]
+    }
+
+    /// Skips a single packet header (`0xFFFFFFFF`) in front of a payload, which some relays
+    /// leave on or add again. Payloads never start with 0xFF, so this can't skip into an actual
+    /// one.
+    pub(crate) fn skip_packet_header(&mut self) {
+        if self.data[self.position..].starts_with(&[0xff; 4]) {
+            self.position += 4;
+        }
+    }
+}
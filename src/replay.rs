@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::transport::AsyncDatagram;
+
+/// A transport replaying datagrams captured from a server, to reproduce its responses byte for
+/// byte without network access, e.g. with [`query_transport`](crate::transport::query_transport).
+///
+/// Captures are a sequence of records, one per datagram received from the server, each being
+/// its length as a little-endian `u32` followed by the datagram itself. Requests sent are
+/// discarded, and receiving once every datagram was replayed fails with
+/// [`io::ErrorKind::TimedOut`], as a silent server would.
+#[derive(Debug, Default)]
+pub struct ReplaySocket {
+    datagrams: Mutex<VecDeque<Vec<u8>>>,
+}
+
+impl ReplaySocket {
+    pub fn new(datagrams: Vec<Vec<u8>>) -> Self {
+        ReplaySocket {
+            datagrams: Mutex::new(datagrams.into()),
+        }
+    }
+
+    /// Reads a capture file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a capture, up to the end of `reader`.
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut datagrams = Vec::new();
+
+        loop {
+            let len = match reader.read_u32::<LittleEndian>() {
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            };
+
+            let mut datagram = Vec::new();
+            reader
+                .by_ref()
+                .take(len.into())
+                .read_to_end(&mut datagram)?;
+            if datagram.len() != len as usize {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            datagrams.push(datagram);
+        }
+
+        Ok(Self::new(datagrams))
+    }
+
+    /// Number of datagrams left to replay.
+    pub fn remaining(&self) -> usize {
+        self.datagrams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+impl AsyncDatagram for ReplaySocket {
+    async fn send(&self, _data: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn recv(&self) -> impl Future<Output = io::Result<Vec<u8>>> {
+        let datagram = self
+            .datagrams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front();
+
+        async {
+            datagram.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::TimedOut, "every datagram was replayed")
+            })
+        }
+    }
+}
+
+/// Appends a datagram received from a server to a capture.
+pub fn write_record<W: Write>(writer: &mut W, datagram: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(datagram.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "datagram too large"))?;
+
+    writer.write_u32::<LittleEndian>(len)?;
+    writer.write_all(datagram)
+}
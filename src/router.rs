@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::sync::{mpsc, Mutex as AsyncMutex, OwnedMutexGuard};
+use tokio::task::JoinHandle;
+
+use crate::buffers::PooledBuffer;
+use crate::errors::{Error, Result};
+use crate::SourceMatch;
+
+type Sender = mpsc::Sender<Result<Vec<u8>>>;
+
+/// Wait after a receive error, doubled with each consecutive one up to [`MAX_ERROR_BACKOFF`],
+/// so that a socket failing persistently doesn't spin the receive task.
+const ERROR_BACKOFF: Duration = Duration::from_millis(1);
+
+const MAX_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// What the receive task reads datagrams from, the client's [`UdpSocket`].
+trait RecvSocket: Send + Sync + 'static {
+    fn recv_from<'a>(
+        &'a self,
+        buf: &'a mut [u8],
+    ) -> impl Future<Output = io::Result<(usize, SocketAddr)>> + Send + 'a;
+
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+}
+
+impl RecvSocket for UdpSocket {
+    fn recv_from<'a>(
+        &'a self,
+        buf: &'a mut [u8],
+    ) -> impl Future<Output = io::Result<(usize, SocketAddr)>> + Send + 'a {
+        UdpSocket::recv_from(self, buf)
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        UdpSocket::peer_addr(self)
+    }
+}
+
+#[derive(Default)]
+struct Routes {
+    /// Queries currently waiting for datagrams, keyed by destination, with how they match the
+    /// source of datagrams
+    senders: HashMap<SocketAddr, (Sender, SourceMatch)>,
+
+    /// Serializes queries to the same destination, as single packet responses carry no ID that
+    /// could tell them apart
+    locks: HashMap<SocketAddr, Arc<AsyncMutex<()>>>,
+}
+
+impl Routes {
+    /// The query a datagram from `source` is for: the one sent to that exact address, or else
+    /// a lenient one sent to the same IP.
+    fn route(&self, source: SocketAddr) -> Option<&Sender> {
+        match self.senders.get(&source) {
+            Some((sender, _)) => Some(sender),
+            None => self
+                .senders
+                .iter()
+                .find(|(&addr, &(_, source_match))| source_match.matches(addr, source))
+                .map(|(_, (sender, _))| sender),
+        }
+    }
+}
+
+fn lock(routes: &Mutex<Routes>) -> MutexGuard<'_, Routes> {
+    routes.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Multiplexes queries over a single shared socket, with a background task routing every
+/// received datagram to the query waiting on its source address.
+pub(crate) struct Router {
+    socket: Arc<UdpSocket>,
+    routes: Arc<Mutex<Routes>>,
+    task: JoinHandle<()>,
+}
+
+impl Router {
+    /// Starts routing the datagrams `socket` receives, into `buf`.
+    pub(crate) fn new(socket: UdpSocket, buf: PooledBuffer) -> Self {
+        let socket = Arc::new(socket);
+        let routes = Arc::new(Mutex::new(Routes::default()));
+        let task = tokio::spawn(recv_loop(socket.clone(), routes.clone(), buf));
+
+        Router {
+            socket,
+            routes,
+            task,
+        }
+    }
+
+    pub(crate) fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Registers a query to `addr`, waiting for any query already in flight to it to finish.
+    /// At most `capacity` datagrams are queued for it, any more arriving before it reads them
+    /// are discarded, so a peer flooding the socket can't grow memory without bound.
+    pub(crate) async fn register(
+        &self,
+        addr: SocketAddr,
+        source_match: SourceMatch,
+        capacity: usize,
+    ) -> Route {
+        let destination = lock(&self.routes).locks.entry(addr).or_default().clone();
+        let guard = destination.lock_owned().await;
+
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        lock(&self.routes)
+            .senders
+            .insert(addr, (sender, source_match));
+
+        Route {
+            routes: self.routes.clone(),
+            addr,
+            receiver,
+            guard: Some(guard),
+        }
+    }
+}
+
+impl Router {
+    /// Stops the receive task and waits for it to finish, releasing its handle on the socket.
+    pub(crate) async fn shutdown(mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+    }
+}
+
+impl Drop for Router {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Datagrams received from a destination for the duration of a single query.
+pub(crate) struct Route {
+    routes: Arc<Mutex<Routes>>,
+    addr: SocketAddr,
+    receiver: mpsc::Receiver<Result<Vec<u8>>>,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl Route {
+    pub(crate) async fn recv(&mut self) -> Result<Vec<u8>> {
+        self.receiver
+            .recv()
+            .await
+            .unwrap_or(Err(Error::Other("Receive task stopped")))
+    }
+}
+
+impl Drop for Route {
+    fn drop(&mut self) {
+        let mut routes = lock(&self.routes);
+        routes.senders.remove(&self.addr);
+
+        // Release the destination before checking whether anyone else is waiting on it
+        drop(self.guard.take());
+        if let Some(destination) = routes.locks.get(&self.addr) {
+            if Arc::strong_count(destination) == 1 {
+                routes.locks.remove(&self.addr);
+            }
+        }
+    }
+}
+
+async fn recv_loop<S: RecvSocket>(
+    socket: Arc<S>,
+    routes: Arc<Mutex<Routes>>,
+    mut buf: PooledBuffer,
+) {
+    let mut backoff = ERROR_BACKOFF;
+    loop {
+        let (read, from) = match socket.recv_from(&mut buf).await {
+            Ok(received) => {
+                backoff = ERROR_BACKOFF;
+                received
+            }
+            Err(err) => {
+                let (kind, message) = (err.kind(), err.to_string());
+                match Error::from_socket(err) {
+                    // ICMP errors caused by an earlier send surface here on some platforms. They
+                    // carry no source address, so they can only be blamed on a destination if
+                    // the socket is connected to it
+                    Error::ConnectionRefused => {
+                        if let Ok(peer) = socket.peer_addr() {
+                            if let Some((sender, _)) = lock(&routes).senders.get(&peer) {
+                                let _ = sender.try_send(Err(Error::ConnectionRefused));
+                            }
+                        }
+                    }
+                    // Any other error fails the queries waiting rather than ending the task,
+                    // which would leave them and every later query to time out without a word.
+                    // Unlike refusals, which each answer a send, it may well repeat on every
+                    // receive, hence the backoff
+                    _ => {
+                        for (sender, _) in lock(&routes).senders.values() {
+                            let err = io::Error::new(kind, message.clone());
+                            let _ = sender.try_send(Err(Error::Io(err)));
+                        }
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_ERROR_BACKOFF);
+                    }
+                }
+                continue;
+            }
+        };
+
+        // Datagrams nobody is waiting for (late fragments of dropped queries, strays) are
+        // discarded, as are those beyond what a query has room for
+        if let Some(sender) = lock(&routes).route(from) {
+            let _ = sender.try_send(Ok(buf[..read].to_vec()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::buffers::BufferPool;
+
+    /// A socket whose every receive fails, counting them.
+    #[derive(Default)]
+    struct FailingSocket(AtomicUsize);
+
+    impl RecvSocket for FailingSocket {
+        async fn recv_from(&self, _buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            // Lets the test run on the single threaded runtime even if the loop spins
+            tokio::task::yield_now().await;
+            Err(io::ErrorKind::Other.into())
+        }
+
+        fn peer_addr(&self) -> io::Result<SocketAddr> {
+            Err(io::ErrorKind::NotConnected.into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recv_errors_back_off() {
+        let socket = Arc::new(FailingSocket::default());
+        let routes = Arc::new(Mutex::new(Routes::default()));
+        let (sender, mut receiver) = mpsc::channel(1);
+        lock(&routes).senders.insert(
+            ([127, 0, 0, 1], 27015).into(),
+            (sender, SourceMatch::Strict),
+        );
+
+        let buf = Arc::new(BufferPool::default()).get(1);
+        let task = tokio::spawn(recv_loop(socket.clone(), routes, buf));
+
+        // The query waiting hears of the error
+        assert!(matches!(receiver.recv().await, Some(Err(Error::Io(_)))));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        task.abort();
+
+        // A few receives with the wait doubling each time, rather than one per iteration
+        let receives = socket.0.load(Ordering::Relaxed);
+        assert!(receives < 20, "{} receives", receives);
+    }
+}
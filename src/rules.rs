@@ -1,9 +1,11 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "codec")]
 use std::io::Cursor;
-#[cfg(not(feature = "async"))]
+#[cfg(all(feature = "std", not(feature = "async")))]
 use std::net::ToSocketAddrs;
 
-use byteorder::{LittleEndian, ReadBytesExt};
-
 #[cfg(feature = "async")]
 use tokio::net::ToSocketAddrs;
 
@@ -11,11 +13,15 @@ use tokio::net::ToSocketAddrs;
 use serde::{Deserialize, Serialize};
 
 use crate::errors::{Error, Result};
-use crate::{A2SClient, ReadCString};
+use crate::reader::BytesReader;
+#[cfg(feature = "std")]
+use crate::{challenge_packet, A2SClient, QueryKind};
+use crate::{with_dump, ParseContext, ParseResponse};
 
-const RULES_REQUEST: [u8; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x56];
+#[cfg(feature = "codec")]
+pub(crate) const RULES_REQUEST: [u8; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x56];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Rule {
     /// Name of the rule.
@@ -25,6 +31,24 @@ pub struct Rule {
     pub value: String,
 }
 
+/// A rule borrowing its name and value from the payload it was parsed from, see
+/// [`Rule::parse_borrowed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleRef<'a> {
+    pub name: Cow<'a, str>,
+
+    pub value: Cow<'a, str>,
+}
+
+impl RuleRef<'_> {
+    pub fn to_rule(&self) -> Rule {
+        Rule {
+            name: self.name.clone().into_owned(),
+            value: self.value.clone().into_owned(),
+        }
+    }
+}
+
 impl Rule {
     pub fn vec_to_bytes(rules: Vec<Self>) -> Vec<u8> {
         let mut bytes = Vec::new();
@@ -51,12 +75,56 @@ impl Rule {
         bytes
     }
 
-    pub fn from_cursor(mut data: Cursor<Vec<u8>>) -> Result<Vec<Self>> {
+    /// Changes from `old` to `new`, such as two polls of the same server, keyed by rule name:
+    /// added and changed rules in the order of `new`, then removed ones. If a name is repeated,
+    /// only its first occurrence is considered.
+    pub fn diff(old: &[Rule], new: &[Rule]) -> Vec<RuleChange> {
+        let first = |rules: &[Rule], name: &str| rules.iter().find(|r| r.name == name).cloned();
+
+        let mut changes = Vec::new();
+
+        for (i, rule) in new.iter().enumerate() {
+            if new[..i].iter().any(|r| r.name == rule.name) {
+                continue;
+            }
+
+            match first(old, &rule.name) {
+                None => changes.push(RuleChange::Added(rule.clone())),
+                Some(previous) if previous.value != rule.value => {
+                    changes.push(RuleChange::Changed {
+                        name: rule.name.clone(),
+                        old: previous.value,
+                        new: rule.value.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (i, rule) in old.iter().enumerate() {
+            if old[..i].iter().all(|r| r.name != rule.name) && first(new, &rule.name).is_none() {
+                changes.push(RuleChange::Removed(rule.clone()));
+            }
+        }
+
+        changes
+    }
+
+    /// Parses a rules payload, skipping a leading single packet header if a relay left one.
+    #[cfg(feature = "codec")]
+    pub fn from_cursor(data: Cursor<Vec<u8>>) -> Result<Vec<Self>> {
+        Self::from_reader(&mut BytesReader::from_cursor(&data))
+    }
+
+    /// Parses a rules payload like [`from_cursor`](Self::from_cursor), from a borrowed buffer.
+    pub fn from_reader(data: &mut BytesReader) -> Result<Vec<Self>> {
+        data.skip_packet_header();
+
         if data.read_u8()? != 0x45 {
             return Err(Error::InvalidResponse);
         }
 
-        let count = data.read_u16::<LittleEndian>()?;
+        let count = data.read_u16_le()?;
 
         let mut rules: Vec<Rule> = Vec::with_capacity(count as usize);
 
@@ -69,18 +137,144 @@ impl Rule {
 
         Ok(rules)
     }
+
+    /// Parses a rules payload like [`from_reader`](Self::from_reader), borrowing names and
+    /// values from `payload` rather than allocating a string for each. Only the list itself is
+    /// allocated, along with the strings that aren't valid UTF-8, decoded lossily. Meant for
+    /// scanners parsing many large responses, which can then copy the few rules they keep.
+    pub fn parse_borrowed(payload: &[u8]) -> Result<Vec<RuleRef<'_>>> {
+        Self::read_borrowed(&mut BytesReader::new(payload))
+    }
+
+    fn read_borrowed<'a>(data: &mut BytesReader<'a>) -> Result<Vec<RuleRef<'a>>> {
+        data.skip_packet_header();
+
+        if data.read_u8()? != 0x45 {
+            return Err(Error::InvalidResponse);
+        }
+
+        let count = data.read_u16_le()?;
+
+        let mut rules = Vec::with_capacity(count as usize);
+
+        for _ in 0..count {
+            rules.push(RuleRef {
+                name: data.read_cstr()?,
+                value: data.read_cstr()?,
+            })
+        }
+
+        Ok(rules)
+    }
 }
 
+impl ParseResponse for Vec<Rule> {
+    fn parse(bytes: &[u8], ctx: &ParseContext) -> Result<Self> {
+        with_dump(bytes, Rule::from_reader(&mut BytesReader::new(bytes)), ctx)
+    }
+}
+
+#[cfg(feature = "std")]
 impl A2SClient {
+    /// The exact datagram [`rules`](Self::rules) sends first, or with `challenge` the one
+    /// answering a server's challenge, e.g. to replay the query with other tools. Without a
+    /// challenge, it carries the `-1` probe if [`ChallengeMode::Always`](crate::ChallengeMode::Always)
+    /// is configured.
+    pub fn rules_request_bytes(&self, challenge: Option<i32>) -> Vec<u8> {
+        challenge_packet(&RULES_REQUEST, challenge, &self.config)
+    }
+
     #[cfg(feature = "async")]
     pub async fn rules<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Rule>> {
-        let data = self.do_challenge_request(addr, &RULES_REQUEST).await?;
-        Rule::from_cursor(Cursor::new(data))
+        Ok(self.rules_with_challenge(addr, None).await?.0)
+    }
+
+    /// Queries rules, accepting multi-packet responses split into fragments of up to
+    /// `buf_size` bytes instead of [`A2SConfig::max_size`](crate::A2SConfig::max_size), for
+    /// servers known to send huge rule lists. The client's configuration is left untouched.
+    #[cfg(feature = "async")]
+    pub async fn rules_with_buffer<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        buf_size: usize,
+    ) -> Result<Vec<Rule>> {
+        let config = self.sized_config(buf_size);
+        let (data, _) = self
+            .do_challenge_request(&addr, &RULES_REQUEST, None, &config)
+            .await?;
+        let ctx = self.parse_context(self.config.app_id);
+        self.parse_from(&addr, QueryKind::Rules, &data, &ctx).await
+    }
+
+    /// Queries rules with a challenge obtained earlier, skipping the handshake unless the server
+    /// rejects it with a fresh one. Returns the challenge the server accepted, to reuse for the
+    /// next query, or `None` if the server didn't require one.
+    #[cfg(feature = "async")]
+    pub async fn rules_with_challenge<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        challenge: Option<i32>,
+    ) -> Result<(Vec<Rule>, Option<i32>)> {
+        let (data, challenge) = self
+            .do_challenge_request(&addr, &RULES_REQUEST, challenge, &self.config)
+            .await?;
+        let ctx = self.parse_context(self.config.app_id);
+        let rules = self
+            .parse_from(&addr, QueryKind::Rules, &data, &ctx)
+            .await?;
+        Ok((rules, challenge))
     }
 
     #[cfg(not(feature = "async"))]
     pub fn rules<A: ToSocketAddrs>(&self, addr: A) -> Result<Vec<Rule>> {
-        let data = self.do_challenge_request(addr, &RULES_REQUEST)?;
-        Rule::from_cursor(Cursor::new(data))
+        Ok(self.rules_with_challenge(addr, None)?.0)
+    }
+
+    /// Queries rules, accepting multi-packet responses split into fragments of up to
+    /// `buf_size` bytes instead of [`A2SConfig::max_size`](crate::A2SConfig::max_size), for
+    /// servers known to send huge rule lists. The client's configuration is left untouched.
+    #[cfg(not(feature = "async"))]
+    pub fn rules_with_buffer<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        buf_size: usize,
+    ) -> Result<Vec<Rule>> {
+        let config = self.sized_config(buf_size);
+        let (data, _) = self.do_challenge_request(&addr, &RULES_REQUEST, None, &config)?;
+        let ctx = self.parse_context(self.config.app_id);
+        self.parse_from(&addr, QueryKind::Rules, &data, &ctx)
+    }
+
+    /// Queries rules with a challenge obtained earlier, skipping the handshake unless the server
+    /// rejects it with a fresh one. Returns the challenge the server accepted, to reuse for the
+    /// next query, or `None` if the server didn't require one.
+    #[cfg(not(feature = "async"))]
+    pub fn rules_with_challenge<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        challenge: Option<i32>,
+    ) -> Result<(Vec<Rule>, Option<i32>)> {
+        let (data, challenge) =
+            self.do_challenge_request(&addr, &RULES_REQUEST, challenge, &self.config)?;
+        let ctx = self.parse_context(self.config.app_id);
+        let rules = self.parse_from(&addr, QueryKind::Rules, &data, &ctx)?;
+        Ok((rules, challenge))
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum RuleChange {
+    /// A rule that wasn't present before.
+    Added(Rule),
+
+    /// A rule that is no longer present.
+    Removed(Rule),
+
+    /// A rule whose value changed.
+    Changed {
+        name: String,
+        old: String,
+        new: String,
+    },
+}
@@ -0,0 +1,163 @@
+use std::net::SocketAddr;
+#[cfg(feature = "async")]
+use std::sync::Arc;
+#[cfg(not(feature = "async"))]
+use std::sync::{mpsc, Mutex, PoisonError};
+
+#[cfg(feature = "async")]
+use tokio::task::JoinSet;
+
+use crate::errors::Result;
+use crate::info::Info;
+#[cfg(feature = "async")]
+use crate::unwind;
+use crate::A2SClient;
+#[cfg(not(feature = "async"))]
+use crate::A2SConfig;
+
+impl A2SClient {
+    /// Queries the info of every server, at most `concurrency` at a time, handing each result
+    /// to `callback` as soon as it's in. Nothing is accumulated, so memory stays bounded by the
+    /// concurrency however many servers are scanned, and `addrs` may be a lazy iterator over a
+    /// whole address range.
+    ///
+    /// The queries run on their own worker threads, each binding its own socket with this
+    /// client's configuration (on an ephemeral port). `callback` is called on the calling
+    /// thread, one result at a time, so it needn't be thread-safe. Workers wait while it runs.
+    #[cfg(not(feature = "async"))]
+    pub fn scan<I, F>(&self, addrs: I, concurrency: usize, mut callback: F) -> Result<()>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+        I::IntoIter: Send,
+        F: FnMut(SocketAddr, Result<Info>),
+    {
+        let config = A2SConfig {
+            bind_port: 0,
+            ..self.config.clone()
+        };
+        let workers = (0..concurrency.max(1))
+            .map(|_| {
+                let mut worker = A2SClient::with_config(config.clone())?;
+                worker.clock = self.clock.clone();
+                Ok(worker)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let addrs = Mutex::new(addrs.into_iter());
+        let (sender, receiver) = mpsc::sync_channel(workers.len());
+
+        std::thread::scope(|scope| {
+            for worker in &workers {
+                let sender = sender.clone();
+                let addrs = &addrs;
+                scope.spawn(move || loop {
+                    let addr = match addrs.lock().unwrap_or_else(PoisonError::into_inner).next() {
+                        Some(addr) => addr,
+                        None => break,
+                    };
+
+                    if sender.send((addr, worker.info(addr))).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            for (addr, info) in receiver {
+                callback(addr, info);
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Queries the info of every server, at most `concurrency` at a time, handing each result
+    /// to `callback` as soon as it's in. Nothing is accumulated, so memory stays bounded by the
+    /// concurrency however many servers are scanned, and `addrs` may be a lazy iterator over a
+    /// whole address range.
+    ///
+    /// The queries run in their own tasks, sharing this client's socket. `callback` is called
+    /// from the task awaiting the scan, one result at a time, so it needn't be `Send` or `Sync`.
+    /// It should return quickly though: no new query starts while it runs.
+    #[cfg(feature = "async")]
+    pub async fn scan<I, F>(self: &Arc<Self>, addrs: I, concurrency: usize, mut callback: F)
+    where
+        I: IntoIterator<Item = SocketAddr>,
+        F: FnMut(SocketAddr, Result<Info>),
+    {
+        let mut tasks = JoinSet::new();
+
+        for addr in addrs {
+            if tasks.len() >= concurrency.max(1) {
+                if let Some(joined) = tasks.join_next().await {
+                    let (addr, info) = unwind(joined);
+                    callback(addr, info);
+                }
+            }
+
+            let client = self.clone();
+            tasks.spawn(async move { (addr, client.info(addr).await) });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            let (addr, info) = unwind(joined);
+            callback(addr, info);
+        }
+    }
+
+    /// Scans like [`scan`](Self::scan), only handing over servers with at least
+    /// `min_players` [`humans`](Info::humans), e.g. 1 to list non-empty servers. Failed queries
+    /// are still handed over.
+    ///
+    /// The protocol has no way to ask a server for its info only if it has players, so every
+    /// server is fully queried and filtered afterwards.
+    #[cfg(not(feature = "async"))]
+    pub fn scan_min_players<I, F>(
+        &self,
+        addrs: I,
+        concurrency: usize,
+        min_players: u8,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = SocketAddr>,
+        I::IntoIter: Send,
+        F: FnMut(SocketAddr, Result<Info>),
+    {
+        self.scan(addrs, concurrency, |addr, info| {
+            if has_min_players(&info, min_players) {
+                callback(addr, info);
+            }
+        })
+    }
+
+    /// Scans like [`scan`](Self::scan), only handing over servers with at least
+    /// `min_players` [`humans`](Info::humans), e.g. 1 to list non-empty servers. Failed queries
+    /// are still handed over.
+    ///
+    /// The protocol has no way to ask a server for its info only if it has players, so every
+    /// server is fully queried and filtered afterwards.
+    #[cfg(feature = "async")]
+    pub async fn scan_min_players<I, F>(
+        self: &Arc<Self>,
+        addrs: I,
+        concurrency: usize,
+        min_players: u8,
+        mut callback: F,
+    ) where
+        I: IntoIterator<Item = SocketAddr>,
+        F: FnMut(SocketAddr, Result<Info>),
+    {
+        self.scan(addrs, concurrency, |addr, info| {
+            if has_min_players(&info, min_players) {
+                callback(addr, info);
+            }
+        })
+        .await
+    }
+}
+
+fn has_min_players(info: &Result<Info>, min_players: u8) -> bool {
+    info.as_ref()
+        .map_or(true, |info| info.humans() >= min_players)
+}
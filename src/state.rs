@@ -0,0 +1,1062 @@
+use std::convert::TryFrom;
+#[cfg(not(feature = "async"))]
+use std::net::ToSocketAddrs;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "async")]
+use tokio::net::ToSocketAddrs;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crc::crc32;
+
+use crate::errors::{Error, Result};
+use crate::info::Info;
+use crate::players::{Player, PlayerList};
+use crate::reader::BytesReader;
+use crate::rules::{Rule, RuleChange};
+use crate::A2SClient;
+
+/// Rules that games or plugins are known to report the server's uptime in, in seconds.
+const UPTIME_RULES: [&str; 3] = ["sv_uptime", "uptime", "server_uptime"];
+
+/// Rules that Source games or plugins are known to report Workshop content in.
+const WORKSHOP_RULES: [&str; 3] = [
+    "host_workshop_collection",
+    "host_workshop_map",
+    "sm_nextmap",
+];
+
+/// Keywords (as `key:value` tags) that servers are known to advertise Workshop content in.
+const WORKSHOP_KEYWORDS: [&str; 2] = ["workshop", "collection"];
+
+/// Rules that games or plugins are known to report the server's region in, either as a name or
+/// as a master server region code.
+const REGION_RULES: [&str; 3] = ["sv_region", "region", "server_region"];
+
+/// Keywords (as `key:value` tags) that servers are known to advertise their region in.
+const REGION_KEYWORDS: [&str; 3] = ["region", "loc", "location"];
+
+/// Rules that games or plugins are known to report the server's tick rate in.
+const TICK_RATE_RULES: [&str; 3] = ["sv_tickrate", "tickrate", "tick_rate"];
+
+/// Rules capping the server's frame rate: `fps_max` and `sv_fps` in Source games, and
+/// `sys_ticrate` in GoldSource ones, where frames are ticks.
+const FPS_RULES: [&str; 3] = ["fps_max", "sv_fps", "sys_ticrate"];
+
+/// Rules that plugins and server operators are known to put a community website in.
+const WEBSITE_RULES: [&str; 5] = ["website", "sv_website", "server_website", "url", "sv_url"];
+
+/// Rules that plugins and server operators are known to put a Discord invite in.
+const DISCORD_RULES: [&str; 4] = ["discord", "sv_discord", "discord_url", "discord_invite"];
+
+/// Rules that plugins and server operators are known to put a TeamSpeak server address in.
+const TEAMSPEAK_RULES: [&str; 4] = ["teamspeak", "sv_teamspeak", "ts3", "ts3_server"];
+
+/// Hosts of Discord invite links.
+const DISCORD_HOSTS: [&str; 3] = ["discord.gg", "discord.com", "discordapp.com"];
+
+/// Community links a server advertises in its rules, see [`ServerState::community_links`].
+/// Each field holds the value as found, trimmed, and is `None` unless it looks like a link of
+/// its kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CommunityLinks {
+    /// Website, from `website`, `sv_website`, `server_website`, `url` or `sv_url`
+    pub website: Option<String>,
+
+    /// Discord invite, from `discord`, `sv_discord`, `discord_url` or `discord_invite`, or else
+    /// the first invite link in any rule, such as the messages of `sm_advertisements`
+    pub discord: Option<String>,
+
+    /// TeamSpeak server address, from `teamspeak`, `sv_teamspeak`, `ts3` or `ts3_server`
+    pub teamspeak: Option<String>,
+}
+
+/// Rules that server plugins and frameworks register, holding their version, with the name
+/// they're detected as.
+const PLUGIN_RULES: [(&str, &str); 6] = [
+    ("metamod_version", "Metamod"),
+    ("sourcemod_version", "SourceMod"),
+    ("amxmodx_version", "AMX Mod X"),
+    ("mani_admin_plugin_version", "Mani Admin Plugin"),
+    ("eventscripts_ver", "EventScripts"),
+    ("reapi_version", "ReAPI"),
+];
+
+/// A plugin a server runs, as detected by [`ServerState::detect_plugins`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct DetectedPlugin {
+    /// Name of the plugin, e.g. `SourceMod`, or the part of the rule between `sm_` and
+    /// `_version` for SourceMod plugins not otherwise known
+    pub name: String,
+
+    /// Version of the plugin, the first word of the rule's value holding a digit
+    pub version: Option<String>,
+
+    /// Name of the rule the plugin was detected from, as the server reported it
+    pub rule: String,
+}
+
+/// Performance settings a server exposes in its rules, see [`ServerState::performance_hints`].
+/// Every field is `None` unless one of its rules holds a number.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PerformanceHints {
+    /// Ticks per second, from `sv_tickrate`, `tickrate` or `tick_rate`, or a `128-tick` style
+    /// keyword
+    pub tick_rate: Option<u16>,
+
+    /// Frame rate cap, from `fps_max`, `sv_fps` or `sys_ticrate`
+    pub fps_max: Option<f32>,
+
+    /// Least bandwidth allowed per client in bytes per second, from `sv_minrate`
+    pub min_rate: Option<u32>,
+
+    /// Most bandwidth allowed per client in bytes per second, from `sv_maxrate`
+    pub max_rate: Option<u32>,
+
+    /// Fewest updates per second sent to clients, from `sv_minupdaterate`
+    pub min_update_rate: Option<u32>,
+
+    /// Most updates per second sent to clients, from `sv_maxupdaterate`
+    pub max_update_rate: Option<u32>,
+
+    /// Fewest commands per second accepted from clients, from `sv_mincmdrate`
+    pub min_cmd_rate: Option<u32>,
+
+    /// Most commands per second accepted from clients, from `sv_maxcmdrate`
+    pub max_cmd_rate: Option<u32>,
+}
+
+/// Where a server is hosted, as the regions of the master server's region codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum Region {
+    UsEast,
+    UsWest,
+    SouthAmerica,
+    Europe,
+    Asia,
+    Australia,
+    MiddleEast,
+    Africa,
+}
+
+impl Region {
+    /// The region of a master server region code, as in the `sv_region` convar. `None` for 255
+    /// ("rest of the world", the default) and unknown codes.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Region::UsEast),
+            1 => Some(Region::UsWest),
+            2 => Some(Region::SouthAmerica),
+            3 => Some(Region::Europe),
+            4 => Some(Region::Asia),
+            5 => Some(Region::Australia),
+            6 => Some(Region::MiddleEast),
+            7 => Some(Region::Africa),
+            _ => None,
+        }
+    }
+
+    /// The master server region code of the region.
+    pub fn code(self) -> u8 {
+        match self {
+            Region::UsEast => 0,
+            Region::UsWest => 1,
+            Region::SouthAmerica => 2,
+            Region::Europe => 3,
+            Region::Asia => 4,
+            Region::Australia => 5,
+            Region::MiddleEast => 6,
+            Region::Africa => 7,
+        }
+    }
+
+    /// The region a name such as `eu`, `us-east` or `Oceania` stands for, ignoring case,
+    /// spaces, dashes and underscores. `None` for unknown and ambiguous names such as `us`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name: String = name
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        match name.as_str() {
+            "useast" | "use" | "naeast" | "nae" | "eastus" => Some(Region::UsEast),
+            "uswest" | "usw" | "nawest" | "naw" | "westus" => Some(Region::UsWest),
+            "southamerica" | "sa" | "sam" | "latam" => Some(Region::SouthAmerica),
+            "europe" | "eu" | "euw" | "eue" | "euwest" | "eueast" => Some(Region::Europe),
+            "asia" | "as" | "sea" | "asiapacific" | "apac" => Some(Region::Asia),
+            "australia" | "au" | "oceania" | "oce" => Some(Region::Australia),
+            "middleeast" | "me" | "mideast" => Some(Region::MiddleEast),
+            "africa" | "af" => Some(Region::Africa),
+            _ => None,
+        }
+    }
+
+    /// The region of a rule or keyword value, either a region code or a name.
+    fn from_value(value: &str) -> Option<Self> {
+        let value = value.trim();
+        match value.parse::<u8>() {
+            Ok(code) => Self::from_code(code),
+            Err(_) => Self::from_name(value),
+        }
+    }
+}
+
+/// Everything a server reports about itself: its info, players and rules.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ServerState {
+    pub info: Info,
+
+    pub players: Vec<Player>,
+
+    pub rules: Vec<Rule>,
+
+    /// Ping of the info query, see [`InfoMeta::ping`](crate::info::InfoMeta::ping). `None` if it wasn't measured, as for the
+    /// states of [`ServerSession::refresh_all`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ping: Option<Duration>,
+}
+
+/// Weights of [`ServerState::health_score`]. Each component is scored from 0.0 to 1.0 and the
+/// score is their weighted average, so weights only matter relative to each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthConfig {
+    /// Weight of the ping, which scores 1.0 when instant, falling linearly to 0.0 at
+    /// `max_ping`. An unknown ping scores 0.0. Defaults to 0.5.
+    pub ping_weight: f32,
+
+    /// Ping scoring 0.0, as does anything slower. Defaults to 300 ms.
+    pub max_ping: Duration,
+
+    /// Weight of the fill ratio, human players (see [`Info::humans`]) over the player limit.
+    /// Defaults to 0.3.
+    pub fill_weight: f32,
+
+    /// Weight of a clean info response, which scores 1.0 without
+    /// [`parse_warnings`](Info::parse_warnings) and 0.0 with. Defaults to 0.2.
+    pub clean_weight: f32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig {
+            ping_weight: 0.5,
+            max_ping: Duration::from_millis(300),
+            fill_weight: 0.3,
+            clean_weight: 0.2,
+        }
+    }
+}
+
+/// Version byte of the [`Snapshot`] encoding.
+pub const SNAPSHOT_VERSION: u8 = 1;
+
+/// Size of an encoded [`Snapshot`], in bytes.
+pub const SNAPSHOT_SIZE: usize = 21;
+
+/// Ping encoded for a [`Snapshot`] without one.
+const NO_PING: u32 = u32::MAX;
+
+/// The figures of a [`ServerState`] worth keeping over time, encoded in a fixed number of bytes
+/// for storing thousands of them per server. See [`ServerState::to_snapshot`].
+///
+/// The encoding is [`SNAPSHOT_SIZE`] bytes, integers in little-endian:
+///
+/// | Bytes | Field                                                                         |
+/// |-------|-------------------------------------------------------------------------------|
+/// | 1     | format version, [`SNAPSHOT_VERSION`]                                          |
+/// | 8     | timestamp, in milliseconds since the Unix epoch                               |
+/// | 1     | players                                                                       |
+/// | 1     | player limit                                                                  |
+/// | 1     | bots                                                                          |
+/// | 4     | map hash, see [`Snapshot::map_hash`]                                          |
+/// | 4     | ping in microseconds, 0xFFFFFFFF if unknown and saturating for longer ones    |
+/// | 1     | reserved, 0                                                                   |
+///
+/// Later versions may add fields but will keep the version byte first, so readers can tell
+/// encodings apart and reject those they don't know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Snapshot {
+    /// When the state was taken, to the millisecond
+    pub timestamp: SystemTime,
+
+    pub players: u8,
+
+    pub max_players: u8,
+
+    pub bots: u8,
+
+    /// Hash of the map name, see [`Snapshot::map_hash`]
+    pub map_hash: u32,
+
+    /// Ping of the info query, to the microsecond, `None` if it wasn't measured
+    pub ping: Option<Duration>,
+}
+
+impl Snapshot {
+    /// The hash a map name is stored as, the CRC-32 (IEEE) of the name lowercased, stable across
+    /// versions so that maps can be looked up by hashing their names.
+    pub fn map_hash(map: &str) -> u32 {
+        crc32::checksum_ieee(map.to_ascii_lowercase().as_bytes())
+    }
+
+    /// Encodes the snapshot. Timestamps before the Unix epoch are stored as the epoch.
+    pub fn to_bytes(&self) -> [u8; SNAPSHOT_SIZE] {
+        let millis = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| {
+                u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+            });
+        let ping = self.ping.map_or(NO_PING, |ping| {
+            u32::try_from(ping.as_micros()).map_or(NO_PING - 1, |micros| micros.min(NO_PING - 1))
+        });
+
+        let mut bytes = [0; SNAPSHOT_SIZE];
+        bytes[0] = SNAPSHOT_VERSION;
+        bytes[1..9].copy_from_slice(&millis.to_le_bytes());
+        bytes[9] = self.players;
+        bytes[10] = self.max_players;
+        bytes[11] = self.bots;
+        bytes[12..16].copy_from_slice(&self.map_hash.to_le_bytes());
+        bytes[16..20].copy_from_slice(&ping.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a snapshot encoded by [`to_bytes`](Self::to_bytes). Fails on an unknown version,
+    /// or if `bytes` is too short for its version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut data = BytesReader::new(bytes);
+        if data.read_u8()? != SNAPSHOT_VERSION {
+            return Err(Error::Other("Unsupported snapshot version"));
+        }
+
+        let millis = data.read_u64_le()?;
+        let players = data.read_u8()?;
+        let max_players = data.read_u8()?;
+        let bots = data.read_u8()?;
+        let map_hash = data.read_u32_le()?;
+        let ping = data.read_u32_le()?;
+        data.read_u8()?;
+
+        Ok(Snapshot {
+            timestamp: UNIX_EPOCH + Duration::from_millis(millis),
+            players,
+            max_players,
+            bots,
+            map_hash,
+            ping: (ping != NO_PING).then(|| Duration::from_micros(u64::from(ping))),
+        })
+    }
+}
+
+/// Where a server was queried, and where players connect to it, see [`ServerState::endpoints`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Endpoints {
+    /// The address queries were sent to.
+    pub query_endpoint: SocketAddr,
+
+    /// The address players connect to: the query host, on the game port the server reports.
+    pub game_endpoint: SocketAddr,
+}
+
+/// What changed between two polls of the same server.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct StateDiff {
+    /// Previous and current map, if the map changed.
+    pub map: Option<(String, String)>,
+
+    /// Players that joined since the previous poll.
+    pub joined: Vec<Player>,
+
+    /// Players that left since the previous poll.
+    pub left: Vec<Player>,
+
+    /// Rules that were added, removed or changed value.
+    pub rules: Vec<RuleChange>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool {
+        self.map.is_none()
+            && self.joined.is_empty()
+            && self.left.is_empty()
+            && self.rules.is_empty()
+    }
+}
+
+impl ServerState {
+    /// Reports what changed since `previous`.
+    ///
+    /// The protocol has no stable player identifier, so players are matched by name and index on
+    /// a best-effort basis. Players sharing a name are told apart only by count: if two players
+    /// named "Player" were present and one is now, one of them is reported as having left.
+    pub fn diff(&self, previous: &ServerState) -> StateDiff {
+        let map = if self.info.map != previous.info.map {
+            Some((previous.info.map.clone(), self.info.map.clone()))
+        } else {
+            None
+        };
+
+        StateDiff {
+            map,
+            joined: unmatched_players(&self.players, &previous.players),
+            left: unmatched_players(&previous.players, &self.players),
+            rules: Rule::diff(&previous.rules, &self.rules),
+        }
+    }
+
+    /// The query and game endpoints of the server, which was queried at `queried`. They only
+    /// differ by port, the game's being the EDF port the server reports (see
+    /// [`Info::game_port`]), so a connect URL should be built from `game_endpoint`.
+    pub fn endpoints(&self, queried: SocketAddr) -> Endpoints {
+        Endpoints {
+            query_endpoint: queried,
+            game_endpoint: SocketAddr::new(queried.ip(), self.info.game_port(queried)),
+        }
+    }
+
+    /// Scores the server from 0.0 to 1.0 for ranking in a server browser, with the default
+    /// [`HealthConfig`] weights: half ping, 30% fill ratio and 20% clean response.
+    pub fn health_score(&self) -> f32 {
+        self.health_score_with(&HealthConfig::default())
+    }
+
+    /// Scores the server like [`health_score`](Self::health_score), with custom weights.
+    /// Negative weights count as 0, and the score is 0.0 if all of them are.
+    pub fn health_score_with(&self, config: &HealthConfig) -> f32 {
+        let ping = match self.ping {
+            Some(ping) if !config.max_ping.is_zero() => {
+                1.0 - (ping.as_secs_f32() / config.max_ping.as_secs_f32()).min(1.0)
+            }
+            _ => 0.0,
+        };
+        let fill = match self.info.max_players {
+            0 => 0.0,
+            max_players => (f32::from(self.info.humans()) / f32::from(max_players)).min(1.0),
+        };
+        let clean = if self.info.parse_warnings.is_empty() {
+            1.0
+        } else {
+            0.0
+        };
+
+        let weights = [
+            (config.ping_weight.max(0.0), ping),
+            (config.fill_weight.max(0.0), fill),
+            (config.clean_weight.max(0.0), clean),
+        ];
+        let total: f32 = weights.iter().map(|(weight, _)| weight).sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        weights
+            .iter()
+            .map(|(weight, score)| weight * score)
+            .sum::<f32>()
+            / total
+    }
+
+    /// Estimates how long the server has been up. The protocol doesn't report it, so this is a
+    /// heuristic: a known uptime rule such as `sv_uptime` if the server has one, otherwise the
+    /// longest connected player, which is only a lower bound. `None` if neither is available.
+    pub fn estimated_uptime(&self) -> Option<Duration> {
+        let rule = self
+            .rules
+            .iter()
+            .filter(|rule| UPTIME_RULES.contains(&rule.name.to_ascii_lowercase().as_str()))
+            .find_map(|rule| rule.value.trim().parse::<f64>().ok());
+
+        let seconds = rule.or_else(|| {
+            self.players
+                .iter()
+                .map(|player| f64::from(player.duration))
+                .filter(|duration| duration.is_finite())
+                .reduce(f64::max)
+        })?;
+
+        Duration::try_from_secs_f64(seconds).ok()
+    }
+
+    /// The whole state as one JSON document, with `info`, `players` and `rules` keys. Enums are
+    /// written by variant name, so the document deserializes back into an equal state.
+    #[cfg(feature = "serialization")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("server states always serialize")
+    }
+
+    /// Like [`to_json`](Self::to_json), indented for reading.
+    #[cfg(feature = "serialization")]
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).expect("server states always serialize")
+    }
+
+    /// The state's player counts, map and ping, timestamped now, for compact storage. See
+    /// [`Snapshot`] for the encoding.
+    pub fn to_snapshot(&self) -> Snapshot {
+        Snapshot {
+            timestamp: SystemTime::now(),
+            players: self.info.players,
+            max_players: self.info.max_players,
+            bots: self.info.bots,
+            map_hash: Snapshot::map_hash(&self.info.map),
+            ping: self.ping,
+        }
+    }
+
+    /// Steam Workshop IDs of the server's map or collection, in the order found and without
+    /// duplicates. Like uptime, the protocol doesn't report them, so they are looked for in:
+    ///
+    /// - the map name, if a Workshop path (`workshop/<id>/<map>`, or `<map>.ugc<id>`),
+    /// - the rules `host_workshop_collection`, `host_workshop_map` and `sm_nextmap` (from
+    ///   SourceMod), which hold either a bare ID or such a path,
+    /// - the `workshop` and `collection` keywords, as in `workshop:<id>`.
+    ///
+    /// Empty if none of them holds an ID.
+    pub fn workshop_ids(&self) -> Vec<u64> {
+        let keywords = self.info.keyword_map();
+
+        let rules = self
+            .rules
+            .iter()
+            .filter(|rule| WORKSHOP_RULES.contains(&rule.name.to_ascii_lowercase().as_str()))
+            .map(|rule| rule.value.as_str());
+        let tags = WORKSHOP_KEYWORDS
+            .iter()
+            .filter_map(|key| keywords.get(*key))
+            .map(String::as_str);
+
+        let mut ids = Vec::new();
+        let values = std::iter::once(self.info.map.as_str())
+            .chain(rules)
+            .chain(tags);
+        for id in values.filter_map(workshop_id) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+
+        ids
+    }
+
+    /// The tick rate, frame rate cap and network rates the server exposes in its rules, parsed
+    /// into numbers. Rule names are matched regardless of case, and values such as
+    /// `128.000000` are accepted for integer settings. See [`PerformanceHints`] for the rules
+    /// each field is read from.
+    pub fn performance_hints(&self) -> PerformanceHints {
+        let integer = |names: &[&str]| {
+            self.rule_number(names)
+                .filter(|value| *value >= 0.0 && *value <= f64::from(u32::MAX))
+                .map(|value| value.round() as u32)
+        };
+
+        PerformanceHints {
+            tick_rate: integer(&TICK_RATE_RULES)
+                .and_then(|rate| u16::try_from(rate).ok())
+                .or_else(|| self.info.csgo_tags().tick_rate),
+            fps_max: self.rule_number(&FPS_RULES).map(|fps| fps as f32),
+            min_rate: integer(&["sv_minrate"]),
+            max_rate: integer(&["sv_maxrate"]),
+            min_update_rate: integer(&["sv_minupdaterate"]),
+            max_update_rate: integer(&["sv_maxupdaterate"]),
+            min_cmd_rate: integer(&["sv_mincmdrate"]),
+            max_cmd_rate: integer(&["sv_maxcmdrate"]),
+        }
+    }
+
+    /// The first finite number held by one of the rules `names`, which must be lowercase.
+    fn rule_number(&self, names: &[&str]) -> Option<f64> {
+        self.rules
+            .iter()
+            .filter(|rule| names.contains(&rule.name.to_ascii_lowercase().as_str()))
+            .filter_map(|rule| rule.value.trim().parse::<f64>().ok())
+            .find(|value| value.is_finite())
+    }
+
+    /// Links to the community running the server, for server browsers to show. The protocol
+    /// has no field for them, so this is a heuristic looking through rules that plugins and
+    /// operators commonly use, listed in [`CommunityLinks`]. Rule names are matched regardless
+    /// of case, and a value is only taken if it looks like a link: a website must be an
+    /// `http(s)` URL or a bare domain, a Discord invite must point to Discord, and a TeamSpeak
+    /// address must be a host name or IP address, optionally with a port or the `ts3server`
+    /// scheme. Values holding text around the link, such as `Join us at discord.gg/abc`, have
+    /// the link picked out.
+    pub fn community_links(&self) -> CommunityLinks {
+        let any_rule = self.rules.iter().map(|rule| rule.value.as_str());
+
+        CommunityLinks {
+            website: self
+                .rule_link(&WEBSITE_RULES, |link| is_website(link) && !is_discord(link))
+                .map(str::to_owned),
+            discord: self
+                .rule_link(&DISCORD_RULES, is_discord)
+                .or_else(|| find_link(any_rule, is_discord))
+                .map(str::to_owned),
+            teamspeak: self
+                .rule_link(&TEAMSPEAK_RULES, is_teamspeak)
+                .map(str::to_owned),
+        }
+    }
+
+    /// The first word of the rules `names`, which must be lowercase, that `check` accepts.
+    fn rule_link<F>(&self, names: &[&str], check: F) -> Option<&str>
+    where
+        F: Fn(&str) -> bool,
+    {
+        let values = self
+            .rules
+            .iter()
+            .filter(|rule| names.contains(&rule.name.to_ascii_lowercase().as_str()))
+            .map(|rule| rule.value.as_str());
+        find_link(values, check)
+    }
+
+    /// Plugins the server runs, detected from the rules they register, in the order of the
+    /// rules and once per plugin. The protocol doesn't list plugins, so this only finds those
+    /// exposing a version rule, as most do to be counted by plugin trackers:
+    ///
+    /// - `metamod_version` (Metamod and Metamod:Source), `sourcemod_version` (SourceMod),
+    ///   `amxmodx_version` (AMX Mod X), `mani_admin_plugin_version` (Mani Admin Plugin),
+    ///   `eventscripts_ver` (EventScripts) and `reapi_version` (ReAPI),
+    /// - `sm_<name>_version`, the convention of SourceMod plugins, detected as `<name>`.
+    ///
+    /// Rule names are matched regardless of case. Servers can hide or fake these rules, so an
+    /// empty list doesn't mean the server runs no plugins.
+    pub fn detect_plugins(&self) -> Vec<DetectedPlugin> {
+        let mut plugins: Vec<DetectedPlugin> = Vec::new();
+
+        for rule in &self.rules {
+            let key = rule.name.to_ascii_lowercase();
+            let name = match PLUGIN_RULES.iter().find(|(name, _)| *name == key) {
+                Some((_, plugin)) => (*plugin).to_owned(),
+                None => match key
+                    .strip_prefix("sm_")
+                    .and_then(|key| key.strip_suffix("_version"))
+                {
+                    Some(plugin) if !plugin.is_empty() => plugin.to_owned(),
+                    _ => continue,
+                },
+            };
+            if plugins.iter().any(|plugin| plugin.name == name) {
+                continue;
+            }
+
+            plugins.push(DetectedPlugin {
+                name,
+                version: rule
+                    .value
+                    .split_whitespace()
+                    .find(|word| word.chars().any(|c| c.is_ascii_digit()))
+                    .map(str::to_owned),
+                rule: rule.name.clone(),
+            });
+        }
+
+        plugins
+    }
+
+    /// Guesses the region the server is hosted in. The protocol doesn't report it, so this is a
+    /// heuristic, and a wrong guess is as likely as none for servers not following the
+    /// conventions it looks for:
+    ///
+    /// - the `region`, `loc` and `location` keywords, as in `region:eu`, or a bare tag naming a
+    ///   region, such as `eu` or `us-east`,
+    /// - the rules `sv_region`, `region` and `server_region`, holding a region name or a master
+    ///   server region code.
+    ///
+    /// `None` if none of them names a region. See [`infer_region_with`](Self::infer_region_with)
+    /// to geolocate the server's address as well.
+    pub fn infer_region(&self) -> Option<Region> {
+        self.keyword_region().or_else(|| self.rule_region())
+    }
+
+    /// Like [`infer_region`](Self::infer_region), asking `geolocate` for the region of the
+    /// server's IP address when its keywords don't name one, before falling back to its rules.
+    /// The crate has no geolocation database, so `geolocate` is whatever lookup the caller has,
+    /// returning `None` for addresses it can't place.
+    pub fn infer_region_with<F>(&self, ip: IpAddr, geolocate: F) -> Option<Region>
+    where
+        F: FnOnce(IpAddr) -> Option<Region>,
+    {
+        self.keyword_region()
+            .or_else(|| geolocate(ip))
+            .or_else(|| self.rule_region())
+    }
+
+    fn keyword_region(&self) -> Option<Region> {
+        let keywords = self.info.keyword_map();
+
+        REGION_KEYWORDS
+            .iter()
+            .filter_map(|key| keywords.get(*key))
+            .find_map(|value| Region::from_value(value))
+            .or_else(|| {
+                keywords
+                    .iter()
+                    .filter(|(_, value)| value.is_empty())
+                    .find_map(|(tag, _)| Region::from_name(tag))
+            })
+    }
+
+    fn rule_region(&self) -> Option<Region> {
+        self.rules
+            .iter()
+            .filter(|rule| REGION_RULES.contains(&rule.name.to_ascii_lowercase().as_str()))
+            .find_map(|rule| Region::from_value(&rule.value))
+    }
+}
+
+/// The Workshop ID in a bare ID, a `workshop/<id>/<map>` path or a `<map>.ugc<id>` name.
+fn workshop_id(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let parse = |id: &str| id.parse::<u64>().ok().filter(|&id| id != 0);
+
+    if let Some(id) = parse(value) {
+        return Some(id);
+    }
+
+    let mut segments = value.split('/');
+    if segments.any(|segment| segment.eq_ignore_ascii_case("workshop")) {
+        if let Some(id) = segments.next().and_then(parse) {
+            return Some(id);
+        }
+    }
+
+    let (_, id) = value.rsplit_once(".ugc")?;
+    parse(id)
+}
+
+/// The first whitespace separated word of `values` that `check` accepts.
+fn find_link<'a, I, F>(values: I, check: F) -> Option<&'a str>
+where
+    I: Iterator<Item = &'a str>,
+    F: Fn(&str) -> bool,
+{
+    values
+        .flat_map(str::split_whitespace)
+        .map(|word| word.trim_end_matches(&['.', ',', ';', '!', ')'][..]))
+        .find(|word| check(word))
+}
+
+/// Splits `link` into its scheme, if it has one, and the host it points to, port excluded.
+/// `None` if the host is neither a domain name nor an IPv4 address.
+fn link_host(link: &str) -> Option<(Option<&str>, &str)> {
+    let (scheme, rest) = match link.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, link),
+    };
+    let authority = rest.split(&['/', '?', '#'][..]).next()?;
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => host,
+        Some(_) => return None,
+        None => authority,
+    };
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let is_domain = labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && labels[labels.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_alphabetic());
+    if is_domain || host.parse::<std::net::Ipv4Addr>().is_ok() {
+        Some((scheme, host))
+    } else {
+        None
+    }
+}
+
+fn is_website(link: &str) -> bool {
+    match link_host(link) {
+        Some((Some(scheme), _)) => {
+            scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+        }
+        Some((None, _)) => true,
+        None => false,
+    }
+}
+
+fn is_discord(link: &str) -> bool {
+    // An invite code is needed, the bare domain doesn't lead to the server's community
+    let rest = link.split_once("://").map_or(link, |(_, rest)| rest);
+    let has_path = rest
+        .split_once('/')
+        .is_some_and(|(_, path)| !path.is_empty());
+
+    has_path
+        && is_website(link)
+        && link_host(link).is_some_and(|(_, host)| {
+            let host = host.to_ascii_lowercase();
+            let host = host.strip_prefix("www.").unwrap_or(&host);
+            DISCORD_HOSTS.contains(&host)
+        })
+}
+
+fn is_teamspeak(link: &str) -> bool {
+    match link_host(link) {
+        Some((Some(scheme), _)) => scheme.eq_ignore_ascii_case("ts3server"),
+        Some((None, _)) => !link.contains('/'),
+        None => false,
+    }
+}
+
+/// Players in `players` that have no counterpart in `others`.
+fn unmatched_players(players: &[Player], others: &[Player]) -> Vec<Player> {
+    let mut matched = vec![false; others.len()];
+
+    players
+        .iter()
+        .filter(|player| {
+            let counterpart = others.iter().enumerate().position(|(i, other)| {
+                !matched[i] && other.name == player.name && other.index == player.index
+            });
+
+            match counterpart {
+                Some(i) => {
+                    matched[i] = true;
+                    false
+                }
+                None => true,
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+impl A2SClient {
+    /// Queries info, players and rules. The app ID learned from the info response is used to
+    /// parse the players, so The Ship fields are populated without configuring
+    /// [`app_id`](Self::app_id) up front.
+    #[cfg(feature = "async")]
+    pub async fn query_all<A: ToSocketAddrs>(&self, addr: A) -> Result<ServerState> {
+        let (info, meta) = self.info_with_meta(&addr).await?;
+        let (players, _) = self
+            .players_for_app(&addr, player_app_id(&info), None)
+            .await?;
+        let rules = self.rules(&addr).await?;
+
+        Ok(ServerState {
+            info,
+            players,
+            rules,
+            ping: Some(meta.ping),
+        })
+    }
+
+    /// Queries info and players, checking the players listed against the count the info
+    /// reports. The app ID learned from the info is used to parse the players, like
+    /// [`query_all`](Self::query_all).
+    #[cfg(feature = "async")]
+    pub async fn players_checked<A: ToSocketAddrs>(&self, addr: A) -> Result<PlayerList> {
+        let info = self.info(&addr).await?;
+        let (players, _) = self
+            .players_for_app(&addr, player_app_id(&info), None)
+            .await?;
+
+        Ok(PlayerList::new(players, &info))
+    }
+
+    /// Queries info and players, checking the players listed against the count the info
+    /// reports. The app ID learned from the info is used to parse the players, like
+    /// [`query_all`](Self::query_all).
+    #[cfg(not(feature = "async"))]
+    pub fn players_checked<A: ToSocketAddrs>(&self, addr: A) -> Result<PlayerList> {
+        let info = self.info(&addr)?;
+        let (players, _) = self.players_for_app(&addr, player_app_id(&info), None)?;
+
+        Ok(PlayerList::new(players, &info))
+    }
+
+    /// Queries info, players and rules. The app ID learned from the info response is used to
+    /// parse the players, so The Ship fields are populated without configuring
+    /// [`app_id`](Self::app_id) up front.
+    #[cfg(not(feature = "async"))]
+    pub fn query_all<A: ToSocketAddrs>(&self, addr: A) -> Result<ServerState> {
+        let (info, meta) = self.info_with_meta(&addr)?;
+        let (players, _) = self.players_for_app(&addr, player_app_id(&info), None)?;
+        let rules = self.rules(&addr)?;
+
+        Ok(ServerState {
+            info,
+            players,
+            rules,
+            ping: Some(meta.ping),
+        })
+    }
+}
+
+/// Repeated queries of a single server sharing one challenge, so that polling it only costs a
+/// handshake when the server rotates its challenge.
+pub struct ServerSession<'a> {
+    client: &'a A2SClient,
+    addr: SocketAddr,
+    challenge: Option<i32>,
+    /// App ID players are parsed with, once learned from an info response
+    app_id: Option<u16>,
+}
+
+impl<'a> ServerSession<'a> {
+    pub fn new(client: &'a A2SClient, addr: SocketAddr) -> Self {
+        ServerSession {
+            client,
+            addr,
+            challenge: None,
+            app_id: None,
+        }
+    }
+
+    /// Starts a session with an info query, whose challenge (if the server required one) and app
+    /// ID the following [`players`](Self::players) and [`rules`](Self::rules) queries reuse.
+    #[cfg(feature = "async")]
+    pub async fn from_info(client: &'a A2SClient, addr: SocketAddr) -> Result<(Self, Info)> {
+        let mut session = Self::new(client, addr);
+        let info = session.info().await?;
+        Ok((session, info))
+    }
+
+    /// Starts a session with an info query, whose challenge (if the server required one) and app
+    /// ID the following [`players`](Self::players) and [`rules`](Self::rules) queries reuse.
+    #[cfg(not(feature = "async"))]
+    pub fn from_info(client: &'a A2SClient, addr: SocketAddr) -> Result<(Self, Info)> {
+        let mut session = Self::new(client, addr);
+        let info = session.info()?;
+        Ok((session, info))
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The challenge the server last accepted, if one was required yet.
+    pub fn challenge(&self) -> Option<i32> {
+        self.challenge
+    }
+
+    /// Queries info, players and rules like [`A2SClient::query_all`], reusing the cached
+    /// challenge. A query the server rejects with a fresh challenge is retried with it, which is
+    /// then kept for the following queries.
+    #[cfg(feature = "async")]
+    pub async fn refresh_all(&mut self) -> Result<ServerState> {
+        let info = self.info().await?;
+        let players = self.players().await?;
+        let rules = self.rules().await?;
+
+        Ok(ServerState {
+            info,
+            players,
+            rules,
+            ping: None,
+        })
+    }
+
+    /// Queries info, players and rules like [`A2SClient::query_all`], reusing the cached
+    /// challenge. A query the server rejects with a fresh challenge is retried with it, which is
+    /// then kept for the following queries.
+    #[cfg(not(feature = "async"))]
+    pub fn refresh_all(&mut self) -> Result<ServerState> {
+        let info = self.info()?;
+        let players = self.players()?;
+        let rules = self.rules()?;
+
+        Ok(ServerState {
+            info,
+            players,
+            rules,
+            ping: None,
+        })
+    }
+
+    /// Queries info with the cached challenge, learning the app ID players are parsed with.
+    #[cfg(feature = "async")]
+    pub async fn info(&mut self) -> Result<Info> {
+        let (info, challenge) = self
+            .client
+            .info_with_challenge(self.addr, self.challenge)
+            .await?;
+        self.challenge = challenge;
+        self.app_id = Some(player_app_id(&info));
+
+        Ok(info)
+    }
+
+    /// Queries info with the cached challenge, learning the app ID players are parsed with.
+    #[cfg(not(feature = "async"))]
+    pub fn info(&mut self) -> Result<Info> {
+        let (info, challenge) = self.client.info_with_challenge(self.addr, self.challenge)?;
+        self.challenge = challenge;
+        self.app_id = Some(player_app_id(&info));
+
+        Ok(info)
+    }
+
+    /// Queries players with the cached challenge, parsed for the app ID of the last info
+    /// response (the client's [`app_id`](A2SClient::app_id) until one was received).
+    #[cfg(feature = "async")]
+    pub async fn players(&mut self) -> Result<Vec<Player>> {
+        let (players, challenge) = self
+            .client
+            .players_for_app(self.addr, self.player_app_id(), self.challenge)
+            .await?;
+        self.challenge = challenge;
+
+        Ok(players)
+    }
+
+    /// Queries players with the cached challenge, parsed for the app ID of the last info
+    /// response (the client's [`app_id`](A2SClient::app_id) until one was received).
+    #[cfg(not(feature = "async"))]
+    pub fn players(&mut self) -> Result<Vec<Player>> {
+        let (players, challenge) =
+            self.client
+                .players_for_app(self.addr, self.player_app_id(), self.challenge)?;
+        self.challenge = challenge;
+
+        Ok(players)
+    }
+
+    /// Queries rules with the cached challenge.
+    #[cfg(feature = "async")]
+    pub async fn rules(&mut self) -> Result<Vec<Rule>> {
+        let (rules, challenge) = self
+            .client
+            .rules_with_challenge(self.addr, self.challenge)
+            .await?;
+        self.challenge = challenge;
+
+        Ok(rules)
+    }
+
+    /// Queries rules with the cached challenge.
+    #[cfg(not(feature = "async"))]
+    pub fn rules(&mut self) -> Result<Vec<Rule>> {
+        let (rules, challenge) = self
+            .client
+            .rules_with_challenge(self.addr, self.challenge)?;
+        self.challenge = challenge;
+
+        Ok(rules)
+    }
+
+    fn player_app_id(&self) -> u16 {
+        self.app_id.unwrap_or(self.client.config.app_id)
+    }
+}
+
+/// App ID to parse players with. An ID too wide for the player parser can't be The Ship's, so it
+/// maps to 0.
+fn player_app_id(info: &Info) -> u16 {
+    u16::try_from(info.true_app_id()).unwrap_or(0)
+}
@@ -0,0 +1,162 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::info::Info;
+
+/// Game modes Team Fortress 2 tags servers with, after their map prefix.
+const TF2_GAME_MODES: [&str; 12] = [
+    "arena", "cp", "ctf", "koth", "mvm", "passtime", "pd", "pl", "plr", "rd", "sd", "tc",
+];
+
+/// Game modes commonly found in Counter-Strike: Global Offensive tags, official and community.
+const CSGO_GAME_MODES: [&str; 14] = [
+    "armsrace",
+    "bhop",
+    "casual",
+    "competitive",
+    "dangerzone",
+    "deathmatch",
+    "demolition",
+    "jailbreak",
+    "kz",
+    "minigames",
+    "retake",
+    "surf",
+    "wingman",
+    "zombie",
+];
+
+/// Team Fortress 2 server tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct Tf2Tags {
+    /// Game mode of the current map, e.g. `koth` or `pl`
+    pub game_mode: Option<String>,
+
+    pub alltalk: bool,
+
+    /// Random critical hits are disabled
+    pub no_crits: bool,
+
+    /// Random damage spread is disabled
+    pub no_damage_spread: bool,
+
+    /// The server runs more than the default 24 player slots
+    pub increased_max_players: bool,
+
+    pub friendly_fire: bool,
+
+    pub cheats: bool,
+
+    /// Respawn times differ from the defaults
+    pub respawn_times: bool,
+
+    /// Players respawn instantly
+    pub no_respawn_time: bool,
+
+    /// Official Valve server
+    pub valve: bool,
+
+    /// Tags with no meaning known to this decoder
+    pub other: Vec<String>,
+}
+
+/// Counter-Strike: Global Offensive server tags.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CsgoTags {
+    /// Game mode, e.g. `competitive` or `surf`
+    pub game_mode: Option<String>,
+
+    /// VAC secured
+    pub secure: bool,
+
+    /// Official Valve dedicated server
+    pub valve_ds: bool,
+
+    /// Nobody is playing
+    pub empty: bool,
+
+    pub alltalk: bool,
+
+    /// Tick rate advertised by a `128-tick` style tag
+    pub tick_rate: Option<u16>,
+
+    /// Tags with no meaning known to this decoder
+    pub other: Vec<String>,
+}
+
+impl Info {
+    /// Decodes the keywords as Team Fortress 2 tags.
+    pub fn tf2_tags(&self) -> Tf2Tags {
+        let mut decoded = Tf2Tags::default();
+
+        for tag in tags(self) {
+            match tag.as_str() {
+                "alltalk" => decoded.alltalk = true,
+                "nocrits" => decoded.no_crits = true,
+                "nodmgspread" => decoded.no_damage_spread = true,
+                "increased_maxplayers" => decoded.increased_max_players = true,
+                "friendlyfire" => decoded.friendly_fire = true,
+                "cheats" => decoded.cheats = true,
+                "respawntimes" => decoded.respawn_times = true,
+                "norespawntime" => decoded.no_respawn_time = true,
+                "valve" => decoded.valve = true,
+                mode if decoded.game_mode.is_none() && TF2_GAME_MODES.contains(&mode) => {
+                    decoded.game_mode = Some(tag)
+                }
+                _ => decoded.other.push(tag),
+            }
+        }
+
+        decoded
+    }
+
+    /// Decodes the keywords as Counter-Strike: Global Offensive tags.
+    pub fn csgo_tags(&self) -> CsgoTags {
+        let mut decoded = CsgoTags::default();
+
+        for tag in tags(self) {
+            match tag.as_str() {
+                "secure" => decoded.secure = true,
+                "valve_ds" => decoded.valve_ds = true,
+                "empty" => decoded.empty = true,
+                "alltalk" => decoded.alltalk = true,
+                mode if decoded.game_mode.is_none() && CSGO_GAME_MODES.contains(&mode) => {
+                    decoded.game_mode = Some(tag)
+                }
+                _ => match tick_rate(&tag) {
+                    Some(rate) if decoded.tick_rate.is_none() => decoded.tick_rate = Some(rate),
+                    _ => decoded.other.push(tag),
+                },
+            }
+        }
+
+        decoded
+    }
+}
+
+/// The non-empty keywords, trimmed and lowercased as games aren't consistent about either.
+fn tags(info: &Info) -> Vec<String> {
+    info.extended_server_info
+        .keywords
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(|tag| tag.trim().to_ascii_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Parses tick rate tags such as `128-tick`, `128tick` or `tickrate128`.
+fn tick_rate(tag: &str) -> Option<u16> {
+    let rate = tag
+        .strip_suffix("tick")
+        .map(|rate| rate.trim_end_matches('-'))
+        .or_else(|| tag.strip_prefix("tickrate"))?;
+
+    rate.parse().ok()
+}
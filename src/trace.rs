@@ -0,0 +1,189 @@
+#[cfg(not(feature = "async"))]
+use std::net::ToSocketAddrs;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "async")]
+use tokio::net::ToSocketAddrs;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Result;
+use crate::info::Info;
+use crate::players::Player;
+use crate::rules::Rule;
+use crate::A2SClient;
+
+/// Which way a logged datagram went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A datagram sent or received during a logged query, see [`A2SClient::info_logged`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PacketLog {
+    pub direction: Direction,
+
+    /// The whole datagram, headers included
+    pub bytes: Vec<u8>,
+
+    /// Time since the query started, measured with the client's [`Clock`](crate::Clock)
+    pub elapsed: Duration,
+}
+
+/// Collects the datagrams of a logged query.
+pub(crate) struct Recorder {
+    start: Instant,
+    packets: Mutex<Vec<PacketLog>>,
+}
+
+impl Recorder {
+    fn take(&self) -> Vec<PacketLog> {
+        std::mem::take(&mut self.packets.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+impl A2SClient {
+    /// Records a datagram if this client logs its queries.
+    pub(crate) fn record(&self, direction: Direction, bytes: &[u8]) {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(?direction, len = bytes.len(), "datagram");
+
+        if let Some(recorder) = &self.recorder {
+            let elapsed = self.clock.now().saturating_duration_since(recorder.start);
+            recorder
+                .packets
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(PacketLog {
+                    direction,
+                    bytes: bytes.to_vec(),
+                    elapsed,
+                });
+        }
+    }
+
+    /// A client sharing this one's socket, configuration, clock and caches.
+    fn shared(&self) -> A2SClient {
+        A2SClient {
+            #[cfg(not(feature = "async"))]
+            socket: self.socket.clone(),
+            #[cfg(feature = "async")]
+            router: self.router.clone(),
+            #[cfg(feature = "async")]
+            limiter: self.limiter.clone(),
+            config: self.config.clone(),
+            clock: self.clock.clone(),
+            recorder: self.recorder.clone(),
+            dns_cache: self.dns_cache.clone(),
+            request_id: self.request_id.clone(),
+            size_collector: self.size_collector.clone(),
+            buffers: self.buffers.clone(),
+        }
+    }
+
+    /// A client sharing this one's socket, configuration and clock, logging its queries.
+    fn recording(&self) -> A2SClient {
+        A2SClient {
+            recorder: Some(Arc::new(Recorder {
+                start: self.clock.now(),
+                packets: Mutex::new(Vec::new()),
+            })),
+            ..self.shared()
+        }
+    }
+
+    /// A client sharing this one's socket, configuration and caches, whose queries carry `id`,
+    /// for correlating them with the caller's own request tracing:
+    /// `client.with_request_id("7f3a").info(addr)`.
+    ///
+    /// With the `tracing` feature, every query runs in an `a2s_query` span recording `id` as its
+    /// `request_id` field, and the events emitted within it (datagrams sent and received,
+    /// retries, the outcome) are tagged with it by subscribers. Without it, the id is ignored.
+    pub fn with_request_id(&self, id: &str) -> A2SClient {
+        A2SClient {
+            request_id: Some(id.into()),
+            ..self.shared()
+        }
+    }
+
+    /// The span a query runs in, see [`with_request_id`](Self::with_request_id).
+    #[cfg(feature = "tracing")]
+    pub(crate) fn query_span(&self) -> tracing::Span {
+        tracing::debug_span!("a2s_query", request_id = self.request_id.as_deref())
+    }
+
+    fn packet_log(&self) -> Vec<PacketLog> {
+        self.recorder.as_ref().map(|r| r.take()).unwrap_or_default()
+    }
+
+    /// Queries info like [`info`](Self::info), also returning every datagram sent and received,
+    /// challenge handshake, retries and fragments included. The log is returned even if the
+    /// query fails, which is when it's most useful. Only logged queries pay for recording.
+    #[cfg(feature = "async")]
+    pub async fn info_logged<A: ToSocketAddrs>(&self, addr: A) -> (Result<Info>, Vec<PacketLog>) {
+        let client = self.recording();
+        let info = client.info(addr).await;
+        (info, client.packet_log())
+    }
+
+    /// Queries players like [`players`](Self::players), also returning every datagram sent and
+    /// received, see [`info_logged`](Self::info_logged).
+    #[cfg(feature = "async")]
+    pub async fn players_logged<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+    ) -> (Result<Vec<Player>>, Vec<PacketLog>) {
+        let client = self.recording();
+        let players = client.players(addr).await;
+        (players, client.packet_log())
+    }
+
+    /// Queries rules like [`rules`](Self::rules), also returning every datagram sent and
+    /// received, see [`info_logged`](Self::info_logged).
+    #[cfg(feature = "async")]
+    pub async fn rules_logged<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+    ) -> (Result<Vec<Rule>>, Vec<PacketLog>) {
+        let client = self.recording();
+        let rules = client.rules(addr).await;
+        (rules, client.packet_log())
+    }
+
+    /// Queries info like [`info`](Self::info), also returning every datagram sent and received,
+    /// challenge handshake, retries and fragments included. The log is returned even if the
+    /// query fails, which is when it's most useful. Only logged queries pay for recording.
+    #[cfg(not(feature = "async"))]
+    pub fn info_logged<A: ToSocketAddrs>(&self, addr: A) -> (Result<Info>, Vec<PacketLog>) {
+        let client = self.recording();
+        let info = client.info(addr);
+        (info, client.packet_log())
+    }
+
+    /// Queries players like [`players`](Self::players), also returning every datagram sent and
+    /// received, see [`info_logged`](Self::info_logged).
+    #[cfg(not(feature = "async"))]
+    pub fn players_logged<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+    ) -> (Result<Vec<Player>>, Vec<PacketLog>) {
+        let client = self.recording();
+        let players = client.players(addr);
+        (players, client.packet_log())
+    }
+
+    /// Queries rules like [`rules`](Self::rules), also returning every datagram sent and
+    /// received, see [`info_logged`](Self::info_logged).
+    #[cfg(not(feature = "async"))]
+    pub fn rules_logged<A: ToSocketAddrs>(&self, addr: A) -> (Result<Vec<Rule>>, Vec<PacketLog>) {
+        let client = self.recording();
+        let rules = client.rules(addr);
+        (rules, client.packet_log())
+    }
+}
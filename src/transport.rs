@@ -0,0 +1,232 @@
+#[cfg(feature = "tcp")]
+use std::convert::TryFrom;
+use std::future::Future;
+use std::io;
+#[cfg(any(feature = "tcp", feature = "async"))]
+use std::time::Duration;
+
+#[cfg(feature = "tcp")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "tcp")]
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+#[cfg(feature = "async")]
+use tokio::net::UdpSocket;
+#[cfg(feature = "tcp")]
+use tokio::net::{TcpStream, ToSocketAddrs};
+#[cfg(feature = "tcp")]
+use tokio::sync::Mutex;
+
+use crate::errors::Result;
+use crate::query::Query;
+#[cfg(feature = "async")]
+use crate::A2SClient;
+#[cfg(any(feature = "tcp", feature = "async"))]
+use crate::MAX_DATAGRAM_SIZE;
+use crate::{challenge_packet, server_challenge, A2SConfig, ChallengeMode, ParseContext, Response};
+
+/// A datagram transport to a single server, for platforms without UDP sockets, such as a
+/// browser relaying datagrams through JavaScript.
+///
+/// Timeouts are left to the implementation: [`recv`](Self::recv) should fail with
+/// [`io::ErrorKind::TimedOut`] rather than wait forever for a server that never answers.
+pub trait AsyncDatagram {
+    /// Sends a single datagram to the server.
+    fn send(&self, data: &[u8]) -> impl Future<Output = io::Result<()>>;
+
+    /// Receives the next datagram from the server.
+    fn recv(&self) -> impl Future<Output = io::Result<Vec<u8>>>;
+}
+
+/// Sends `query` over `transport` and parses its response, handling challenges (following
+/// [`A2SConfig::challenge_mode`]) and multi-packet reassembly with the limits of `config`.
+/// Requests are sent once, retries are up to the caller.
+pub async fn query_transport<T: AsyncDatagram, Q: Query>(
+    transport: &T,
+    query: &Q,
+    config: &A2SConfig,
+) -> Result<Q::Output> {
+    let request = query.request_bytes();
+
+    // Queries that don't need a challenge only do the handshake if asked, like info ones
+    let config = &A2SConfig {
+        challenge_mode: if query.needs_challenge() {
+            config.challenge_mode
+        } else {
+            ChallengeMode::OnDemand
+        },
+        ..config.clone()
+    };
+
+    let mut packet = challenge_packet(&request, None, config);
+    let mut data = exchange(transport, &packet, config).await?;
+
+    if let Some(challenge) = server_challenge(&data, None, config)? {
+        packet.truncate(request.len());
+        packet.extend(challenge.to_le_bytes());
+        data = exchange(transport, &packet, config).await?;
+    }
+
+    let ctx = ParseContext {
+        app_id: config.app_id,
+        parse_the_ship: config.parse_the_ship,
+        reject_inconsistent: config.reject_inconsistent,
+        lenient_edf: config.lenient_edf,
+        dump_invalid_responses: config.dump_invalid_responses,
+    };
+    query.parse(&data, &ctx)
+}
+
+/// Sends a request and receives its whole response, without the packet header.
+async fn exchange<T: AsyncDatagram>(
+    transport: &T,
+    packet: &[u8],
+    config: &A2SConfig,
+) -> Result<Vec<u8>> {
+    transport.send(packet).await?;
+
+    let mut reassembly = match Response::start(transport.recv().await?, config)? {
+        Response::Complete(payload) => return Ok(payload),
+        Response::Partial(reassembly) => reassembly,
+    };
+
+    while !reassembly.is_complete() {
+        let data = transport
+            .recv()
+            .await
+            .map_err(|err| reassembly.timed_out(err.into()))?;
+        reassembly.push(data, config)?;
+    }
+
+    reassembly.finish()
+}
+
+/// Whether a datagram is an A2S response, i.e. starts with the single (`0xFFFFFFFF`) or
+/// multi-packet (`0xFFFFFFFE`) header, for telling them apart from the datagrams of other
+/// protocols sharing a socket.
+pub fn is_a2s_datagram(data: &[u8]) -> bool {
+    matches!(
+        data,
+        [0xff, 0xff, 0xff, 0xff, ..] | [0xfe, 0xff, 0xff, 0xff, ..]
+    )
+}
+
+/// A tokio `UdpSocket` connected to a server and shared with other protocols, such as RCON
+/// over UDP, so that a manager needs a single socket per server.
+///
+/// Demultiplexing is up to the caller: while a query waits for its response, the transport reads
+/// the socket itself and discards every datagram [`is_a2s_datagram`] rejects, so the caller must
+/// not expect other traffic in the meantime, nor read the socket concurrently, as a response it
+/// reads is lost to the query. Receiving fails with [`io::ErrorKind::TimedOut`] once `timeout`
+/// elapses.
+#[cfg(feature = "async")]
+pub struct SharedUdpSocket<'a> {
+    socket: &'a UdpSocket,
+    timeout: Duration,
+}
+
+#[cfg(feature = "async")]
+impl<'a> SharedUdpSocket<'a> {
+    /// Borrows `socket`, which must already be connected to the server.
+    pub fn new(socket: &'a UdpSocket, timeout: Duration) -> Self {
+        SharedUdpSocket { socket, timeout }
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncDatagram for SharedUdpSocket<'_> {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        self.socket.send(data).await.map(|_| ())
+    }
+
+    async fn recv(&self) -> io::Result<Vec<u8>> {
+        let recv = async {
+            let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+            loop {
+                let read = self.socket.recv(&mut buf).await?;
+                if is_a2s_datagram(&buf[..read]) {
+                    buf.truncate(read);
+                    return Ok(buf);
+                }
+            }
+        };
+
+        tokio::time::timeout(self.timeout, recv)
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?
+    }
+}
+
+#[cfg(feature = "async")]
+impl A2SClient {
+    /// Sends `query` over `socket`, a socket connected to the server and shared with other
+    /// protocols, with the client's configuration. See [`SharedUdpSocket`] for how datagrams of
+    /// other protocols are handled during the query. Like [`query_transport`], requests are sent
+    /// once.
+    pub async fn query_shared<Q: Query>(&self, socket: &UdpSocket, query: &Q) -> Result<Q::Output> {
+        let transport = SharedUdpSocket::new(socket, self.config.timeout);
+        query_transport(&transport, query, &self.config).await
+    }
+}
+
+/// A transport to a relay exposing a server's queries over TCP, as some managed hosts do. Every
+/// datagram is framed by its length as a little-endian `u32`, the bodies are the same as over
+/// UDP. Receiving fails with [`io::ErrorKind::TimedOut`] once `timeout` elapses.
+#[cfg(feature = "tcp")]
+pub struct TcpTransport {
+    reader: Mutex<OwnedReadHalf>,
+    writer: Mutex<OwnedWriteHalf>,
+    timeout: Duration,
+}
+
+#[cfg(feature = "tcp")]
+impl TcpTransport {
+    pub async fn connect<A: ToSocketAddrs>(addr: A, timeout: Duration) -> io::Result<Self> {
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(addr))
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+        stream.set_nodelay(true)?;
+
+        let (reader, writer) = stream.into_split();
+        Ok(TcpTransport {
+            reader: Mutex::new(reader),
+            writer: Mutex::new(writer),
+            timeout,
+        })
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl AsyncDatagram for TcpTransport {
+    async fn send(&self, data: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(data.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "datagram too large"))?;
+
+        let mut frame = Vec::with_capacity(data.len() + 4);
+        frame.extend(len.to_le_bytes());
+        frame.extend(data);
+
+        self.writer.lock().await.write_all(&frame).await
+    }
+
+    async fn recv(&self) -> io::Result<Vec<u8>> {
+        let mut reader = self.reader.lock().await;
+        let read = async {
+            let len = reader.read_u32_le().await? as usize;
+            // No datagram is that large, the stream is out of sync or not framed this way
+            if len > MAX_DATAGRAM_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "frame larger than a datagram",
+                ));
+            }
+
+            let mut datagram = vec![0; len];
+            reader.read_exact(&mut datagram).await?;
+            Ok(datagram)
+        };
+
+        tokio::time::timeout(self.timeout, read)
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?
+    }
+}
@@ -36,7 +36,7 @@ async fn test_async_multipleservers() {
     .into_iter()
     .map(lookup_host);
     let addresses = future::join_all(addresses).await.into_iter().flat_map(|a| {
-        a.unwrap().into_iter().flat_map(|sa| match sa {
+        a.unwrap().flat_map(|sa| match sa {
             SocketAddr::V4(sa4) => Some(sa4),
             _ => None,
         })
@@ -52,3 +52,370 @@ async fn test_async_multipleservers() {
         fut = remaining;
     }
 }
+
+#[cfg(feature = "async")]
+mod common;
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_dropped_query() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    let requests = AtomicUsize::new(0);
+    let address = common::mock_server(move |_| {
+        if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+            // Answer the first query only once its future has been dropped
+            std::thread::sleep(Duration::from_millis(200));
+            vec![common::single(&common::info_payload_named("stale"))]
+        } else {
+            vec![common::single(&common::info_payload_named("fresh"))]
+        }
+    });
+
+    let client = A2SClient::new().await.unwrap();
+
+    let dropped = tokio::time::timeout(Duration::from_millis(50), client.info(address)).await;
+    assert!(dropped.is_err());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let info = client.info(address).await.unwrap();
+    assert_eq!(info.name, "fresh");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_shared_socket_routing() {
+    let first = common::mock_server(|_| vec![common::single(&common::info_payload_named("first"))]);
+    let second =
+        common::mock_server(|_| vec![common::single(&common::info_payload_named("second"))]);
+
+    let client = A2SClient::new().await.unwrap();
+
+    let queries = (0..8).map(|i| {
+        let address = if i % 2 == 0 { first } else { second };
+        client.info(address)
+    });
+
+    for (i, info) in future::join_all(queries).await.into_iter().enumerate() {
+        let expected = if i % 2 == 0 { "first" } else { "second" };
+        assert_eq!(info.unwrap().name, expected);
+    }
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_players_with_challenge() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let address = common::mock_server(move |request| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        if request.ends_with(&7i32.to_le_bytes()) {
+            vec![common::single(&common::players_payload(&["alice"], false))]
+        } else {
+            vec![common::challenge(7)]
+        }
+    });
+
+    let client = A2SClient::new().await.unwrap();
+
+    let (players, challenge) = client
+        .players_with_challenge(address, Some(7))
+        .await
+        .unwrap();
+    assert_eq!(players[0].name, "alice");
+    assert_eq!(challenge, Some(7));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // A stale challenge is replaced by the one the server hands out
+    let (_, challenge) = client
+        .players_with_challenge(address, Some(3))
+        .await
+        .unwrap();
+    assert_eq!(challenge, Some(7));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_local_addr() {
+    let client = A2SClient::new().await.unwrap();
+
+    assert_ne!(client.local_addr().unwrap().port(), 0);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_shutdown() {
+    use std::time::Duration;
+
+    let is_free = |port| std::net::UdpSocket::bind(("0.0.0.0", port)).is_ok();
+
+    let client = A2SClient::new().await.unwrap();
+    let port = client.local_addr().unwrap().port();
+    assert!(!is_free(port));
+    client.shutdown().await;
+    assert!(is_free(port));
+
+    // Dropping stops the task too, if not right away
+    let client = A2SClient::new().await.unwrap();
+    let port = client.local_addr().unwrap().port();
+    drop(client);
+    let released = async {
+        while !is_free(port) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(1), released)
+        .await
+        .unwrap();
+
+    // A client sharing the socket keeps it running
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+    let client = A2SClient::new().await.unwrap();
+    let tagged = client.with_request_id("poll-1");
+    client.shutdown().await;
+    assert_eq!(tagged.info(address).await.unwrap().name, "Test Server");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_connection_refused() {
+    let closed = {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.local_addr().unwrap()
+    };
+
+    let client = A2SClient::new().await.unwrap();
+    client.connect(closed).await.unwrap();
+
+    assert!(matches!(
+        client.info(closed).await,
+        Err(a2s::errors::Error::ConnectionRefused)
+    ));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_scan() {
+    let names = ["first", "second", "third", "fourth"];
+    let servers: Vec<_> = names
+        .iter()
+        .map(|&name| {
+            common::mock_server(move |_| vec![common::single(&common::info_payload_named(name))])
+        })
+        .collect();
+
+    let client = std::sync::Arc::new(A2SClient::new().await.unwrap());
+
+    let mut found = Vec::new();
+    client
+        .scan(servers, 2, |_, info| found.push(info.unwrap().name))
+        .await;
+
+    found.sort();
+    assert_eq!(found, ["first", "fourth", "second", "third"]);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_resolve_addr() {
+    let addr = a2s::resolve_addr("[::ffff:127.0.0.1]:27015").await.unwrap();
+    assert_eq!(addr, "127.0.0.1:27015".parse().unwrap());
+
+    assert!(
+        a2s::resolve_addr(Vec::<std::net::SocketAddr>::new().as_slice())
+            .await
+            .is_err()
+    );
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_info_logged() {
+    use a2s::trace::Direction;
+
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+    let client = A2SClient::new().await.unwrap();
+
+    let (info, log) = client.info_logged(address).await;
+    assert_eq!(info.unwrap().name, "Test Server");
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].direction, Direction::Sent);
+    assert_eq!(log[1].direction, Direction::Received);
+    assert_eq!(log[1].bytes, common::single(&common::info_payload()));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_concurrent_single_packets() {
+    use std::time::Duration;
+
+    // The slow server's reply arrives after the fast one's, though it was asked first
+    let slow = common::mock_server(|_| {
+        std::thread::sleep(Duration::from_millis(100));
+        vec![common::single(&common::info_payload_named("slow"))]
+    });
+    let fast = common::mock_server(|_| vec![common::single(&common::info_payload_named("fast"))]);
+
+    let client = A2SClient::new().await.unwrap();
+
+    let (slow, fast) = futures::join!(client.info(slow), client.info(fast));
+    assert_eq!(slow.unwrap().name, "slow");
+    assert_eq!(fast.unwrap().name, "fast");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_max_concurrent() {
+    use std::time::{Duration, Instant};
+
+    let slow = common::mock_server(|_| {
+        std::thread::sleep(Duration::from_millis(100));
+        vec![common::single(&common::info_payload_named("slow"))]
+    });
+    let fast = common::mock_server(|_| vec![common::single(&common::info_payload_named("fast"))]);
+
+    let mut client = A2SClient::new().await.unwrap();
+    client.max_concurrent(1);
+
+    // Asked second, the fast server can't be queried until the slow one has answered
+    let timed = |addr| {
+        let client = &client;
+        async move {
+            let info = client.info(addr).await.unwrap();
+            (info, Instant::now())
+        }
+    };
+    let ((slow, slow_done), (fast, fast_done)) = futures::join!(timed(slow), timed(fast));
+    assert_eq!(slow.name, "slow");
+    assert_eq!(fast.name, "fast");
+    assert!(fast_done >= slow_done);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_randomize_source_port() {
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    let (sources, received) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || {
+        let mut buf = [0; 1400];
+        while let Ok((_, src)) = server.recv_from(&mut buf) {
+            sources.send(src.port()).unwrap();
+            let reply = common::single(&common::info_payload());
+            server.send_to(&reply, src).unwrap();
+        }
+    });
+
+    let mut client = A2SClient::new().await.unwrap();
+    client.randomize_source_port(true);
+    let own_port = client.local_addr().unwrap().port();
+
+    client.info(address).await.unwrap();
+    client.info(address).await.unwrap();
+
+    let ports: Vec<u16> = received.try_iter().collect();
+    assert_eq!(ports.len(), 2);
+    assert!(ports.iter().all(|&port| port != own_port));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_source_match() {
+    use a2s::SourceMatch;
+
+    // A relay answering from another port than the one queried
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let relay = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut buf = [0; 1400];
+        while let Ok((_, src)) = server.recv_from(&mut buf) {
+            let reply = common::single(&common::info_payload());
+            relay.send_to(&reply, src).unwrap();
+        }
+    });
+
+    let mut client = A2SClient::with_config(a2s::A2SConfig {
+        timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    assert!(matches!(
+        client.info(address).await,
+        Err(a2s::errors::Error::ErrTimeout)
+    ));
+
+    client.source_match(SourceMatch::Lenient);
+    assert_eq!(client.info(address).await.unwrap().map, "de_dust2");
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_resolve_dns_cache() {
+    let client = A2SClient::new().await.unwrap();
+
+    let addr = client.resolve("localhost:27015").await.unwrap();
+    assert!(addr.ip().is_loopback());
+    assert_eq!(client.resolve("localhost:27015").await.unwrap(), addr);
+    assert!(client.resolve("localhost").await.is_err());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_extended_info() {
+    let server = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let client = A2SClient::new().await.unwrap();
+    let extended = client.extended_info(server).await.unwrap();
+
+    assert_eq!(extended.port, Some(27015));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_query_shared() {
+    use a2s::query::RulesQuery;
+    use a2s::transport::is_a2s_datagram;
+    use tokio::net::UdpSocket;
+
+    // RCON traffic on the same socket, interleaved with the A2S responses
+    let server = common::mock_server(|request| {
+        let response = if common::is_probe(request) {
+            common::challenge(42)
+        } else {
+            common::single(&common::rules_payload(&[("sv_gravity", "800")]))
+        };
+        vec![b"rcon 1 output".to_vec(), response]
+    });
+
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket.connect(server).await.unwrap();
+
+    let client = A2SClient::new().await.unwrap();
+    let rules = client.query_shared(&socket, &RulesQuery).await.unwrap();
+    assert_eq!(rules[0].value, "800");
+
+    assert!(!is_a2s_datagram(b"rcon 1 output"));
+    assert!(is_a2s_datagram(&common::challenge(42)));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_flooded_route() {
+    // Far more datagrams than a query has room for, the extra ones are discarded
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload()); 200]);
+    let client = A2SClient::new().await.unwrap();
+
+    for _ in 0..3 {
+        assert_eq!(client.info(address).await.unwrap().name, "Test Server");
+    }
+}
@@ -0,0 +1,794 @@
+mod common;
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_bind_port() {
+    let free = {
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        socket.local_addr().unwrap().port()
+    };
+
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.bind_port(free).unwrap();
+    client.info(address).unwrap();
+    assert_eq!(client.local_addr().unwrap().port(), free);
+
+    let occupied = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    let port = occupied.local_addr().unwrap().port();
+
+    assert!(matches!(
+        client.bind_port(port),
+        Err(a2s::errors::Error::AddrInUse(p)) if p == port
+    ));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_with_config_limits() {
+    let address = common::mock_server(|request| {
+        if common::is_probe(request) {
+            vec![common::challenge(1)]
+        } else {
+            vec![common::fragment(3, 2, 0, &common::single(&[0x45, 0, 0]))]
+        }
+    });
+
+    let config = a2s::A2SConfig {
+        max_packets: 1,
+        ..Default::default()
+    };
+    let client = a2s::A2SClient::with_config(config).unwrap();
+
+    assert!(client.rules(address).unwrap_err().is_invalid_response());
+}
+
+#[test]
+fn test_client_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<a2s::A2SClient>();
+    assert_send_sync::<a2s::A2SConfig>();
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_custom_challenge_header() {
+    let address = common::mock_server(|request| {
+        // Both queries are sent without a challenge first, and asked for one
+        let payload = if common::is_probe(request) || request.len() == 5 || request.len() == 25 {
+            let mut challenge = common::challenge(1);
+            challenge[4] = b'B';
+            challenge
+        } else if request[4] == 0x54 {
+            common::single(&common::info_payload())
+        } else {
+            common::single(&common::players_payload(&["alice"], false))
+        };
+        vec![payload]
+    });
+
+    let mut client = a2s::A2SClient::new().unwrap();
+
+    assert!(client.players(address).unwrap_err().is_invalid_response());
+
+    client.challenge_header(b'B');
+
+    assert_eq!(client.info(address).unwrap().name, "Test Server");
+    assert_eq!(client.players(address).unwrap()[0].name, "alice");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_challenge_mode() {
+    use a2s::ChallengeMode;
+    use std::sync::{Arc, Mutex};
+
+    // Answers players queries whatever challenge they carry, recording them
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let open = common::mock_server(move |request| {
+        recorded.lock().unwrap().push(request.to_vec());
+        vec![common::single(&common::players_payload(&["alice"], false))]
+    });
+
+    let mut client = a2s::A2SClient::new().unwrap();
+
+    assert_eq!(client.config().challenge_mode, ChallengeMode::OnDemand);
+    assert_eq!(client.players(open).unwrap()[0].name, "alice");
+    assert_eq!(
+        requests.lock().unwrap().pop().unwrap(),
+        b"\xff\xff\xff\xffU"
+    );
+
+    client.challenge_mode(ChallengeMode::Always);
+    assert_eq!(client.players(open).unwrap()[0].name, "alice");
+    assert!(common::is_probe(&requests.lock().unwrap().pop().unwrap()));
+
+    let guarded = common::mock_server(|request| {
+        if request.ends_with(&9i32.to_le_bytes()) {
+            vec![common::single(&common::players_payload(&["bob"], false))]
+        } else {
+            vec![common::challenge(9)]
+        }
+    });
+
+    client.challenge_mode(ChallengeMode::Never);
+    assert!(matches!(
+        client.players(guarded),
+        Err(a2s::errors::Error::ChallengeRequired)
+    ));
+
+    // A known challenge is still sent
+    let (players, challenge) = client.players_with_challenge(guarded, Some(9)).unwrap();
+    assert_eq!(players[0].name, "bob");
+    assert_eq!(challenge, Some(9));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_probe_challenge_behavior() {
+    use a2s::probe::ChallengeBehavior;
+
+    let client = a2s::A2SClient::new().unwrap();
+    let info = || common::single(&common::info_payload());
+    let players = || common::single(&common::players_payload(&["alice"], false));
+
+    let open = common::mock_server(move |request| match request[4] {
+        0x54 => vec![info()],
+        _ => vec![players()],
+    });
+    let single = common::mock_server(move |request| match request[4] {
+        0x54 => vec![info()],
+        _ if request.ends_with(&3i32.to_le_bytes()) => vec![players()],
+        _ => vec![common::challenge(3)],
+    });
+    let repeated = common::mock_server(move |request| match request[4] {
+        0x54 => vec![info()],
+        _ => vec![common::challenge(request.len() as i32)],
+    });
+    let on_info = common::mock_server(|_| vec![common::challenge(3)]);
+
+    let behaviors = [open, single, repeated, on_info]
+        .iter()
+        .map(|&address| client.probe_challenge_behavior(address).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        behaviors,
+        [
+            ChallengeBehavior::NoChallenge,
+            ChallengeBehavior::SingleChallenge,
+            ChallengeBehavior::RepeatedChallenge,
+            ChallengeBehavior::ChallengeOnInfo,
+        ]
+    );
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_request_bytes() {
+    use a2s::ChallengeMode;
+    use std::sync::{Arc, Mutex};
+
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let received = requests.clone();
+    let address = common::mock_server(move |request| {
+        received.lock().unwrap().push(request.to_vec());
+        match (request[4], request.len()) {
+            (b'T', 25) | (_, 5) | (_, 9) if !request.ends_with(&7i32.to_le_bytes()) => {
+                vec![common::challenge(7)]
+            }
+            (b'T', _) => vec![common::single(&common::info_payload())],
+            (b'U', _) => vec![common::single(&common::players_payload(&["alice"], false))],
+            _ => vec![common::single(&common::rules_payload(&[(
+                "sv_gravity",
+                "800",
+            )]))],
+        }
+    });
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    let mut expected = vec![
+        client.info_request_bytes(None),
+        client.info_request_bytes(Some(7)),
+        client.players_request_bytes(None),
+        client.players_request_bytes(Some(7)),
+    ];
+    client.info(address).unwrap();
+    client.players(address).unwrap();
+
+    // The probe is part of the first request when always sent
+    client.challenge_mode(ChallengeMode::Always);
+    expected.push(client.rules_request_bytes(None));
+    expected.push(client.rules_request_bytes(Some(7)));
+    client.rules(address).unwrap();
+
+    assert_eq!(*requests.lock().unwrap(), expected);
+    assert_eq!(
+        client.rules_request_bytes(None),
+        b"\xff\xff\xff\xffV\xff\xff\xff\xff"
+    );
+    assert_eq!(client.info_request_bytes(Some(7))[25..], 7i32.to_le_bytes());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_is_reflection_vulnerable() {
+    let client = a2s::A2SClient::new().unwrap();
+
+    let unpatched = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+    let gold_source =
+        common::mock_server(|_| vec![common::single(&common::gold_source_info_payload())]);
+    let patched = common::mock_server(|request| match request.len() {
+        25 => vec![common::challenge(3)],
+        _ => vec![common::single(&common::info_payload())],
+    });
+    let confused = common::mock_server(|_| vec![common::single(&[0x44, 0])]);
+
+    assert!(client.is_reflection_vulnerable(unpatched).unwrap());
+    assert!(client.is_reflection_vulnerable(gold_source).unwrap());
+    assert!(!client.is_reflection_vulnerable(patched).unwrap());
+    assert!(client
+        .is_reflection_vulnerable(confused)
+        .unwrap_err()
+        .is_invalid_response());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_logged() {
+    use a2s::trace::Direction;
+
+    let mut payload = vec![0xff, 0xff, 0xff, 0xff];
+    payload.extend(common::rules_payload(&[("sv_gravity", "800")]));
+    let (first, second) = payload.split_at(payload.len() / 2);
+    let fragments = vec![
+        common::fragment(7, 2, 0, first),
+        common::fragment(7, 2, 1, second),
+    ];
+
+    let replies = fragments.clone();
+    let address = common::mock_server(move |request| {
+        if request.ends_with(&3i32.to_le_bytes()) {
+            replies.clone()
+        } else {
+            vec![common::challenge(3)]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+    let (rules, log) = client.rules_logged(address);
+
+    assert_eq!(rules.unwrap()[0].value, "800");
+
+    let exchange = log
+        .iter()
+        .map(|packet| (packet.direction, packet.bytes.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        exchange,
+        [
+            (Direction::Sent, b"\xff\xff\xff\xffV".to_vec()),
+            (Direction::Received, common::challenge(3)),
+            (Direction::Sent, b"\xff\xff\xff\xffV\x03\0\0\0".to_vec()),
+            (Direction::Received, fragments[0].clone()),
+            (Direction::Received, fragments[1].clone()),
+        ]
+    );
+    assert!(log.windows(2).all(|w| w[0].elapsed <= w[1].elapsed));
+
+    // The log is kept when a query fails, here on the rules sent back for the info
+    let (info, log) = client.info_logged(address);
+    assert!(info.is_err());
+    assert_eq!(log[1].bytes, common::challenge(3));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_stray_datagram_discarded() {
+    use std::time::Duration;
+
+    let address = common::mock_server(|_| {
+        std::thread::sleep(Duration::from_millis(100));
+        vec![common::single(&common::info_payload_named("real"))]
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+    let port = client.local_addr().unwrap().port();
+
+    // Another server's reply arriving first must not be taken for this one's
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        let stray = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let reply = common::single(&common::info_payload_named("stray"));
+        stray.send_to(&reply, ("127.0.0.1", port)).unwrap();
+    });
+
+    assert_eq!(client.info(address).unwrap().name, "real");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_sync_timeout() {
+    let config = a2s::A2SConfig {
+        timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    };
+    let client = a2s::A2SClient::with_config(config).unwrap();
+
+    // TEST-NET-1, reserved for documentation and never routed
+    assert!(matches!(
+        client.info("192.0.2.1:27015"),
+        Err(a2s::errors::Error::ErrTimeout)
+    ));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_incomplete_response() {
+    let address = common::mock_server(|request| {
+        if common::is_probe(request) {
+            vec![common::challenge(1)]
+        } else {
+            vec![common::fragment(3, 3, 0, &common::single(&[0x45, 0, 0]))]
+        }
+    });
+
+    let config = a2s::A2SConfig {
+        timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    };
+    let client = a2s::A2SClient::with_config(config).unwrap();
+
+    assert!(matches!(
+        client.rules(address),
+        Err(a2s::errors::Error::IncompleteResponse {
+            received: 1,
+            expected: 3
+        })
+    ));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_empty_response() {
+    let address = common::mock_server(|_| vec![Vec::new()]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert!(matches!(
+        client.info(address),
+        Err(a2s::errors::Error::EmptyResponse)
+    ));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_config_getter() {
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.max_size(2000).app_id(2400);
+
+    assert_eq!(client.config().max_size, 2000);
+    assert_eq!(client.config().app_id, 2400);
+    assert_eq!(client.config().retries, a2s::A2SConfig::default().retries);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_scanner() {
+    let config = a2s::A2SConfig::scanner();
+
+    assert_eq!(config.timeout, std::time::Duration::from_secs(1));
+    assert_eq!(config.retries, 2);
+    assert_eq!(config.recv_buffer_size, Some(4 * 1024 * 1024));
+
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let client = a2s::A2SClient::scanner().unwrap();
+
+    assert_eq!(client.info(address).unwrap().name, "Test Server");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_reconnect() {
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.info(address).unwrap();
+
+    client.reconnect().unwrap();
+
+    assert_eq!(client.info(address).unwrap().name, "Test Server");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_connection_refused() {
+    let closed = {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.local_addr().unwrap()
+    };
+
+    let client = a2s::A2SClient::new().unwrap();
+    client.connect(closed).unwrap();
+
+    assert!(matches!(
+        client.info(closed),
+        Err(a2s::errors::Error::ConnectionRefused)
+    ));
+    assert!(!client.is_alive(closed).unwrap());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_scan() {
+    let names = ["first", "second", "third", "fourth"];
+    let servers: Vec<_> = names
+        .iter()
+        .map(|&name| {
+            common::mock_server(move |_| vec![common::single(&common::info_payload_named(name))])
+        })
+        .collect();
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let mut found = Vec::new();
+    client
+        .scan(servers.clone(), 2, |addr, info| {
+            found.push((addr, info.unwrap().name))
+        })
+        .unwrap();
+
+    found.sort();
+    let mut expected: Vec<_> = servers
+        .into_iter()
+        .zip(names.iter().map(|name| name.to_string()))
+        .collect();
+    expected.sort();
+    assert_eq!(found, expected);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_scan_min_players() {
+    use a2s::info::Info;
+    use std::io::Cursor;
+
+    // Players counts, bots included, of servers running a single bot
+    let servers: Vec<_> = [1, 2, 5]
+        .iter()
+        .map(|&players| {
+            let mut info = Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+            info.players = players;
+            info.bots = 1;
+            let datagram = info.to_bytes();
+            common::mock_server(move |_| vec![datagram.clone()])
+        })
+        .collect();
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let mut humans = Vec::new();
+    client
+        .scan_min_players(servers, 2, 1, |_, info| humans.push(info.unwrap().humans()))
+        .unwrap();
+
+    humans.sort();
+    assert_eq!(humans, [1, 4]);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_truncated_challenge() {
+    let server = common::mock_server(|_| vec![vec![0xff, 0xff, 0xff, 0xff, b'A']]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert!(client.info(server).unwrap_err().is_invalid_response());
+    assert!(client.rules(server).unwrap_err().is_invalid_response());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_max_total_duration() {
+    use std::time::{Duration, Instant};
+
+    // Never answers, so every attempt times out
+    let silent = common::mock_server(|_| vec![]);
+
+    let client = a2s::A2SClient::with_config(a2s::A2SConfig {
+        timeout: Duration::from_millis(200),
+        retries: 5,
+        max_total_duration: Some(Duration::from_millis(300)),
+        ..Default::default()
+    })
+    .unwrap();
+
+    // Without the bound, the 6 attempts would take 1.2 seconds
+    let start = Instant::now();
+    assert!(matches!(
+        client.info(silent),
+        Err(a2s::errors::Error::ErrTimeout)
+    ));
+    assert!(start.elapsed() < Duration::from_millis(600));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_fragment_timeout() {
+    use std::time::{Duration, Instant};
+
+    let payload = common::single(&common::info_payload());
+    let (first, _) = payload.split_at(16);
+    let first = first.to_vec();
+
+    // Starts a multi-packet response, but never sends its second fragment
+    let server = common::mock_server(move |_| vec![common::fragment(9, 2, 0, &first)]);
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.fragment_timeout(Duration::from_millis(200));
+
+    let start = Instant::now();
+    assert!(matches!(
+        client.info(server),
+        Err(a2s::errors::Error::IncompleteResponse {
+            received: 1,
+            expected: 2
+        })
+    ));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_multipacket_challenge() {
+    // A proxy splitting even the challenge reply into fragments
+    let reply = common::challenge(5);
+    let (first, second) = reply.split_at(6);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let server = common::mock_server(move |request| {
+        if request.ends_with(&5i32.to_le_bytes()) {
+            vec![common::single(&common::players_payload(&["alice"], false))]
+        } else {
+            vec![
+                common::fragment(4, 2, 0, &first),
+                common::fragment(4, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert_eq!(client.players(server).unwrap()[0].name, "alice");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_randomize_source_port() {
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    let (sources, received) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || {
+        let mut buf = [0; 1400];
+        while let Ok((read, src)) = server.recv_from(&mut buf) {
+            sources.send(src.port()).unwrap();
+            let reply = if buf[..read].ends_with(&9i32.to_le_bytes()) {
+                common::single(&common::players_payload(&["alice"], false))
+            } else {
+                common::challenge(9)
+            };
+            server.send_to(&reply, src).unwrap();
+        }
+    });
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.randomize_source_port(true);
+    let own_port = client.local_addr().unwrap().port();
+
+    // The challenge handshake spans two sockets, the challenge being tied to the IP only
+    assert_eq!(client.players(address).unwrap()[0].name, "alice");
+
+    let ports: Vec<u16> = received.try_iter().collect();
+    assert_eq!(ports.len(), 2);
+    assert!(ports.iter().all(|&port| port != own_port));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_source_match() {
+    use a2s::SourceMatch;
+
+    // A relay answering from another port than the one queried
+    let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let relay = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut buf = [0; 1400];
+        while let Ok((_, src)) = server.recv_from(&mut buf) {
+            let reply = common::single(&common::info_payload());
+            relay.send_to(&reply, src).unwrap();
+        }
+    });
+
+    let mut client = a2s::A2SClient::with_config(a2s::A2SConfig {
+        timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert!(matches!(
+        client.info(address),
+        Err(a2s::errors::Error::ErrTimeout)
+    ));
+
+    client.source_match(SourceMatch::Lenient);
+    assert_eq!(client.info(address).unwrap().map, "de_dust2");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_resolve_dns_cache() {
+    let mut client = a2s::A2SClient::new().unwrap();
+
+    let addr = client.resolve("localhost:27015").unwrap();
+    assert!(addr.ip().is_loopback());
+    assert_eq!(addr.port(), 27015);
+    assert_eq!(client.resolve("localhost:27015").unwrap(), addr);
+    assert!(client.resolve("localhost").is_err());
+
+    client.clear_dns_cache();
+    client.dns_cache_ttl(None);
+    assert_eq!(client.resolve("localhost:27015").unwrap(), addr);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_challenge_shape() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Starts with the challenge header, but is longer than a challenge response
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counted = requests.clone();
+    let server = common::mock_server(move |_| {
+        counted.fetch_add(1, Ordering::SeqCst);
+        vec![vec![0xff, 0xff, 0xff, 0xff, b'A', 1, 2, 3, 4, 5]]
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    // Parsed as the response rather than answered with a challenge of 0x04030201
+    assert!(client.info(server).unwrap_err().is_invalid_response());
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_malformed_dump_dir() {
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("a2s-malformed-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let malformed = vec![0x49, 17, b'N', b'a', b'm', b'e'];
+    let payload = malformed.clone();
+    let broken = common::mock_server(move |_| vec![common::single(&payload)]);
+    let working = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.info(broken).unwrap_err();
+    assert!(!dir.exists());
+
+    client.malformed_dump_dir(Some(dir.clone()));
+    client.info(working).unwrap();
+    client.info(broken).unwrap_err();
+
+    let files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1);
+    let name = files[0].file_name().unwrap().to_str().unwrap().to_owned();
+    assert!(name.contains(&format!("-info-127.0.0.1_{}-", broken.port())));
+    assert_eq!(fs::read(&files[0]).unwrap(), malformed);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_size_collector() {
+    use a2s::histogram::SizeHistogram;
+    use std::sync::Arc;
+
+    let payload = common::rules_payload(&[("mp_timelimit", "30"), ("sv_gravity", "800")]);
+    let rules = common::single(&payload);
+    let (first, second) = rules.split_at(rules.len() / 2);
+    let (first, second) = (first.to_vec(), second.to_vec());
+    let address = common::mock_server(move |request| match request[4] {
+        b'T' => vec![common::single(&common::info_payload())],
+        _ => vec![
+            common::fragment(7, 2, 0, &first),
+            common::fragment(7, 2, 1, &second),
+        ],
+    });
+
+    let histogram = Arc::new(SizeHistogram::new());
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.size_collector(Some(histogram.clone()));
+
+    client.info(address).unwrap();
+    client.rules(address).unwrap();
+
+    assert_eq!(histogram.count(), 2);
+    assert_eq!(histogram.packet_counts(), [(1, 1), (2, 1)]);
+    assert_eq!(histogram.max_packets(), 2);
+    let info_payload = common::info_payload().len();
+    assert_eq!(histogram.max_payload(), info_payload);
+    assert_eq!(histogram.max_datagram(), info_payload + 4);
+    assert_eq!(
+        histogram.payload_quantile(1.0),
+        Some(info_payload.next_power_of_two())
+    );
+    assert_eq!(
+        histogram.payload_quantile(0.5),
+        Some(payload.len().next_power_of_two())
+    );
+
+    histogram.clear();
+    assert_eq!(histogram.count(), 0);
+    assert_eq!(histogram.payload_quantile(0.5), None);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_buffer_pool() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Long and short names in turn, so reused buffers hold leftovers of a longer response
+    let long = "x".repeat(1000);
+    let requests = AtomicUsize::new(0);
+    let rules = common::single(&common::rules_payload(&[("sv_gravity", "800")]));
+    let (first, second) = rules.split_at(rules.len() / 2);
+    let (first, second) = (first.to_vec(), second.to_vec());
+    let address = common::mock_server(move |request| match request[4] {
+        b'T' if requests.fetch_add(1, Ordering::SeqCst).is_multiple_of(2) => {
+            vec![common::single(&common::info_payload_named(&long))]
+        }
+        b'T' => vec![common::single(&common::info_payload())],
+        _ => vec![
+            common::fragment(7, 2, 0, &first),
+            common::fragment(7, 2, 1, &second),
+        ],
+    });
+
+    for size in [0, 1, 4] {
+        let mut client = a2s::A2SClient::new().unwrap();
+        client.buffer_pool_size(size);
+
+        for _ in 0..2 {
+            assert_eq!(client.info(address).unwrap().name.len(), 1000);
+            assert_eq!(client.info(address).unwrap().name, "Test Server");
+            assert_eq!(client.rules(address).unwrap()[0].value, "800");
+        }
+    }
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_with_request_id() {
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.retries(3);
+
+    // Without the tracing feature the id goes nowhere, the query is the same
+    let tagged = client.with_request_id("7f3a");
+    assert_eq!(tagged.info(address).unwrap().name, "Test Server");
+    assert_eq!(tagged.config().retries, 3);
+}
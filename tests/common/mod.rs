@@ -0,0 +1,172 @@
+#![allow(dead_code)]
+
+/// A minimal Source A2S_INFO payload (without the single packet header) with EDF 0x80 (port).
+pub fn info_payload() -> Vec<u8> {
+    info_payload_named("Test Server")
+}
+
+pub fn info_payload_named(name: &str) -> Vec<u8> {
+    let mut bytes = vec![0x49, 17];
+    bytes.extend(name.as_bytes());
+    bytes.push(0);
+    bytes.extend(b"de_dust2\0");
+    bytes.extend(b"csgo\0");
+    bytes.extend(b"Counter-Strike: Global Offensive\0");
+    bytes.extend(730u16.to_le_bytes());
+    bytes.extend(&[5, 24, 1, b'd', b'l', 0, 1]);
+    bytes.extend(b"1.38.0.0\0");
+    bytes.push(0x80);
+    bytes.extend(27015u16.to_le_bytes());
+    bytes
+}
+
+/// Wraps a payload in the single packet header.
+pub fn single(payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0xff, 0xff, 0xff, 0xff];
+    bytes.extend(payload);
+    bytes
+}
+
+/// Spawns a local UDP server that answers every request with the datagrams returned by `handler`.
+pub fn mock_server<F>(mut handler: F) -> std::net::SocketAddr
+where
+    F: FnMut(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+{
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(std::time::Duration::from_secs(10)))
+        .unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        let mut buf = [0; 1400];
+        while let Ok((read, src)) = socket.recv_from(&mut buf) {
+            for datagram in handler(&buf[..read]) {
+                socket.send_to(&datagram, src).unwrap();
+            }
+        }
+    });
+
+    addr
+}
+
+/// Builds a Source multi-packet fragment. The first fragment's payload should start with the
+/// single packet header, like real servers send it.
+pub fn fragment(id: i32, total: u8, number: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0xfe, 0xff, 0xff, 0xff];
+    bytes.extend(id.to_le_bytes());
+    bytes.push(total);
+    bytes.push(number);
+    bytes.extend(1248u16.to_le_bytes());
+    bytes.extend(payload);
+    bytes
+}
+
+/// A GoldSource multi-packet fragment, with the number and total packed into one byte.
+/// Synthesized from the documented layout, not captured from a server.
+pub fn gold_source_fragment(id: i32, total: u8, number: u8, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0xfe, 0xff, 0xff, 0xff];
+    bytes.extend(id.to_le_bytes());
+    bytes.push(number << 4 | total);
+    bytes.extend(payload);
+    bytes
+}
+
+/// Whether the request is the initial challenge probe (challenge -1) of a players/rules query.
+pub fn is_probe(request: &[u8]) -> bool {
+    request.ends_with(&[0xff, 0xff, 0xff, 0xff])
+}
+
+/// A challenge response carrying `challenge`.
+pub fn challenge(challenge: i32) -> Vec<u8> {
+    let mut bytes = vec![0xff, 0xff, 0xff, 0xff, b'A'];
+    bytes.extend(challenge.to_le_bytes());
+    bytes
+}
+
+/// An A2S_RULES payload (without the single packet header) holding `rules`.
+pub fn rules_payload(rules: &[(&str, &str)]) -> Vec<u8> {
+    let mut bytes = vec![0x45];
+    bytes.extend((rules.len() as u16).to_le_bytes());
+    for (name, value) in rules {
+        bytes.extend(name.as_bytes());
+        bytes.push(0);
+        bytes.extend(value.as_bytes());
+        bytes.push(0);
+    }
+    bytes
+}
+
+/// An obsolete GoldSource A2S_INFO payload (without the single packet header) of a mod server.
+/// Synthesized from the documented layout, not captured from a server.
+pub fn gold_source_info_payload() -> Vec<u8> {
+    let mut bytes = vec![0x6d];
+    bytes.extend(b"77.111.194.110:27015\0");
+    bytes.extend(b"Old School Server\0");
+    bytes.extend(b"de_dust\0");
+    bytes.extend(b"cstrike\0");
+    bytes.extend(b"Counter-Strike\0");
+    bytes.extend(&[12, 32, 47, b'D', b'L', 0, 1]);
+    bytes.extend(b"http://counter-strike.net\0");
+    bytes.extend(b"\0");
+    bytes.push(0);
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(184_000_000u32.to_le_bytes());
+    bytes.extend(&[1, 1, 1, 0]);
+    bytes
+}
+
+/// An A2S_INFO payload (without the single packet header) of a server running The Ship.
+pub fn ship_info_payload() -> Vec<u8> {
+    ship_info_payload_with(7, true)
+}
+
+/// A The Ship A2S_INFO payload of a build reporting `protocol`, with or without The Ship fields.
+/// Synthesized from the documented layout, no capture of a The Ship server was available.
+pub fn ship_info_payload_with(protocol: u8, ship_fields: bool) -> Vec<u8> {
+    let mut bytes = vec![0x49, protocol];
+    bytes.extend(b"Ship Server\0");
+    bytes.extend(b"batavier\0");
+    bytes.extend(b"ship\0");
+    bytes.extend(b"The Ship\0");
+    bytes.extend(2400u16.to_le_bytes());
+    bytes.extend(&[2, 16, 0, b'd', b'w', 0, 1]);
+    if ship_fields {
+        bytes.extend(&[0, 2, 30]);
+    }
+    bytes.extend(b"1.0.0.4\0");
+    bytes
+}
+
+/// An A2S_PLAYER payload (without the single packet header), with The Ship fields if `ship`.
+pub fn players_payload(names: &[&str], ship: bool) -> Vec<u8> {
+    let mut bytes = vec![0x44, names.len() as u8];
+    for name in names {
+        bytes.push(0);
+        bytes.extend(name.as_bytes());
+        bytes.push(0);
+        bytes.extend(10i32.to_le_bytes());
+        bytes.extend(60f32.to_le_bytes());
+        if ship {
+            bytes.extend(3u32.to_le_bytes());
+            bytes.extend(500u32.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+/// Answers info, players and rules queries like a server requiring challenges for players and
+/// rules only.
+pub fn full_server(
+    info: Vec<u8>,
+    players: Vec<u8>,
+    rules: Vec<u8>,
+) -> impl FnMut(&[u8]) -> Vec<Vec<u8>> + Send + 'static {
+    move |request| match request[4] {
+        0x54 => vec![single(&info)],
+        0x55 | 0x56 if is_probe(request) => vec![challenge(1)],
+        0x55 => vec![single(&players)],
+        0x56 => vec![single(&rules)],
+        _ => vec![],
+    }
+}
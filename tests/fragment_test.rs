@@ -0,0 +1,170 @@
+mod common;
+
+use a2s::errors::Error;
+use a2s::fragment::FragmentAssembler;
+
+fn rules() -> Vec<u8> {
+    common::rules_payload(&[("mp_timelimit", "30"), ("sv_gravity", "800")])
+}
+
+#[test]
+fn test_fragment_assembler_single_packet() {
+    let mut assembler = FragmentAssembler::new();
+
+    let payload = assembler.push(&common::single(&rules())).unwrap();
+    assert_eq!(payload, Some(rules()));
+    assert!(!assembler.is_pending());
+}
+
+#[test]
+fn test_fragment_assembler_reordered_duplicates() {
+    let payload = common::single(&rules());
+    let parts: Vec<_> = payload.chunks(12).collect();
+    let total = parts.len() as u8;
+    let fragment = |number: usize| common::fragment(7, total, number as u8, parts[number]);
+
+    let mut assembler = FragmentAssembler::new();
+
+    assert_eq!(assembler.push(&fragment(2)).unwrap(), None);
+    assert_eq!(assembler.push(&fragment(0)).unwrap(), None);
+    // A retransmitted fragment can't stand in for the missing one
+    assert_eq!(assembler.push(&fragment(0)).unwrap(), None);
+    assert!(assembler.is_pending());
+    for number in (1..parts.len()).filter(|&number| number != 2) {
+        let result = assembler.push(&fragment(number)).unwrap();
+        assert_eq!(result.is_some(), number == parts.len() - 1);
+        if let Some(result) = result {
+            assert_eq!(result, rules());
+        }
+    }
+    assert!(!assembler.is_pending());
+
+    // The next datagram starts a new response
+    assert_eq!(
+        assembler.push(&common::single(&rules())).unwrap(),
+        Some(rules())
+    );
+}
+
+#[test]
+fn test_fragment_assembler_errors() {
+    let payload = common::single(&rules());
+    let (first, second) = payload.split_at(payload.len() / 2);
+
+    let mut assembler = FragmentAssembler::new();
+
+    assert_eq!(
+        assembler.push(&common::fragment(7, 2, 0, first)).unwrap(),
+        None
+    );
+    assert!(matches!(
+        assembler.push(&common::fragment(8, 2, 1, second)),
+        Err(Error::MismatchID)
+    ));
+    assert!(!assembler.is_pending());
+
+    assert_eq!(
+        assembler.push(&common::fragment(9, 2, 0, first)).unwrap(),
+        None
+    );
+    assert!(matches!(
+        assembler.reset(),
+        Some(Error::IncompleteResponse {
+            received: 1,
+            expected: 2
+        })
+    ));
+    assert!(assembler.reset().is_none());
+
+    assert!(matches!(assembler.push(&[]), Err(Error::EmptyResponse)));
+}
+
+#[test]
+fn test_fragment_assembler_resync() {
+    let payload = common::single(&rules());
+    let (first, second) = payload.split_at(payload.len() / 2);
+
+    let config = a2s::A2SConfig {
+        resync_fragments: true,
+        ..Default::default()
+    };
+    let mut assembler = FragmentAssembler::with_config(config);
+
+    assert_eq!(
+        assembler.push(&common::fragment(8, 2, 0, first)).unwrap(),
+        None
+    );
+
+    // A whole stale response of an earlier query arrives in between, and is never taken over
+    // the one being received
+    let stale = common::single(&common::rules_payload(&[("stale", "1")]));
+    let (stale_first, stale_second) = stale.split_at(stale.len() / 2);
+    for fragment in [
+        common::fragment(7, 2, 0, stale_first),
+        common::fragment(7, 2, 1, stale_second),
+    ] {
+        assert_eq!(assembler.push(&fragment).unwrap(), None);
+    }
+
+    assert_eq!(
+        assembler.push(&common::fragment(8, 2, 1, second)).unwrap(),
+        Some(rules())
+    );
+    assert!(!assembler.is_pending());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_resync_stale_fragment() {
+    let payload = common::single(&common::info_payload());
+    let (first, second) = payload.split_at(payload.len() / 2);
+    let datagrams = vec![
+        common::fragment(8, 2, 0, first),
+        common::fragment(7, 2, 1, b"stale"),
+        common::fragment(8, 2, 1, second),
+    ];
+    let addr = common::mock_server(move |_| datagrams.clone());
+
+    let client = a2s::A2SClient::new().unwrap();
+    assert!(matches!(client.info(addr), Err(Error::MismatchID)));
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.resync_fragments(true);
+    assert_eq!(client.info(addr).unwrap().name, "Test Server");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_resync_stale_response() {
+    use std::time::Duration;
+
+    let mut requests = 0;
+    let addr = common::mock_server(move |_| {
+        requests += 1;
+        let (id, name) = if requests == 1 {
+            // Answers the first query only after it gave up
+            std::thread::sleep(Duration::from_millis(300));
+            (7, "Stale Server")
+        } else {
+            (8, "Test Server")
+        };
+        let payload = common::single(&common::info_payload_named(name));
+        let (first, second) = payload.split_at(payload.len() / 2);
+        vec![
+            common::fragment(id, 2, 0, first),
+            common::fragment(id, 2, 1, second),
+        ]
+    });
+
+    let config = a2s::A2SConfig {
+        timeout: Duration::from_millis(100),
+        resync_fragments: true,
+        ..Default::default()
+    };
+    let client = a2s::A2SClient::with_config(config).unwrap();
+    assert!(matches!(client.info(addr), Err(Error::ErrTimeout)));
+
+    // The complete stale response is queued before the next query sends its request
+    std::thread::sleep(Duration::from_millis(400));
+    assert_eq!(client.info(addr).unwrap().name, "Test Server");
+}
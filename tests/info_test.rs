@@ -1,3 +1,5 @@
+mod common;
+
 #[cfg(not(feature = "async"))]
 #[test]
 fn test_info() {
@@ -7,3 +9,1180 @@ fn test_info() {
 
     println!("{:?}", result);
 }
+
+#[test]
+fn test_info_extra_bytes() {
+    use std::io::Cursor;
+
+    let mut payload = common::info_payload();
+    payload.extend(&[0xde, 0xad, 0xbe, 0xef]);
+
+    let info = a2s::info::Info::from_cursor(Cursor::new(payload.clone())).unwrap();
+
+    assert_eq!(info.extended_server_info.port, Some(27015));
+    assert_eq!(info.extra, vec![0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(info.to_bytes()[4..], payload[..]);
+}
+
+#[test]
+fn test_info_proxy_ping() {
+    use std::io::Cursor;
+
+    let mut payload = common::info_payload();
+    payload.extend(42u16.to_le_bytes());
+
+    let info = a2s::info::Info::from_cursor(Cursor::new(payload.clone())).unwrap();
+
+    assert_eq!(info.proxy_ping(), Some(42));
+    assert_eq!(info.extra, 42u16.to_le_bytes());
+    assert_eq!(info.to_bytes()[4..], payload[..]);
+
+    // The width read is written back
+    let mut payload = common::info_payload();
+    payload.extend(250u32.to_le_bytes());
+
+    let info = a2s::info::Info::from_cursor(Cursor::new(payload.clone())).unwrap();
+
+    assert_eq!(info.proxy_ping(), Some(250));
+    assert_eq!(info.to_bytes()[4..], payload[..]);
+
+    // Anything else isn't a ping
+    let mut payload = common::info_payload();
+    payload.extend(&[1, 2, 3]);
+
+    let info = a2s::info::Info::from_cursor(Cursor::new(payload)).unwrap();
+
+    assert_eq!(info.proxy_ping(), None);
+    assert_eq!(info.extra, vec![1, 2, 3]);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_is_alive_malformed_body() {
+    let address = common::mock_server(|_| vec![common::single(&[0x49, 0xff])]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert!(client.info(address).is_err());
+    assert!(client.is_alive(address).unwrap());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_is_alive_empty_response() {
+    let address = common::mock_server(|_| vec![vec![]]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert!(!client.is_alive(address).unwrap());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_is_alive_incomplete_response() {
+    // Only the first of two fragments ever arrives
+    let payload = common::single(&common::info_payload());
+    let first = common::fragment(1, 2, 0, &payload[..payload.len() / 2]);
+    let address = common::mock_server(move |_| vec![first.clone()]);
+
+    let config = a2s::A2SConfig {
+        timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    };
+    let client = a2s::A2SClient::with_config(config).unwrap();
+
+    assert!(matches!(
+        client.info(address),
+        Err(a2s::errors::Error::IncompleteResponse {
+            received: 1,
+            expected: 2
+        })
+    ));
+    assert!(client.is_alive(address).unwrap());
+}
+
+#[test]
+fn test_steam_id() {
+    use a2s::info::SteamId;
+
+    let steam_id = SteamId(85568392923919828);
+
+    assert_eq!(steam_id.universe(), 1);
+    assert_eq!(steam_id.account_type(), 3);
+    assert_eq!(steam_id.instance(), 0);
+    assert_eq!(steam_id.account_id(), 3_880_404);
+    assert_eq!(steam_id.steam2(), "STEAM_1:0:1940202");
+    assert_eq!(steam_id.steam3(), "[G:1:3880404]");
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn test_info_bincode_roundtrip() {
+    use std::io::Cursor;
+
+    let info = a2s::info::Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    let encoded = bincode::serialize(&info).unwrap();
+    let decoded: a2s::info::Info = bincode::deserialize(&encoded).unwrap();
+
+    assert_eq!(decoded.to_bytes(), info.to_bytes());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_by_deadline() {
+    use std::time::{Duration, Instant};
+
+    let address = common::mock_server(|_| vec![]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let start = Instant::now();
+    let result = client.info_by(address, start + Duration::from_millis(200));
+
+    assert!(matches!(result, Err(a2s::errors::Error::ErrTimeout)));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_server_type_os_roundtrip() {
+    use std::io::Cursor;
+
+    // Offset of the server type byte, the OS byte follows it
+    let payload = common::info_payload();
+    let offset = payload.windows(2).position(|w| w == b"dl").unwrap();
+
+    for server_type in [b'd', b'i', b'p'] {
+        for server_os in [b'l', b'w', b'm', b'o'] {
+            let mut payload = payload.clone();
+            payload[offset] = server_type;
+            payload[offset + 1] = server_os;
+
+            let info = a2s::info::Info::from_cursor(Cursor::new(payload.clone())).unwrap();
+
+            assert_eq!(info.server_type_raw, server_type);
+            assert_eq!(info.server_os_raw, server_os);
+            assert_eq!(info.to_bytes()[4..], payload[..]);
+        }
+    }
+
+    let mut payload = payload;
+    payload[offset + 1] = b'x';
+    assert!(a2s::info::Info::from_cursor(Cursor::new(payload)).is_err());
+}
+
+#[test]
+fn test_legacy_mac_os_roundtrip() {
+    use a2s::info::{Info, ServerOS};
+    use std::io::Cursor;
+
+    let mut payload = common::info_payload();
+    let offset = payload.windows(2).position(|w| w == b"dl").unwrap() + 1;
+    payload[offset] = b'o';
+
+    let mut info = Info::from_cursor(Cursor::new(payload.clone())).unwrap();
+
+    assert_eq!(info.server_os, ServerOS::Mac);
+    assert_eq!(info.to_bytes()[4 + offset], b'o');
+
+    // A changed OS is encoded with its canonical byte
+    info.server_os = ServerOS::Windows;
+    assert_eq!(info.to_bytes()[4 + offset], b'w');
+}
+
+#[test]
+fn test_gold_source_info() {
+    use a2s::info::{Info, ServerOS, ServerType};
+    use std::io::Cursor;
+
+    let payload = common::gold_source_info_payload();
+
+    let info = Info::from_cursor(Cursor::new(payload.clone())).unwrap();
+
+    assert_eq!(
+        info.gold_source_address.as_deref(),
+        Some("77.111.194.110:27015")
+    );
+    assert_eq!(info.name, "Old School Server");
+    assert_eq!(info.players, 12);
+    assert_eq!(info.protocol, 47);
+    assert_eq!(info.server_type, ServerType::Dedicated);
+    assert_eq!(info.server_os, ServerOS::Linux);
+    assert!(info.vac);
+    assert_eq!(
+        info.gold_source_mod.as_ref().unwrap().link,
+        "http://counter-strike.net"
+    );
+    assert_eq!(info.to_bytes()[4..], payload[..]);
+
+    let source = Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+    assert_eq!(source.gold_source_address, None);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_engine() {
+    use a2s::info::Engine;
+
+    let gold_source =
+        common::mock_server(|_| vec![common::single(&common::gold_source_info_payload())]);
+    let source = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let info = client.info(gold_source).unwrap();
+    assert_eq!(info.engine(), Engine::GoldSource);
+    assert_eq!(info.name, "Old School Server");
+
+    assert_eq!(client.info(source).unwrap().engine(), Engine::Source);
+}
+
+#[test]
+fn test_app_name() {
+    use a2s::apps::app_name;
+
+    assert_eq!(app_name(10), Some("Counter-Strike"));
+    assert_eq!(app_name(440), Some("Team Fortress 2"));
+    assert_eq!(app_name(730), Some("Counter-Strike: Global Offensive"));
+    assert_eq!(app_name(17710), Some("Nuclear Dawn"));
+    assert_eq!(app_name(0), None);
+    assert_eq!(app_name(441), None);
+}
+
+#[test]
+fn test_info_empty_folder_and_game() {
+    use a2s::info::Info;
+    use std::io::Cursor;
+
+    let mut payload = vec![0x49, 17];
+    payload.extend(b"Bare Server\0de_dust2\0\0\0");
+    payload.extend(730u16.to_le_bytes());
+    payload.extend(&[5, 24, 1, b'd', b'l', 0, 1]);
+    payload.extend(b"1.38.0.0\0");
+
+    let mut info = Info::from_cursor(Cursor::new(payload)).unwrap();
+    assert_eq!(info.map, "de_dust2");
+    assert_eq!(info.folder, "");
+    assert_eq!(info.game, "");
+    assert_eq!(info.version, "1.38.0.0");
+    assert!(info.to_json_minimal().contains(r#""folder":"","game":"""#));
+
+    assert_eq!(info.game_name(), Some("Counter-Strike: Global Offensive"));
+    info.app_id = 441;
+    assert_eq!(info.game_name(), None);
+    info.folder = "mymod".to_owned();
+    assert_eq!(info.game_name(), Some("mymod"));
+    info.game = "My Mod".to_owned();
+    assert_eq!(info.game_name(), Some("My Mod"));
+}
+
+#[test]
+fn test_info_is_sdr() {
+    use a2s::info::Info;
+    use std::io::Cursor;
+
+    let mut info = Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    assert!(info.is_sdr("169.254.12.34:27015".parse().unwrap()));
+    assert!(info.is_sdr("[::ffff:169.254.12.34]:27015".parse().unwrap()));
+    assert!(!info.is_sdr("192.0.2.7:27015".parse().unwrap()));
+
+    info.gold_source_address = Some("169.254.1.2:27015".to_owned());
+    assert!(info.is_sdr("192.0.2.7:27015".parse().unwrap()));
+}
+
+#[test]
+fn test_info_flags() {
+    use a2s::info::{Info, ServerFlags, SourceTVInfo};
+    use std::io::Cursor;
+
+    let mut info = Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+    assert_eq!(info.flags(), ServerFlags::VAC);
+
+    info.visibility = true;
+    info.source_tvs = vec![SourceTVInfo {
+        port: 27020,
+        name: "SourceTV".to_owned(),
+    }];
+    assert_eq!(info.flags(), ServerFlags::all());
+    assert_eq!(
+        ServerFlags::from_bits(info.flags().bits()),
+        Some(info.flags())
+    );
+}
+
+#[test]
+fn test_info_fingerprint() {
+    use a2s::info::Info;
+    use std::io::Cursor;
+
+    let info = Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    // The same server seen later, on another map and with other players
+    let mut later = info.clone();
+    later.map = "de_inferno".to_owned();
+    later.players = 12;
+    assert_eq!(later.fingerprint(), info.fingerprint());
+
+    let mut other = info.clone();
+    other.extended_server_info.steam_id = Some(90071996842377216);
+    assert_ne!(other.fingerprint(), info.fingerprint());
+
+    // Bytes moved from the name to the game make another server
+    let (mut first, mut second) = (info.clone(), info.clone());
+    first.name = "ab".to_owned();
+    first.game = "c".to_owned();
+    second.name = "a".to_owned();
+    second.game = "bc".to_owned();
+    assert_ne!(first.fingerprint(), second.fingerprint());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_large_single_packet() {
+    let name = "x".repeat(2048);
+    let payload = common::single(&common::info_payload_named(&name));
+    let address = common::mock_server(move |_| vec![payload.clone()]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let info = client.info(address).unwrap();
+
+    assert_eq!(info.name, name);
+    assert_eq!(info.extended_server_info.port, Some(27015));
+}
+
+#[test]
+fn test_visibility_vac_raw() {
+    use std::io::Cursor;
+
+    let mut payload = common::info_payload();
+    let offset = payload.windows(2).position(|w| w == b"dl").unwrap() + 2;
+    payload[offset] = 2;
+    payload[offset + 1] = 5;
+
+    let mut info = a2s::info::Info::from_cursor(Cursor::new(payload.clone())).unwrap();
+
+    assert!(info.visibility);
+    assert_eq!(info.visibility_raw, 2);
+    assert!(info.vac);
+    assert_eq!(info.vac_raw, 5);
+    assert_eq!(info.to_bytes()[4..], payload[..]);
+
+    info.vac = false;
+    assert_eq!(info.to_bytes()[4 + offset + 1], 0);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_with_port_fallback() {
+    use std::time::Duration;
+
+    let closed = common::mock_server(|_| vec![]);
+    let open = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let config = a2s::A2SConfig {
+        timeout: Duration::from_millis(100),
+        ..Default::default()
+    };
+    let client = a2s::A2SClient::with_config(config).unwrap();
+
+    let (info, port) = client
+        .info_with_port_fallback("127.0.0.1", &[closed.port(), open.port()])
+        .unwrap();
+
+    assert_eq!(port, open.port());
+    assert_eq!(info.name, "Test Server");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_with_query_port_discovery() {
+    use a2s::QueryPort;
+    use std::time::Duration;
+
+    assert_eq!(QueryPort::Offset(1).resolve(27015), Some(27016));
+    assert_eq!(QueryPort::Offset(1).resolve(u16::MAX), None);
+    assert_eq!(QueryPort::Offset(-27015).resolve(27015), None);
+    assert_eq!(QueryPort::Fixed(27015).resolve(7777), Some(27015));
+
+    // The game port doesn't answer, the query port is found as a fixed one
+    let game = common::mock_server(|_| vec![]);
+    let query = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+
+    let config = a2s::A2SConfig {
+        timeout: Duration::from_millis(100),
+        query_ports: vec![QueryPort::Offset(0), QueryPort::Fixed(query.port())],
+        ..Default::default()
+    };
+    let client = a2s::A2SClient::with_config(config).unwrap();
+
+    let (info, port) = client.info_with_query_port_discovery(game).unwrap();
+    assert_eq!(port, query.port());
+    assert_eq!(info.name, "Test Server");
+}
+
+#[test]
+fn test_info_to_map() {
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+
+    let info = a2s::info::Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    let map = BTreeMap::from(&info);
+
+    assert_eq!(map["name"], "Test Server");
+    assert_eq!(map["app_id"], "730");
+    assert_eq!(map["server_type"], "Dedicated");
+    assert_eq!(map["port"], "27015");
+    assert!(!map.contains_key("steam_id"));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_truncated_multi_packet_header() {
+    let address = common::mock_server(|_| vec![vec![0xfe, 0xff, 0xff, 0xff]]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert!(client.info(address).unwrap_err().is_invalid_response());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_and_ping() {
+    use std::time::Duration;
+
+    // Slow to hand out challenges, quick to answer the info request itself
+    let address = common::mock_server(|request| {
+        if request.len() == 25 {
+            std::thread::sleep(Duration::from_millis(300));
+            vec![common::challenge(1)]
+        } else {
+            vec![common::single(&common::info_payload())]
+        }
+    });
+
+    let mut client = a2s::A2SClient::new().unwrap();
+
+    let (info, ping) = client.info_and_ping(address).unwrap();
+
+    assert_eq!(info.name, "Test Server");
+    assert!(ping < Duration::from_millis(300));
+
+    client.ping_includes_challenge(true);
+
+    let (_, ping) = client.info_and_ping(address).unwrap();
+
+    assert!(ping >= Duration::from_millis(300));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_ping_n() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    // Answers every other request
+    let requests = AtomicUsize::new(0);
+    let address = common::mock_server(move |_| {
+        if requests.fetch_add(1, Ordering::SeqCst).is_multiple_of(2) {
+            vec![common::single(&common::info_payload())]
+        } else {
+            Vec::new()
+        }
+    });
+
+    let client = a2s::A2SClient::with_config(a2s::A2SConfig {
+        timeout: Duration::from_millis(200),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let stats = client.ping_n(address, 4).unwrap();
+    assert_eq!((stats.received, stats.dropped), (2, 2));
+    assert_eq!(stats.loss(), 0.5);
+    assert!(stats.min <= stats.mean && stats.mean <= stats.max);
+    assert!(stats.max < Duration::from_millis(200));
+
+    // Nothing answered fails with the timeout
+    let silent = common::mock_server(|_| Vec::new());
+    assert!(matches!(
+        client.ping_n(silent, 2),
+        Err(a2s::errors::Error::ErrTimeout)
+    ));
+}
+
+#[test]
+fn test_ping_stats() {
+    use a2s::ping::PingStats;
+    use std::time::Duration;
+
+    let millis = |ms| Duration::from_millis(ms);
+    let stats = PingStats::from_samples(&[millis(10), millis(20), millis(30), millis(20)], 1);
+
+    assert_eq!(stats.received, 4);
+    assert_eq!(stats.dropped, 1);
+    assert_eq!(stats.min, millis(10));
+    assert_eq!(stats.max, millis(30));
+    assert_eq!(stats.mean, millis(20));
+    assert_eq!(stats.stddev.as_micros(), 7071);
+    assert_eq!(stats.loss(), 0.2);
+
+    assert_eq!(
+        PingStats::from_samples(&[], 3),
+        PingStats {
+            dropped: 3,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_info_to_json_minimal() {
+    use std::io::Cursor;
+
+    let payload = common::info_payload_named("\"Quoted\"\\\n\u{1}");
+    let info = a2s::info::Info::from_cursor(Cursor::new(payload)).unwrap();
+
+    assert_eq!(
+        info.to_json_minimal(),
+        concat!(
+            r#"{"name":"\"Quoted\"\\\n\u0001","map":"de_dust2","folder":"csgo","#,
+            r#""game":"Counter-Strike: Global Offensive","app_id":730,"players":5,"#,
+            r#""max_players":24,"bots":1,"server_type":"Dedicated","server_os":"Linux","#,
+            r#""visibility":false,"vac":true,"version":"1.38.0.0"}"#,
+        )
+    );
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_with_meta_challenge_required() {
+    let unpatched = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+    let patched = common::mock_server(|request| {
+        if request.len() == 25 {
+            vec![common::challenge(1)]
+        } else {
+            vec![common::single(&common::info_payload())]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let (_, meta) = client.info_with_meta(unpatched).unwrap();
+    assert!(!meta.challenge_required);
+
+    let (info, meta) = client.info_with_meta(patched).unwrap();
+    assert!(meta.challenge_required);
+    assert_eq!(info.name, "Test Server");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_and_ping_fake_clock() {
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    /// Advances by 25 milliseconds every time it's read.
+    struct FakeClock(Mutex<Instant>);
+
+    impl a2s::Clock for FakeClock {
+        fn now(&self) -> Instant {
+            let mut now = self.0.lock().unwrap();
+            *now += Duration::from_millis(25);
+            *now
+        }
+    }
+
+    let address = common::mock_server(|request| {
+        if request.len() == 25 {
+            vec![common::challenge(1)]
+        } else {
+            vec![common::single(&common::info_payload())]
+        }
+    });
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.clock(FakeClock(Mutex::new(Instant::now())));
+
+    let (_, ping) = client.info_and_ping(address).unwrap();
+    assert_eq!(ping, Duration::from_millis(25));
+
+    client.ping_includes_challenge(true);
+
+    let (_, ping) = client.info_and_ping(address).unwrap();
+    assert_eq!(ping, Duration::from_millis(50));
+}
+
+#[test]
+fn test_info_project() {
+    use a2s::info::{Info, InfoFields, PartialInfo};
+    use std::io::Cursor;
+
+    let info = Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    let partial = info.project(InfoFields::NAME | InfoFields::PLAYERS | InfoFields::SOURCE_TV);
+
+    assert_eq!(
+        partial,
+        PartialInfo {
+            name: Some("Test Server".to_owned()),
+            players: Some(5),
+            ..Default::default()
+        }
+    );
+    assert_eq!(info.project(InfoFields::all()).version.unwrap(), "1.38.0.0");
+}
+
+#[test]
+fn test_info_max_players_sentinel() {
+    use std::io::Cursor;
+
+    let mut info = a2s::info::Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    assert_eq!(info.max_players_display(), Some(24));
+    assert!(!info.is_full());
+
+    info.players = 24;
+    assert!(info.is_full());
+
+    info.max_players = 255;
+    info.players = 255;
+    assert_eq!(info.max_players_display(), None);
+    assert!(!info.is_full());
+}
+
+#[test]
+fn test_info_game_port() {
+    use std::io::Cursor;
+
+    let mut info = a2s::info::Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+    let queried = "127.0.0.1:27016".parse().unwrap();
+
+    assert_eq!(info.game_port(queried), 27015);
+
+    info.extended_server_info.port = None;
+    assert_eq!(info.game_port(queried), 27016);
+}
+
+#[test]
+fn test_info_keyword_map() {
+    use std::io::Cursor;
+
+    let mut info = a2s::info::Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    assert!(info.keyword_map().is_empty());
+
+    info.extended_server_info.keywords =
+        Some("secure, region:eu,mode:ctf:v2,,:orphan,region:us".to_owned());
+
+    let map = info.keyword_map();
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(map["secure"], "");
+    assert_eq!(map["region"], "eu");
+    assert_eq!(map["mode"], "ctf:v2");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_with_meta_packets() {
+    let payload = common::single(&common::info_payload());
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let single = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+    let split = common::mock_server(move |_| {
+        vec![
+            common::fragment(9, 2, 0, &first),
+            common::fragment(9, 2, 1, &second),
+        ]
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert_eq!(client.info_with_meta(single).unwrap().1.packets, 1);
+
+    let (info, meta) = client.info_with_meta(split).unwrap();
+    assert_eq!(meta.packets, 2);
+    assert_eq!(info.name, "Test Server");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_with_meta_bytes() {
+    let payload = common::single(&common::info_payload());
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let single = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+    let split = common::mock_server(move |request| {
+        if request.len() == 25 {
+            vec![common::challenge(1)]
+        } else {
+            vec![
+                common::fragment(9, 2, 0, &first),
+                common::fragment(9, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let (_, meta) = client.info_with_meta(single).unwrap();
+    assert_eq!(meta.request_bytes, 25);
+    assert_eq!(meta.response_bytes, payload.len());
+    assert_eq!(meta.amplification(), payload.len() as f64 / 25.0);
+
+    // Only the exchange answered with info counts, the challenge is part of the request
+    let (_, meta) = client.info_with_meta(split).unwrap();
+    assert_eq!(meta.request_bytes, 29);
+    assert_eq!(meta.response_bytes, payload.len() + 2 * 12);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_with_meta_payload() {
+    use a2s::{ParseContext, ParseResponse};
+
+    let payload = common::single(&common::info_payload());
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let split = common::mock_server(move |_| {
+        vec![
+            common::fragment(9, 2, 0, &first),
+            common::fragment(9, 2, 1, &second),
+        ]
+    });
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    assert_eq!(client.info_with_meta(split).unwrap().1.payload, None);
+
+    client.keep_payload(true);
+    let (info, meta) = client.info_with_meta(split).unwrap();
+    let kept = meta.payload.unwrap();
+    assert_eq!(kept, common::info_payload());
+    assert_eq!(
+        a2s::info::Info::parse(&kept, &ParseContext::default()).unwrap(),
+        info
+    );
+}
+
+#[test]
+fn test_info_matches_app() {
+    use std::io::Cursor;
+
+    let mut info = a2s::info::Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    assert!(info.matches_app(730));
+    assert!(!info.matches_app(440));
+
+    // An app ID too wide for the 16-bit field, only reported by the EDF game ID
+    info.app_id = 0x4240;
+    info.extended_server_info.game_id = Some(0x0100_0000_0001_4240);
+
+    assert!(info.matches_app(0x14240));
+    assert!(info.matches_app(0x4240));
+    assert!(!info.matches_app(730));
+}
+
+#[test]
+fn test_info_clean_name() {
+    use std::io::Cursor;
+
+    let payload = common::info_payload_named("\x04[EU]\x01 Surf\t24/7");
+    let info = a2s::info::Info::from_cursor(Cursor::new(payload)).unwrap();
+
+    assert_eq!(info.clean_name(), "[EU] Surf24/7");
+    assert_eq!(info.name, "\x04[EU]\x01 Surf\t24/7");
+}
+
+#[test]
+fn test_info_game_tags() {
+    use std::io::Cursor;
+
+    let mut info = a2s::info::Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    info.extended_server_info.keywords =
+        Some("alltalk,nocrits, KOTH,increased_maxplayers,payload,cp".to_owned());
+    let tf2 = info.tf2_tags();
+
+    assert!(tf2.alltalk && tf2.no_crits && tf2.increased_max_players);
+    assert!(!tf2.friendly_fire);
+    assert_eq!(tf2.game_mode.as_deref(), Some("koth"));
+    assert_eq!(tf2.other, ["payload", "cp"]);
+
+    info.extended_server_info.keywords = Some("secure,128-tick,surf,empty,,custom".to_owned());
+    let csgo = info.csgo_tags();
+
+    assert!(csgo.secure && csgo.empty && !csgo.valve_ds);
+    assert_eq!(csgo.tick_rate, Some(128));
+    assert_eq!(csgo.game_mode.as_deref(), Some("surf"));
+    assert_eq!(csgo.other, ["custom"]);
+
+    info.extended_server_info.keywords = None;
+    assert_eq!(info.csgo_tags(), Default::default());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_unexpected_header_after_challenge() {
+    use std::sync::{Arc, Mutex};
+
+    // Challenges the bare request, then answers the first challenged one with an echo of the
+    // probe, and with a fresh challenge if `rechallenge`
+    let server = |rechallenge: bool, requests: Arc<Mutex<Vec<Vec<u8>>>>| {
+        common::mock_server(move |request| {
+            let mut requests = requests.lock().unwrap();
+            requests.push(request.to_vec());
+            match requests.len() {
+                1 => vec![common::challenge(7)],
+                2 if rechallenge => vec![common::challenge(8)],
+                2 => vec![common::single(&request[4..])],
+                _ => vec![common::single(&common::info_payload())],
+            }
+        })
+    };
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let requests = Arc::default();
+    let info = client.info(server(false, Arc::clone(&requests))).unwrap();
+    assert_eq!(info.name, "Test Server");
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[2], requests[1]);
+
+    let requests = Arc::default();
+    let address = server(true, Arc::clone(&requests));
+    let (info, challenge) = client.info_with_challenge(address, None).unwrap();
+    assert_eq!(info.name, "Test Server");
+    assert_eq!(challenge, Some(8));
+    assert!(requests.lock().unwrap()[2].ends_with(&8i32.to_le_bytes()));
+
+    // Resent once only
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let counter = Arc::clone(&requests);
+    let address = common::mock_server(move |request| {
+        counter.lock().unwrap().push(request.to_vec());
+        if request.len() == 25 {
+            vec![common::challenge(7)]
+        } else {
+            vec![common::single(&[0x42, 0])]
+        }
+    });
+    assert!(client.info(address).unwrap_err().is_invalid_response());
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_with_challenge() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let address = common::mock_server(move |request| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        if request.ends_with(&7i32.to_le_bytes()) {
+            vec![common::single(&common::info_payload())]
+        } else {
+            vec![common::challenge(7)]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let (info, challenge) = client.info_with_challenge(address, Some(7)).unwrap();
+    assert_eq!(info.name, "Test Server");
+    assert_eq!(challenge, Some(7));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // A stale challenge falls back to the handshake
+    let (_, challenge) = client.info_with_challenge(address, Some(3)).unwrap();
+    assert_eq!(challenge, Some(7));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_info_inconsistent_counts() {
+    use a2s::errors::Error;
+    use a2s::info::Info;
+    use a2s::{ParseContext, ParseResponse};
+
+    // Players, player limit and bots, followed by the rest of the payload
+    let with_counts = |players, max_players, bots| {
+        let mut payload = common::info_payload();
+        let counts = payload.len() - 19;
+        payload[counts..counts + 3].copy_from_slice(&[players, max_players, bots]);
+        payload
+    };
+    let strict = ParseContext {
+        reject_inconsistent: true,
+        ..Default::default()
+    };
+
+    for consistent in [
+        with_counts(5, 24, 1),
+        with_counts(24, 24, 24),
+        with_counts(40, 255, 0),
+    ] {
+        let info = Info::parse(&consistent, &strict).unwrap();
+        assert!(info.is_consistent());
+    }
+
+    for inconsistent in [with_counts(25, 24, 0), with_counts(3, 24, 4)] {
+        let info = Info::parse(&inconsistent, &ParseContext::default()).unwrap();
+        assert!(!info.is_consistent());
+        assert!(matches!(
+            Info::parse(&inconsistent, &strict),
+            Err(Error::InvalidResponse)
+        ));
+    }
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_info_reject_inconsistent() {
+    let mut payload = common::info_payload();
+    let counts = payload.len() - 19;
+    payload[counts] = 200;
+    let address = common::mock_server(move |_| vec![common::single(&payload)]);
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    assert_eq!(client.info(address).unwrap().players, 200);
+
+    client.reject_inconsistent(true);
+    assert!(client.info(address).is_err());
+}
+
+#[test]
+fn test_info_the_ship_game_id() {
+    use a2s::info::{Info, TheShipDetection};
+    use a2s::{ParseContext, ParseResponse};
+
+    // The Ship's fields with another 16-bit app ID, followed by an EDF game ID
+    let with_game_id = |game_id: u64| {
+        let mut payload = common::ship_info_payload_with(7, true);
+        let app_id = payload.len() - 20;
+        payload[app_id..app_id + 2].copy_from_slice(&0u16.to_le_bytes());
+        payload.push(0x01);
+        payload.extend(game_id.to_le_bytes());
+        payload
+    };
+    let ctx = ParseContext::default();
+
+    let info = Info::parse(&with_game_id(2400 | 1 << 32), &ctx).unwrap();
+    assert_eq!(info.version, "1.0.0.4");
+    assert_eq!(info.true_app_id(), 2400);
+    assert_eq!(info.the_ship.unwrap().detection, TheShipDetection::GameId);
+
+    // Without The Ship's game ID the fields aren't recognized, and the rest is misread
+    let info = Info::parse(&with_game_id(730), &ctx).unwrap();
+    assert_eq!(info.the_ship, None);
+    assert_ne!(info.version, "1.0.0.4");
+
+    let no_ship = ParseContext {
+        parse_the_ship: false,
+        ..ctx
+    };
+    assert_eq!(
+        Info::parse(&with_game_id(2400), &no_ship).unwrap().the_ship,
+        None
+    );
+}
+
+#[test]
+fn test_info_lenient_edf() {
+    use a2s::info::{EdfOrder, Info};
+    use a2s::{ParseContext, ParseResponse};
+
+    // Port, then keywords before the Steam ID, then the game ID
+    let mut payload = common::info_payload();
+    let edf = payload.len() - 3;
+    payload[edf] |= 0x10 | 0x20 | 0x01;
+    payload.extend(b"secure,dm\0");
+    payload.extend(90071992547409920u64.to_le_bytes());
+    payload.extend(730u64.to_le_bytes());
+
+    let info = Info::parse(&payload, &ParseContext::default()).unwrap();
+    assert_eq!(info.edf_order, EdfOrder::Standard);
+    assert!(!info.parse_warnings.is_empty() || !info.extra.is_empty());
+
+    let lenient = ParseContext {
+        lenient_edf: true,
+        ..Default::default()
+    };
+    let info = Info::parse(&payload, &lenient).unwrap();
+    assert_eq!(info.edf_order, EdfOrder::KeywordsFirst);
+    assert_eq!(info.extended_server_info.port, Some(27015));
+    assert_eq!(
+        info.extended_server_info.keywords.as_deref(),
+        Some("secure,dm")
+    );
+    assert_eq!(info.extended_server_info.steam_id, Some(90071992547409920));
+    assert_eq!(info.extended_server_info.game_id, Some(730));
+    assert!(info.parse_warnings.is_empty());
+
+    // Well-formed responses keep the standard order
+    let info = Info::parse(&common::info_payload(), &lenient).unwrap();
+    assert_eq!(info.edf_order, EdfOrder::Standard);
+}
+
+#[test]
+fn test_info_second_source_tv() {
+    use a2s::info::{Info, SourceTVInfo};
+    use std::io::Cursor;
+
+    // A second SourceTV block right after the first, ahead of the keywords and game ID
+    let mut payload = common::info_payload();
+    let edf = payload.len() - 3;
+    payload[edf] |= 0x40 | 0x20 | 0x01;
+    payload.extend(27020u16.to_le_bytes());
+    payload.extend(b"SourceTV\0");
+    payload.extend(27021u16.to_le_bytes());
+    payload.extend(b"Relay\0");
+    payload.extend(b"secure\0");
+    payload.extend(730u64.to_le_bytes());
+
+    let info = Info::from_cursor(Cursor::new(payload.clone())).unwrap();
+    assert_eq!(
+        info.source_tvs,
+        vec![
+            SourceTVInfo {
+                port: 27020,
+                name: "SourceTV".to_owned(),
+            },
+            SourceTVInfo {
+                port: 27021,
+                name: "Relay".to_owned(),
+            },
+        ]
+    );
+    assert_eq!(info.source_tv().map(|tv| tv.port), Some(27020));
+    assert_eq!(
+        info.extended_server_info.keywords.as_deref(),
+        Some("secure")
+    );
+    assert_eq!(info.extended_server_info.game_id, Some(730));
+    assert!(info.parse_warnings.is_empty());
+    assert!(info.extra.is_empty());
+    assert_eq!(info.to_map()["source_tv.1.name"], "Relay");
+    assert_eq!(info.to_bytes(), common::single(&payload));
+
+    // A single relay is read as before
+    let mut single = common::info_payload();
+    single[edf] |= 0x40;
+    single.extend(27020u16.to_le_bytes());
+    single.extend(b"SourceTV\0");
+    let info = Info::from_cursor(Cursor::new(single)).unwrap();
+    assert_eq!(info.source_tvs.len(), 1);
+}
+
+#[test]
+fn test_info_truncated_source_tv() {
+    use std::io::Cursor;
+
+    // EDF announcing a port and SourceTV, with the SourceTV port cut short
+    let mut payload = common::info_payload();
+    let edf = payload.len() - 3;
+    payload[edf] |= 0x40;
+    payload.push(0x87);
+
+    let info = a2s::info::Info::from_cursor(Cursor::new(payload)).unwrap();
+
+    assert_eq!(info.name, "Test Server");
+    assert_eq!(info.extended_server_info.port, Some(27015));
+    assert_eq!(info.source_tv(), None);
+    assert_eq!(info.parse_warnings.len(), 1);
+    assert!(info.parse_warnings[0].contains("source_tv"));
+
+    let complete = a2s::info::Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+    assert!(complete.parse_warnings.is_empty());
+}
+
+#[test]
+fn test_info_the_ship_detection() {
+    use a2s::info::{Info, TheShipDetection, TheShipMode};
+    use a2s::{ParseContext, ParseResponse};
+
+    let ctx = ParseContext::default();
+
+    let info = Info::parse(&common::ship_info_payload(), &ctx).unwrap();
+    let the_ship = info.the_ship.unwrap();
+    assert_eq!(the_ship.mode, TheShipMode::Hunt);
+    assert_eq!(the_ship.duration, 30);
+    assert_eq!(the_ship.detection, TheShipDetection::AppIdAndProtocol);
+    assert_eq!(info.version, "1.0.0.4");
+
+    // Another protocol, but still The Ship's layout
+    let info = Info::parse(&common::ship_info_payload_with(17, true), &ctx).unwrap();
+    assert_eq!(info.the_ship.unwrap().detection, TheShipDetection::Layout);
+    assert_eq!(info.version, "1.0.0.4");
+
+    // Another protocol without the fields, the version follows `vac`
+    let info = Info::parse(&common::ship_info_payload_with(17, false), &ctx).unwrap();
+    assert_eq!(info.the_ship, None);
+    assert_eq!(info.version, "1.0.0.4");
+    assert!(info.parse_warnings.is_empty());
+
+    // An empty version starts with 0 like a game mode, but only parses without the fields
+    let mut payload = common::ship_info_payload_with(17, false);
+    payload.truncate(payload.len() - b"1.0.0.4\0".len());
+    payload.extend(&[0, 0x80]);
+    payload.extend(27015u16.to_le_bytes());
+    let info = Info::parse(&payload, &ctx).unwrap();
+    assert_eq!(info.the_ship, None);
+    assert_eq!(info.version, "");
+    assert_eq!(info.extended_server_info.port, Some(27015));
+    assert!(info.parse_warnings.is_empty());
+}
+
+#[test]
+fn test_extended_info_parse() {
+    use a2s::info::{ExtendedServerInfo, Info};
+    use a2s::{ParseContext, ParseResponse};
+
+    let ctx = ParseContext::default();
+
+    let mut full = common::info_payload();
+    *full.iter_mut().rev().nth(2).unwrap() = 0x80 | 0x10 | 0x20 | 0x01;
+    full.extend(76561198000000000u64.to_le_bytes());
+    full.extend(b"secure,casual\0");
+    full.extend(730u64.to_le_bytes());
+
+    let mut ship = common::ship_info_payload();
+    ship.push(0x80);
+    ship.extend(27016u16.to_le_bytes());
+
+    for payload in [common::info_payload(), full, ship] {
+        let extended = ExtendedServerInfo::parse(&payload, &ctx).unwrap();
+        let info = Info::parse(&payload, &ctx).unwrap();
+        assert_eq!(extended, info.extended_server_info);
+    }
+
+    let extended = ExtendedServerInfo::parse(&common::gold_source_info_payload(), &ctx).unwrap();
+    assert_eq!(extended.port, None);
+    assert!(ExtendedServerInfo::parse(&[0x44, 0], &ctx).is_err());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_extended_info() {
+    let server = common::mock_server(|request| {
+        if common::is_probe(request) || request.len() == 25 {
+            vec![common::challenge(7)]
+        } else {
+            vec![common::single(&common::info_payload())]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+    let extended = client.extended_info(server).unwrap();
+
+    assert_eq!(extended.port, Some(27015));
+    assert_eq!(extended.steam_id, None);
+}
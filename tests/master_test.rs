@@ -0,0 +1,44 @@
+use a2s::master::MasterFilter;
+
+#[test]
+fn test_master_filter() {
+    let mut filter = MasterFilter::new();
+    filter
+        .app_id(440)
+        .game_type(&["payload", "alltalk"])
+        .dedicated()
+        .password(false)
+        .not_empty();
+
+    assert_eq!(
+        filter.to_string(),
+        r"\appid\440\gametype\payload,alltalk\dedicated\1\password\0\empty\1"
+    );
+    assert_eq!(
+        filter.to_bytes(),
+        b"\\appid\\440\\gametype\\payload,alltalk\\dedicated\\1\\password\\0\\empty\\1\0"
+    );
+    assert_eq!(MasterFilter::new().to_bytes(), b"\0");
+}
+
+#[test]
+fn test_master_filter_nested() {
+    let mut excluded = MasterFilter::new();
+    excluded.map("de_dust2").not_app_id(730);
+
+    let mut filter = MasterFilter::new();
+    filter.game_dir("csgo").nor(excluded).secure();
+
+    assert_eq!(
+        filter.to_string(),
+        r"\gamedir\csgo\nor\2\map\de_dust2\napp\730\secure\1"
+    );
+}
+
+#[test]
+fn test_master_filter_strips_delimiters() {
+    let mut filter = MasterFilter::new();
+    filter.name_match("*evil\\appid\\1\0*");
+
+    assert_eq!(filter.to_string(), r"\name_match\*evilappid1*");
+}
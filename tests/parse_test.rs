@@ -0,0 +1,166 @@
+mod common;
+
+use a2s::{parse_response, ParsedResponse, QueryKind};
+
+const KINDS: [QueryKind; 3] = [QueryKind::Info, QueryKind::Players, QueryKind::Rules];
+
+#[test]
+fn test_parse_response() {
+    let parsed = parse_response(&common::single(&common::info_payload()), QueryKind::Info, 0);
+
+    match parsed.unwrap() {
+        ParsedResponse::Info(info) => assert_eq!(info.name, "Test Server"),
+        parsed => panic!("unexpected response {:?}", parsed),
+    }
+
+    let players = common::players_payload(&["alice"], true);
+    let parsed = parse_response(&common::single(&players), QueryKind::Players, 2400).unwrap();
+
+    assert!(matches!(parsed, ParsedResponse::Players(players) if players[0].the_ship.is_some()));
+}
+
+#[test]
+fn test_parse_response_fuzz_seeds() {
+    // Known-bad datagrams that used to panic, or that stress a parser's counts
+    let seeds = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/seeds/parse_response");
+
+    for seed in std::fs::read_dir(seeds).unwrap() {
+        let data = std::fs::read(seed.unwrap().path()).unwrap();
+
+        for kind in KINDS {
+            let _ = parse_response(&data[1..], kind, 2400);
+        }
+    }
+}
+
+#[test]
+fn test_parse_response_truncated() {
+    let rules = common::rules_payload(&[("sv_gravity", "800"), ("mp_timelimit", "30")]);
+    let datagrams = [
+        common::single(&common::info_payload()),
+        common::fragment(1, 1, 0, &common::single(&rules)),
+        common::fragment(i32::MIN | 1, 1, 0, &common::single(&rules)),
+    ];
+
+    // Every prefix must fail cleanly rather than panic
+    for datagram in &datagrams {
+        for len in 0..datagram.len() {
+            for kind in KINDS {
+                let _ = parse_response(&datagram[..len], kind, 2400);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_double_wrapped() {
+    use a2s::info::Info;
+    use a2s::players::Player;
+    use a2s::rules::Rule;
+    use std::io::Cursor;
+
+    // Relays that wrap the unwrapped payload again in a single packet header
+    let info = common::single(&common::info_payload());
+    let players = common::single(&common::players_payload(&["alice"], false));
+    let rules = common::single(&common::rules_payload(&[("sv_gravity", "800")]));
+
+    assert_eq!(
+        Info::from_cursor(Cursor::new(info)).unwrap().name,
+        "Test Server"
+    );
+    assert_eq!(
+        Player::from_cursor(Cursor::new(players), 0).unwrap()[0].name,
+        "alice"
+    );
+    assert_eq!(
+        Rule::from_cursor(Cursor::new(rules.clone())).unwrap()[0].value,
+        "800"
+    );
+
+    let parsed = parse_response(&common::single(&rules), QueryKind::Rules, 0).unwrap();
+    assert!(matches!(parsed, ParsedResponse::Rules(rules) if rules[0].name == "sv_gravity"));
+}
+
+#[test]
+fn test_parse_response_trait() {
+    use a2s::info::Info;
+    use a2s::players::Player;
+    use a2s::rules::Rule;
+    use a2s::{ParseContext, ParseResponse};
+
+    fn parse<T: ParseResponse>(payload: &[u8], ctx: &ParseContext) -> T {
+        T::parse(payload, ctx).unwrap()
+    }
+
+    let ship = ParseContext {
+        app_id: 2400,
+        ..Default::default()
+    };
+    let no_ship = ParseContext {
+        parse_the_ship: false,
+        ..ship
+    };
+    let players = common::players_payload(&["alice"], true);
+
+    let info: Info = parse(&common::info_payload(), &ship);
+    let rules: Vec<Rule> = parse(&common::rules_payload(&[("sv_gravity", "800")]), &ship);
+    let ship_players: Vec<Player> = parse(&players, &ship);
+    let plain_players: Vec<Player> = parse(&players, &no_ship);
+
+    assert_eq!(info.name, "Test Server");
+    assert_eq!(rules[0].value, "800");
+    assert_eq!(ship_players[0].the_ship.as_ref().unwrap().money, 500);
+    assert!(plain_players[0].the_ship.is_none());
+}
+
+#[test]
+fn test_parse_failure_dump() {
+    use a2s::errors::Error;
+    use a2s::info::Info;
+    use a2s::{ParseContext, ParseResponse};
+
+    let payload = common::info_payload_named(&"x".repeat(100));
+    let truncated = &payload[..80];
+
+    // Only attached on request
+    assert!(matches!(
+        Info::parse(truncated, &ParseContext::default()),
+        Err(Error::UnexpectedEof)
+    ));
+
+    let ctx = ParseContext {
+        dump_invalid_responses: true,
+        ..Default::default()
+    };
+    match Info::parse(truncated, &ctx) {
+        Err(Error::InvalidResponseWithDump(dump)) => assert_eq!(dump, &truncated[..64]),
+        parsed => panic!("unexpected result {:?}", parsed),
+    }
+}
+
+#[test]
+fn test_bytes_reader() {
+    use a2s::info::Info;
+    use a2s::reader::BytesReader;
+
+    let mut reader = BytesReader::new(b"\x01\x02\x03abc\0def");
+    assert_eq!(reader.read_u8().unwrap(), 1);
+    assert_eq!(reader.read_u16_le().unwrap(), 0x0302);
+    assert_eq!(reader.read_cstring().unwrap(), "abc");
+    // A missing terminator at the end is tolerated
+    assert_eq!(reader.read_cstring().unwrap(), "def");
+    assert!(reader.is_empty());
+    assert!(matches!(
+        reader.read_u8(),
+        Err(a2s::errors::Error::UnexpectedEof)
+    ));
+
+    // Parsing borrows the payload, leaving the reader past it
+    let mut payload = common::info_payload();
+    payload.extend(b"tail");
+    let mut reader = BytesReader::new(&payload);
+    let info = Info::from_reader(&mut reader).unwrap();
+    assert_eq!(info.name, "Test Server");
+    assert_eq!(info.extra, b"tail");
+    assert!(reader.is_empty());
+}
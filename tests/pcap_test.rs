@@ -0,0 +1,175 @@
+#[cfg(feature = "pcap")]
+mod common;
+
+#[cfg(feature = "pcap")]
+mod capture {
+    use std::net::SocketAddr;
+
+    /// A pcap capture of `frames` with microsecond timestamps, one second apart.
+    pub fn pcap(link_type: u32, frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0];
+        bytes.extend([0; 8]);
+        bytes.extend(65535u32.to_le_bytes());
+        bytes.extend(link_type.to_le_bytes());
+
+        for (second, frame) in frames.iter().enumerate() {
+            bytes.extend((second as u32).to_le_bytes());
+            bytes.extend(0u32.to_le_bytes());
+            bytes.extend((frame.len() as u32).to_le_bytes());
+            bytes.extend((frame.len() as u32).to_le_bytes());
+            bytes.extend(frame);
+        }
+
+        bytes
+    }
+
+    fn udp(source: SocketAddr, destination: SocketAddr, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(source.port().to_be_bytes());
+        bytes.extend(destination.port().to_be_bytes());
+        bytes.extend((8 + payload.len() as u16).to_be_bytes());
+        bytes.extend([0, 0]);
+        bytes.extend(payload);
+        bytes
+    }
+
+    /// An Ethernet frame of an IPv4 UDP datagram.
+    pub fn ethernet(source: &str, destination: &str, payload: &[u8]) -> Vec<u8> {
+        let (source, destination): (SocketAddr, SocketAddr) =
+            (source.parse().unwrap(), destination.parse().unwrap());
+        let (SocketAddr::V4(source_v4), SocketAddr::V4(destination_v4)) = (source, destination)
+        else {
+            panic!("IPv4 addresses expected");
+        };
+        let segment = udp(source, destination, payload);
+
+        let mut bytes = vec![0; 12];
+        bytes.extend([0x08, 0x00]);
+        bytes.extend([0x45, 0]);
+        bytes.extend((20 + segment.len() as u16).to_be_bytes());
+        bytes.extend([0, 0, 0x40, 0, 64, 17, 0, 0]);
+        bytes.extend(source_v4.ip().octets());
+        bytes.extend(destination_v4.ip().octets());
+        bytes.extend(segment);
+        bytes
+    }
+
+    /// A raw IPv6 UDP datagram.
+    pub fn ipv6(source: &str, destination: &str, payload: &[u8]) -> Vec<u8> {
+        let (source, destination): (SocketAddr, SocketAddr) =
+            (source.parse().unwrap(), destination.parse().unwrap());
+        let (SocketAddr::V6(source_v6), SocketAddr::V6(destination_v6)) = (source, destination)
+        else {
+            panic!("IPv6 addresses expected");
+        };
+        let segment = udp(source, destination, payload);
+
+        let mut bytes = vec![0x60, 0, 0, 0];
+        bytes.extend((segment.len() as u16).to_be_bytes());
+        bytes.extend([17, 64]);
+        bytes.extend(source_v6.ip().octets());
+        bytes.extend(destination_v6.ip().octets());
+        bytes.extend(segment);
+        bytes
+    }
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_responses() {
+    use a2s::errors::Error;
+    use a2s::pcap::read_responses;
+    use a2s::ParsedResponse;
+    use capture::ethernet;
+
+    const SERVER: &str = "10.0.0.1:27015";
+    const CLIENT: &str = "10.0.0.2:50000";
+
+    let mut rules = vec![0xff, 0xff, 0xff, 0xff];
+    rules.extend(common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = rules.split_at(rules.len() / 2);
+
+    let frames = [
+        ethernet(CLIENT, SERVER, &[0xff, 0xff, 0xff, 0xff, 0x54]),
+        ethernet(SERVER, CLIENT, &common::single(&common::info_payload())),
+        ethernet(SERVER, CLIENT, &common::challenge(42)),
+        // Game traffic shares the port, without an A2S header
+        ethernet(SERVER, CLIENT, &[0x12, 0x34, 0x56, 0x78, 0x9a]),
+        ethernet(SERVER, CLIENT, &common::fragment(7, 2, 0, first)),
+        ethernet(SERVER, CLIENT, &common::fragment(7, 2, 1, second)),
+        ethernet(SERVER, CLIENT, &common::fragment(9, 2, 0, first)),
+    ];
+
+    let responses = read_responses(capture::pcap(1, &frames).as_slice()).unwrap();
+    assert_eq!(responses.len(), 3);
+
+    assert_eq!(responses[0].server, SERVER.parse().unwrap());
+    assert_eq!(responses[0].client, CLIENT.parse().unwrap());
+    assert_eq!(responses[0].timestamp.as_secs(), 1);
+    match &responses[0].response {
+        Ok(ParsedResponse::Info(info)) => assert_eq!(info.map, "de_dust2"),
+        other => panic!("expected info, got {:?}", other),
+    }
+
+    assert_eq!(responses[1].timestamp.as_secs(), 5);
+    match &responses[1].response {
+        Ok(ParsedResponse::Rules(rules)) => assert_eq!(rules[1].value, "800"),
+        other => panic!("expected rules, got {:?}", other),
+    }
+
+    // Capture ended before the last response's second fragment
+    assert!(matches!(
+        responses[2].response,
+        Err(Error::IncompleteResponse {
+            received: 1,
+            expected: 2
+        })
+    ));
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_raw_ipv6() {
+    use a2s::pcap::{read_datagrams, responses, QUERY_PORTS};
+    use a2s::ParsedResponse;
+
+    let frames = [capture::ipv6(
+        "[2001:db8::1]:27016",
+        "[2001:db8::2]:50000",
+        &common::single(&common::info_payload()),
+    )];
+
+    let datagrams = read_datagrams(capture::pcap(101, &frames).as_slice()).unwrap();
+    assert_eq!(datagrams.len(), 1);
+    assert_eq!(datagrams[0].source, "[2001:db8::1]:27016".parse().unwrap());
+
+    let parsed = responses(&datagrams, QUERY_PORTS);
+    assert!(matches!(parsed[0].response, Ok(ParsedResponse::Info(_))));
+
+    // Outside the ports given, nothing is a response
+    assert!(responses(&datagrams, 27015..=27015).is_empty());
+}
+
+#[cfg(feature = "pcap")]
+#[test]
+fn test_pcap_invalid_capture() {
+    use a2s::pcap::read_datagrams;
+    use std::io::ErrorKind;
+
+    // Starting like a pcapng section header block
+    let mut pcapng = vec![0x0a, 0x0d, 0x0d, 0x0a];
+    pcapng.resize(24, 0);
+    let err = read_datagrams(pcapng.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let mut truncated = capture::pcap(
+        1,
+        &[capture::ethernet("10.0.0.1:27015", "10.0.0.2:1", &[0])],
+    );
+    truncated.pop();
+    let err = read_datagrams(truncated.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
@@ -1,3 +1,5 @@
+mod common;
+
 #[cfg(not(feature = "async"))]
 #[test]
 fn test_players() {
@@ -7,3 +9,178 @@ fn test_players() {
 
     println!("{:?}", result);
 }
+
+#[test]
+fn test_players_extra_bytes() {
+    use std::io::Cursor;
+
+    let mut payload = vec![0x44, 2];
+    for name in &["alice", "bob"] {
+        payload.push(0);
+        payload.extend(name.as_bytes());
+        payload.push(0);
+        payload.extend(10i32.to_le_bytes());
+        payload.extend(60f32.to_le_bytes());
+        payload.extend(&[1, 2, 3]);
+    }
+
+    let players = a2s::players::Player::from_cursor(Cursor::new(payload), 0).unwrap();
+
+    assert_eq!(players.len(), 2);
+    assert_eq!(players[1].name, "bob");
+    assert_eq!(players[1].extra, vec![1, 2, 3]);
+
+    let standard = common::players_payload(&["alice", "bob"], false);
+    let players = a2s::players::Player::from_cursor(Cursor::new(standard), 0).unwrap();
+
+    assert!(players[1].extra.is_empty());
+}
+
+#[test]
+fn test_players_truncated_record() {
+    use std::io::Cursor;
+
+    let mut payload = common::players_payload(&["alice", "bob"], false);
+    payload.truncate(payload.len() - 2);
+
+    let players = a2s::players::Player::from_cursor(Cursor::new(payload), 0).unwrap();
+
+    assert_eq!(players.len(), 1);
+    assert_eq!(players[0].name, "alice");
+}
+
+#[test]
+fn test_players_gotv_record() {
+    use std::io::Cursor;
+
+    // The second record's duration is NaN, as when a GOTV spectator shifts the fields
+    let mut payload = common::players_payload(&["alice", "GOTV"], false);
+    let len = payload.len();
+    payload[len - 4..].copy_from_slice(&f32::NAN.to_le_bytes());
+
+    let players = a2s::players::Player::from_cursor(Cursor::new(payload), 0).unwrap();
+
+    assert_eq!(players.len(), 1);
+    assert_eq!(players[0].name, "alice");
+
+    let long_name = "x".repeat(200);
+    let payload = common::players_payload(&["alice", &long_name], false);
+    let players = a2s::players::Player::from_cursor(Cursor::new(payload), 0).unwrap();
+
+    assert_eq!(players.len(), 1);
+}
+
+#[test]
+fn test_players_clean_name() {
+    use std::io::Cursor;
+
+    let payload = common::players_payload(&["\x07FF0000Red\x01 ^1Player\n"], false);
+    let players = a2s::players::Player::from_cursor(Cursor::new(payload), 0).unwrap();
+
+    assert_eq!(players[0].clean_name(), "FF0000Red ^1Player");
+    assert_eq!(a2s::clean_name(&players[0].name, true), "FF0000Red Player");
+    assert_eq!(a2s::clean_name("x^y ^", true), "x^y ^");
+}
+
+#[test]
+fn test_reconcile_players() {
+    use a2s::info::Info;
+    use a2s::players::{reconcile_players, Player};
+    use std::io::Cursor;
+
+    // The info reports 5 players, 1 of them a bot
+    let mut info = Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+    let payload = common::players_payload(&["alice", "bob", "", "bot"], false);
+    let players = Player::from_cursor(Cursor::new(payload), 0).unwrap();
+
+    let breakdown = reconcile_players(&info, &players);
+    assert_eq!(breakdown.humans, 4);
+    assert_eq!(breakdown.bots, 1);
+    assert_eq!(breakdown.unnamed, 1);
+    assert!(!breakdown.bots_excluded);
+    assert!(!breakdown.list_longer_than_info);
+
+    // More bots than players, so the player count leaves them out
+    info.players = 2;
+    info.bots = 3;
+    let breakdown = reconcile_players(&info, &players);
+    assert_eq!(breakdown.humans, 2);
+    assert!(breakdown.bots_excluded);
+    assert!(!breakdown.list_longer_than_info);
+
+    info.players = 3;
+    info.bots = 0;
+    assert!(reconcile_players(&info, &players).list_longer_than_info);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_players_gold_source_multipacket() {
+    // GoldSource servers frame players like Source ones, only their fragments differ. Synthesized
+    // fragments, no capture of a GoldSource server was available to check against.
+    let payload = common::single(&common::players_payload(&["alice", "bob", "carol"], false));
+    let (first, second) = payload.split_at(20);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::gold_source_fragment(3, 2, 0, &first),
+                common::gold_source_fragment(3, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let players = client.players(address).unwrap();
+
+    assert_eq!(players.len(), 3);
+    assert_eq!(players[2].name, "carol");
+}
+
+#[test]
+fn test_sort_players() {
+    use a2s::players::{sort_players, Player};
+
+    let player = |name: &str, score: i32, duration: f32| Player {
+        index: 0,
+        name: name.to_owned(),
+        score,
+        duration,
+        the_ship: None,
+        extra: Vec::new(),
+    };
+
+    let raw = vec![
+        player("zed", 1, 10.0),
+        player("alice", 3, 5.0),
+        player("bob", 2, 60.0),
+        player("alice", 3, 90.0),
+        player("alice", 7, 1.0),
+    ];
+    let mut sorted = raw.clone();
+    sort_players(&mut sorted);
+
+    let order: Vec<_> = sorted
+        .iter()
+        .map(|p| (p.name.as_str(), p.score, p.duration))
+        .collect();
+    assert_eq!(
+        order,
+        [
+            ("alice", 7, 1.0),
+            ("alice", 3, 90.0),
+            ("alice", 3, 5.0),
+            ("bob", 2, 60.0),
+            ("zed", 1, 10.0),
+        ]
+    );
+
+    // Any order the server lists the same players in sorts the same
+    let mut reversed: Vec<_> = raw.into_iter().rev().collect();
+    sort_players(&mut reversed);
+    assert_eq!(reversed, sorted);
+}
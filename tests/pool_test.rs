@@ -0,0 +1,106 @@
+mod common;
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_connected_client_pool() {
+    use std::time::Duration;
+
+    let first = common::mock_server(|_| vec![common::single(&common::info_payload_named("first"))]);
+    let second =
+        common::mock_server(|_| vec![common::single(&common::info_payload_named("second"))]);
+
+    let pool = a2s::pool::ConnectedClientPool::new(Default::default(), Duration::from_millis(200));
+
+    assert_eq!(pool.info(first).unwrap().name, "first");
+    assert_eq!(pool.info(second).unwrap().name, "second");
+    assert_eq!(pool.info(first).unwrap().name, "first");
+    assert_eq!(pool.len(), 2);
+
+    std::thread::sleep(Duration::from_millis(300));
+
+    assert_eq!(pool.info(second).unwrap().name, "second");
+    assert_eq!(pool.len(), 1);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_pool_canonical_addr() {
+    use std::net::{IpAddr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+    let pool = a2s::pool::ConnectedClientPool::new(Default::default(), Duration::from_secs(10));
+
+    let client = pool.client(address).unwrap();
+
+    // The same server written as an IPv4-mapped IPv6 address or by name shares the client
+    let mapped = match address.ip() {
+        IpAddr::V4(ip) => SocketAddr::new(ip.to_ipv6_mapped().into(), address.port()),
+        IpAddr::V6(_) => unreachable!(),
+    };
+    assert_eq!(a2s::canonical_addr(mapped), address);
+    assert!(Arc::ptr_eq(&pool.client(mapped).unwrap(), &client));
+
+    let named = a2s::resolve_addr(format!("127.0.0.1:{}", address.port())).unwrap();
+    assert!(Arc::ptr_eq(&pool.client(named).unwrap(), &client));
+    assert_eq!(pool.len(), 1);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_query_all_many() {
+    use std::time::Duration;
+
+    let servers: Vec<_> = ["first", "second", "third"]
+        .iter()
+        .map(|name| {
+            common::mock_server(common::full_server(
+                common::info_payload_named(name),
+                common::players_payload(&["alice"], false),
+                common::rules_payload(&[("sv_gravity", "800")]),
+            ))
+        })
+        .collect();
+
+    let pool = a2s::pool::ConnectedClientPool::new(Default::default(), Duration::from_secs(10));
+
+    let results = pool.query_all_many(servers.clone(), 2);
+
+    assert_eq!(results.len(), 3);
+    for ((addr, state), (expected, name)) in results
+        .into_iter()
+        .zip(servers.iter().zip(["first", "second", "third"]))
+    {
+        assert_eq!(addr, *expected);
+        assert_eq!(state.unwrap().info.name, name);
+    }
+    assert_eq!(pool.len(), 3);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_query_all_many() {
+    use std::time::Duration;
+
+    let servers: Vec<_> = ["first", "second", "third"]
+        .iter()
+        .map(|name| {
+            common::mock_server(common::full_server(
+                common::info_payload_named(name),
+                common::players_payload(&["alice"], false),
+                common::rules_payload(&[("sv_gravity", "800")]),
+            ))
+        })
+        .collect();
+
+    let pool = a2s::pool::ConnectedClientPool::new(Default::default(), Duration::from_secs(10));
+
+    let results = pool.query_all_many(servers.clone(), 2).await;
+
+    let names: Vec<_> = results
+        .into_iter()
+        .map(|(_, state)| state.unwrap().info.name)
+        .collect();
+    assert_eq!(names, ["first", "second", "third"]);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_custom_query() {
+    use a2s::query::Query;
+    use a2s::ParseContext;
+
+    /// An undocumented query answered with a single string, requiring a challenge.
+    struct Motd;
+
+    impl Query for Motd {
+        type Output = String;
+
+        fn request_bytes(&self) -> Vec<u8> {
+            b"\xff\xff\xff\xffmotd\0".to_vec()
+        }
+
+        fn needs_challenge(&self) -> bool {
+            true
+        }
+
+        fn parse(&self, bytes: &[u8], _ctx: &ParseContext) -> a2s::errors::Result<String> {
+            Ok(String::from_utf8_lossy(&bytes[1..]).into_owned())
+        }
+    }
+
+    let address = common::mock_server(|request| {
+        if !request.starts_with(b"\xff\xff\xff\xffmotd\0") {
+            vec![]
+        } else if request.ends_with(&5i32.to_le_bytes()) {
+            vec![common::single(b"mWelcome")]
+        } else {
+            vec![common::challenge(5)]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert_eq!(client.query(Motd, address).unwrap(), "Welcome");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_builtin_queries() {
+    use a2s::query::{InfoQuery, PlayersQuery, RulesQuery};
+
+    let address = common::mock_server(common::full_server(
+        common::info_payload(),
+        common::players_payload(&["alice"], false),
+        common::rules_payload(&[("sv_gravity", "800")]),
+    ));
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert_eq!(
+        client.query(InfoQuery, address).unwrap().name,
+        "Test Server"
+    );
+    assert_eq!(
+        client.query(PlayersQuery, address).unwrap()[0].name,
+        "alice"
+    );
+    assert_eq!(client.query(RulesQuery, address).unwrap()[0].value, "800");
+}
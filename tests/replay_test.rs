@@ -0,0 +1,54 @@
+#[cfg(feature = "replay")]
+mod common;
+
+#[cfg(feature = "replay")]
+#[test]
+fn test_replay_capture() {
+    use a2s::query::RulesQuery;
+    use a2s::replay::{write_record, ReplaySocket};
+    use a2s::transport::query_transport;
+    use a2s::A2SConfig;
+    use futures::executor::block_on;
+
+    let mut payload = vec![0xff, 0xff, 0xff, 0xff];
+    payload.extend(common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = payload.split_at(payload.len() / 2);
+
+    let path = std::env::temp_dir().join(format!("a2s-replay-{}.bin", std::process::id()));
+    let mut capture = std::fs::File::create(&path).unwrap();
+    write_record(&mut capture, &common::challenge(42)).unwrap();
+    write_record(&mut capture, &common::fragment(7, 2, 0, first)).unwrap();
+    write_record(&mut capture, &common::fragment(7, 2, 1, second)).unwrap();
+    drop(capture);
+
+    let replay = ReplaySocket::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(replay.remaining(), 3);
+
+    let rules = block_on(query_transport(&replay, &RulesQuery, &A2SConfig::default())).unwrap();
+    assert_eq!(rules[0].name, "mp_timelimit");
+    assert_eq!(rules[0].value, "30");
+    assert_eq!(rules[1].name, "sv_gravity");
+    assert_eq!(rules[1].value, "800");
+    assert_eq!(replay.remaining(), 0);
+
+    // Exhausted captures behave like a server that went quiet
+    let err = block_on(query_transport(&replay, &RulesQuery, &A2SConfig::default())).unwrap_err();
+    assert!(matches!(err, a2s::errors::Error::Io(_)));
+}
+
+#[cfg(feature = "replay")]
+#[test]
+fn test_replay_truncated_capture() {
+    use a2s::replay::{write_record, ReplaySocket};
+
+    let mut capture = Vec::new();
+    write_record(&mut capture, &common::challenge(42)).unwrap();
+    capture.pop();
+
+    let err = ReplaySocket::from_reader(capture.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
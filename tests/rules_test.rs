@@ -1,3 +1,5 @@
+mod common;
+
 #[cfg(not(feature = "async"))]
 #[test]
 fn test_rules() {
@@ -27,3 +29,439 @@ fn test_rules_multipacket2() {
 
     println!("{:?}", result);
 }
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_multipacket_reassembly() {
+    let payload = common::single(&common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::fragment(7, 2, 0, &first),
+                common::fragment(7, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let result = client.rules(address).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].name, "sv_gravity");
+    assert_eq!(result[1].value, "800");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_gold_source_multipacket() {
+    let payload = common::single(&common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::gold_source_fragment(7, 2, 0, &first),
+                common::gold_source_fragment(7, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let result = client.rules(address).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].name, "sv_gravity");
+    assert_eq!(result[1].value, "800");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_multipacket_without_header() {
+    // The first fragment doesn't repeat the single packet header, its payload starts right away
+    let payload = common::rules_payload(&[("mp_timelimit", "30"), ("sv_gravity", "800")]);
+    let (first, second) = payload.split_at(12);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::fragment(7, 2, 0, &first),
+                common::fragment(7, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let result = client.rules(address).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].name, "mp_timelimit");
+    assert_eq!(result[1].value, "800");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_multipacket_empty_datagram() {
+    let payload = common::single(&common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::fragment(7, 2, 0, &first),
+                Vec::new(),
+                common::fragment(7, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let result = client.rules(address).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].value, "800");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_multipacket_duplicate_fragment() {
+    let payload = common::single(&common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::fragment(7, 2, 0, &first),
+                common::fragment(7, 2, 0, &first),
+                common::fragment(7, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let result = client.rules(address).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].name, "sv_gravity");
+    assert_eq!(result[1].value, "800");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_multipacket_duplicates_out_of_order() {
+    let payload = common::single(&common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, rest) = payload.split_at(10);
+    let (second, third) = rest.split_at(10);
+    let (first, second, third) = (first.to_vec(), second.to_vec(), third.to_vec());
+
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::fragment(7, 3, 2, &third),
+                common::fragment(7, 3, 2, &third),
+                common::fragment(7, 3, 0, &first),
+                common::fragment(7, 3, 0, &first),
+                common::fragment(7, 3, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let result = client.rules(address).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0].value, "30");
+    assert_eq!(result[1].value, "800");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_multipacket_duplicate_with_gap() {
+    let payload = common::single(&common::rules_payload(&[("mp_timelimit", "30")]));
+    let (first, rest) = payload.split_at(8);
+    let (_, third) = rest.split_at(4);
+    let (first, third) = (first.to_vec(), third.to_vec());
+
+    // As many datagrams as fragments, but fragment 1 never arrives
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::fragment(7, 3, 0, &first),
+                common::fragment(7, 3, 2, &third),
+                common::fragment(7, 3, 2, &third),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::with_config(a2s::A2SConfig {
+        timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    })
+    .unwrap();
+
+    assert!(matches!(
+        client.rules(address),
+        Err(a2s::errors::Error::IncompleteResponse {
+            received: 2,
+            expected: 3
+        })
+    ));
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_multipacket_out_of_range_fragment() {
+    let payload = common::single(&common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::fragment(7, 3, 0, &first),
+                common::fragment(7, 3, 200, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert!(client.rules(address).unwrap_err().is_invalid_response());
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_with_buffer() {
+    let payload = common::single(&common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = payload.split_at(16);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    // Fragments announcing a switching size above the default max_size of 1400
+    let fragment = |number: u8, payload: &[u8]| {
+        let mut bytes = common::fragment(5, 2, number, payload);
+        bytes[10..12].copy_from_slice(&2000u16.to_le_bytes());
+        bytes
+    };
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![fragment(0, &first), fragment(1, &second)]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    assert!(client.rules(address).unwrap_err().is_invalid_response());
+
+    // A fresh client, as the rejected response's second fragment is still queued on the socket
+    let client = a2s::A2SClient::new().unwrap();
+    let result = client.rules_with_buffer(address, 2000).unwrap();
+
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].value, "800");
+    assert_eq!(client.config().max_size, 1400);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_compressed_checksum() {
+    use std::io::Write;
+
+    let decompressed = common::single(&common::rules_payload(&[("sv_gravity", "800")]));
+    let checksum = crc::crc32::checksum_ieee(&decompressed);
+
+    let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::best());
+    encoder.write_all(&decompressed).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    // A compressed response in a single fragment, reporting `checksum`
+    let server = |checksum: u32| {
+        let mut response = vec![0xfe, 0xff, 0xff, 0xff];
+        response.extend((3u32 | 0x8000_0000).to_le_bytes());
+        response.extend(&[1, 0]);
+        response.extend(1248u16.to_le_bytes());
+        response.extend((decompressed.len() as u32).to_le_bytes());
+        response.extend(checksum.to_le_bytes());
+        response.extend(&compressed);
+
+        common::mock_server(move |request| {
+            if common::is_probe(request) {
+                vec![common::challenge(42)]
+            } else {
+                vec![response.clone()]
+            }
+        })
+    };
+    let valid = server(checksum);
+    let corrupt = server(!checksum);
+
+    let mut client = a2s::A2SClient::new().unwrap();
+
+    assert_eq!(client.rules(valid).unwrap()[0].value, "800");
+    assert!(matches!(
+        client.rules(corrupt),
+        Err(a2s::errors::Error::CheckSumMismatch)
+    ));
+
+    client.verify_checksum(false);
+    assert_eq!(client.rules(corrupt).unwrap()[0].value, "800");
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_rules_gold_source_compressed() {
+    use std::io::Write;
+
+    let decompressed = common::single(&common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::best());
+    encoder.write_all(&decompressed).unwrap();
+    let compressed = encoder.finish().unwrap();
+    let (first, second) = compressed.split_at(compressed.len() / 2);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    // GoldSource framing has no size nor checksum, the ID's high bit flags the compression
+    let id = 7 | i32::MIN;
+    let address = common::mock_server(move |request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::gold_source_fragment(id, 2, 0, &first),
+                common::gold_source_fragment(id, 2, 1, &second),
+            ]
+        }
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let result = client.rules(address).unwrap();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1].value, "800");
+
+    let client = a2s::A2SClient::with_config(a2s::A2SConfig {
+        max_bz2_size: 16,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(matches!(
+        client.rules(address),
+        Err(a2s::errors::Error::InvalidBz2Size)
+    ));
+}
+
+#[test]
+fn test_rules_parse_borrowed() {
+    use a2s::rules::Rule;
+    use std::borrow::Cow;
+    use std::io::Cursor;
+
+    let mut payload = common::rules_payload(&[("sv_gravity", "800"), ("hostname", "")]);
+    // An invalid UTF-8 value, decoded lossily like the owned parse does
+    payload[1] = 3;
+    payload.extend(b"motd\0caf\xe9\0");
+
+    let rules = Rule::parse_borrowed(&payload).unwrap();
+    let owned = Rule::from_cursor(Cursor::new(payload.clone())).unwrap();
+
+    assert_eq!(rules.len(), 3);
+    assert!(matches!(rules[0].value, Cow::Borrowed("800")));
+    assert!(matches!(rules[2].value, Cow::Owned(_)));
+    assert_eq!(
+        rules.iter().map(|rule| rule.to_rule()).collect::<Vec<_>>(),
+        owned
+    );
+
+    assert!(Rule::parse_borrowed(&[0x44, 0, 0])
+        .unwrap_err()
+        .is_invalid_response());
+}
+
+#[test]
+fn test_rule_diff() {
+    use a2s::rules::{Rule, RuleChange};
+
+    let rule = |name: &str, value: &str| Rule {
+        name: name.to_owned(),
+        value: value.to_owned(),
+    };
+
+    let old = vec![
+        rule("mp_timelimit", "30"),
+        rule("sv_gravity", "800"),
+        rule("sv_cheats", "0"),
+    ];
+    // A duplicate name only counts once, with its first value
+    let new = vec![
+        rule("mp_timelimit", "45"),
+        rule("sv_gravity", "800"),
+        rule("sv_alltalk", "1"),
+        rule("sv_alltalk", "0"),
+    ];
+
+    assert_eq!(
+        Rule::diff(&old, &new),
+        vec![
+            RuleChange::Changed {
+                name: "mp_timelimit".to_owned(),
+                old: "30".to_owned(),
+                new: "45".to_owned(),
+            },
+            RuleChange::Added(rule("sv_alltalk", "1")),
+            RuleChange::Removed(rule("sv_cheats", "0")),
+        ]
+    );
+    assert!(Rule::diff(&new, &new).is_empty());
+}
@@ -0,0 +1,528 @@
+mod common;
+
+use std::io::Cursor;
+
+use a2s::info::Info;
+use a2s::players::Player;
+use a2s::rules::{Rule, RuleChange};
+use a2s::state::{CommunityLinks, DetectedPlugin, PerformanceHints, Region, ServerState, Snapshot};
+
+fn player(name: &str) -> Player {
+    Player {
+        index: 0,
+        name: name.to_owned(),
+        score: 0,
+        duration: 0.0,
+        the_ship: None,
+        extra: Vec::new(),
+    }
+}
+
+fn rule(name: &str, value: &str) -> Rule {
+    Rule {
+        name: name.to_owned(),
+        value: value.to_owned(),
+    }
+}
+
+#[test]
+fn test_state_diff() {
+    let info = Info::from_cursor(Cursor::new(common::info_payload())).unwrap();
+
+    let previous = ServerState {
+        info: info.clone(),
+        players: vec![player("alice"), player("bob"), player("bob")],
+        rules: vec![rule("sv_gravity", "800"), rule("mp_timelimit", "30")],
+        ping: None,
+    };
+
+    let mut current = ServerState {
+        info,
+        players: vec![player("bob"), player("carol")],
+        rules: vec![rule("sv_gravity", "600"), rule("sv_cheats", "0")],
+        ping: None,
+    };
+    current.info.map = "de_inferno".to_owned();
+
+    let diff = current.diff(&previous);
+
+    assert_eq!(
+        diff.map,
+        Some(("de_dust2".to_owned(), "de_inferno".to_owned()))
+    );
+    assert_eq!(diff.joined, vec![player("carol")]);
+    assert_eq!(diff.left, vec![player("alice"), player("bob")]);
+    assert_eq!(
+        diff.rules,
+        vec![
+            RuleChange::Changed {
+                name: "sv_gravity".to_owned(),
+                old: "800".to_owned(),
+                new: "600".to_owned(),
+            },
+            RuleChange::Added(rule("sv_cheats", "0")),
+            RuleChange::Removed(rule("mp_timelimit", "30")),
+        ]
+    );
+
+    assert!(current.diff(&current).is_empty());
+}
+
+#[test]
+fn test_health_score() {
+    use a2s::state::HealthConfig;
+    use std::time::Duration;
+
+    // 4 humans out of 24 slots
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: Vec::new(),
+        rules: Vec::new(),
+        ping: Some(Duration::from_millis(150)),
+    };
+
+    assert!((state.health_score() - (0.25 + 0.3 / 6.0 + 0.2)).abs() < 1e-6);
+
+    let fill_only = HealthConfig {
+        ping_weight: 0.0,
+        clean_weight: -1.0,
+        ..Default::default()
+    };
+    assert!((state.health_score_with(&fill_only) - 1.0 / 6.0).abs() < 1e-6);
+
+    state.ping = None;
+    state
+        .info
+        .parse_warnings
+        .push("failed to read port".to_owned());
+    assert!((state.health_score() - 0.3 / 6.0).abs() < 1e-6);
+
+    let no_weights = HealthConfig {
+        ping_weight: 0.0,
+        fill_weight: 0.0,
+        clean_weight: 0.0,
+        ..Default::default()
+    };
+    assert_eq!(state.health_score_with(&no_weights), 0.0);
+}
+
+#[test]
+fn test_estimated_uptime() {
+    use std::time::Duration;
+
+    let mut veteran = player("alice");
+    veteran.duration = 3600.0;
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: vec![player("bob"), veteran],
+        rules: vec![rule("sv_gravity", "800")],
+        ping: None,
+    };
+
+    // Without an uptime rule, the longest connected player is the best guess
+    assert_eq!(state.estimated_uptime(), Some(Duration::from_secs(3600)));
+
+    state.rules.push(rule("SV_Uptime", " 86400 "));
+    assert_eq!(state.estimated_uptime(), Some(Duration::from_secs(86400)));
+
+    state.rules.clear();
+    state.players.clear();
+    assert_eq!(state.estimated_uptime(), None);
+}
+
+#[cfg(feature = "serialization")]
+#[test]
+fn test_state_to_json() {
+    let state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::ship_info_payload())).unwrap(),
+        players: vec![player("alice"), player("bob")],
+        rules: vec![rule("sv_gravity", "800")],
+        ping: None,
+    };
+
+    let json = state.to_json();
+    let document: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(document["info"]["server_type"], "Dedicated");
+    assert_eq!(document["players"][1]["name"], "bob");
+    assert_eq!(document["rules"][0]["value"], "800");
+
+    let decoded: ServerState = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, state);
+
+    let pretty = state.to_json_pretty();
+    assert!(pretty.contains('\n'));
+    assert_eq!(serde_json::from_str::<ServerState>(&pretty).unwrap(), state);
+}
+
+#[test]
+fn test_state_endpoints() {
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: Vec::new(),
+        rules: Vec::new(),
+        ping: None,
+    };
+    let queried = "192.0.2.7:27016".parse().unwrap();
+
+    // The info reports game port 27015
+    let endpoints = state.endpoints(queried);
+    assert_eq!(endpoints.query_endpoint, queried);
+    assert_eq!(endpoints.game_endpoint, "192.0.2.7:27015".parse().unwrap());
+
+    state.info.extended_server_info.port = None;
+    assert_eq!(state.endpoints(queried).game_endpoint, queried);
+}
+
+#[test]
+fn test_workshop_ids() {
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: Vec::new(),
+        rules: vec![rule("sv_gravity", "800")],
+        ping: None,
+    };
+    assert!(state.workshop_ids().is_empty());
+
+    state.info.map = "workshop/125438255/de_dust2_se".to_owned();
+    state.info.extended_server_info.keywords = Some("secure,workshop:300000".to_owned());
+    state.rules = vec![
+        rule("host_workshop_collection", "123456789"),
+        rule("sm_nextmap", "de_cache.ugc987654321"),
+        rule("host_workshop_map", "125438255"),
+    ];
+    assert_eq!(
+        state.workshop_ids(),
+        [125438255, 123456789, 987654321, 300000]
+    );
+}
+
+#[test]
+fn test_performance_hints() {
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: Vec::new(),
+        rules: vec![rule("sv_gravity", "800")],
+        ping: None,
+    };
+    assert_eq!(state.performance_hints(), PerformanceHints::default());
+
+    state.info.extended_server_info.keywords = Some("secure,128-tick".to_owned());
+    state.rules = vec![
+        rule("SV_MaxRate", "786432"),
+        rule("sv_minrate", "-1"),
+        rule("fps_max", "300.000000"),
+        rule("sv_maxupdaterate", "128.000000"),
+        rule("sv_mincmdrate", "fast"),
+        rule("sv_maxcmdrate", "66"),
+    ];
+    assert_eq!(
+        state.performance_hints(),
+        PerformanceHints {
+            tick_rate: Some(128),
+            fps_max: Some(300.0),
+            max_rate: Some(786432),
+            max_update_rate: Some(128),
+            max_cmd_rate: Some(66),
+            ..Default::default()
+        }
+    );
+
+    // The rule wins over the keyword
+    state.rules.push(rule("sv_tickrate", "64"));
+    assert_eq!(state.performance_hints().tick_rate, Some(64));
+}
+
+#[test]
+fn test_snapshot() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: Vec::new(),
+        rules: Vec::new(),
+        ping: Some(Duration::from_micros(42_500)),
+    };
+
+    let mut snapshot = state.to_snapshot();
+    assert_eq!(
+        (snapshot.players, snapshot.max_players, snapshot.bots),
+        (5, 24, 1)
+    );
+    assert_eq!(snapshot.map_hash, Snapshot::map_hash("DE_DUST2"));
+    assert_ne!(snapshot.map_hash, Snapshot::map_hash("de_inferno"));
+
+    snapshot.timestamp = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+    let bytes = snapshot.to_bytes();
+    assert_eq!(bytes.len(), a2s::state::SNAPSHOT_SIZE);
+    assert_eq!(bytes[0], a2s::state::SNAPSHOT_VERSION);
+    assert_eq!(Snapshot::from_bytes(&bytes).unwrap(), snapshot);
+
+    // Unknown pings stay unknown, and the timestamp is kept to the millisecond
+    state.ping = None;
+    let mut snapshot = state.to_snapshot();
+    let decoded = Snapshot::from_bytes(&snapshot.to_bytes()).unwrap();
+    assert_eq!(decoded.ping, None);
+    snapshot.timestamp = decoded.timestamp;
+    assert_eq!(decoded, snapshot);
+
+    assert!(Snapshot::from_bytes(&bytes[..10]).is_err());
+    let mut future = bytes;
+    future[0] = 2;
+    assert!(Snapshot::from_bytes(&future).is_err());
+}
+
+#[test]
+fn test_detect_plugins() {
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: Vec::new(),
+        rules: vec![rule("sv_gravity", "800"), rule("sm_version", "1")],
+        ping: None,
+    };
+    assert!(state.detect_plugins().is_empty());
+
+    state.rules = vec![
+        rule("MetaMod_Version", "1.11.0-dev+1145V"),
+        rule("sv_gravity", "800"),
+        rule("sourcemod_version", "1.11.0.6911"),
+        rule("sm_advertisements_version", "v2.1.2 by Tsunami"),
+        rule("sm_nextmap", "de_nuke"),
+        rule("sm_rtv_version", ""),
+        rule("sourcemod_version", "1.10.0.6502"),
+    ];
+    let plugin = |name: &str, version: Option<&str>, rule: &str| DetectedPlugin {
+        name: name.to_owned(),
+        version: version.map(str::to_owned),
+        rule: rule.to_owned(),
+    };
+    assert_eq!(
+        state.detect_plugins(),
+        vec![
+            plugin("Metamod", Some("1.11.0-dev+1145V"), "MetaMod_Version"),
+            plugin("SourceMod", Some("1.11.0.6911"), "sourcemod_version"),
+            plugin(
+                "advertisements",
+                Some("v2.1.2"),
+                "sm_advertisements_version"
+            ),
+            plugin("rtv", None, "sm_rtv_version"),
+        ]
+    );
+}
+
+#[test]
+fn test_community_links() {
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: Vec::new(),
+        rules: vec![
+            rule("website", "not a link"),
+            rule("discord", "https://discord.gg"),
+            rule("teamspeak", "http://ts.example.com"),
+        ],
+        ping: None,
+    };
+    assert_eq!(state.community_links(), CommunityLinks::default());
+
+    state.rules = vec![
+        rule("sm_advertisements", "Join us at discord.gg/abc123!"),
+        rule("SV_Website", "Visit https://example.com/forum."),
+        rule("ts3", "ts3server://203.0.113.7:9987"),
+    ];
+    assert_eq!(
+        state.community_links(),
+        CommunityLinks {
+            website: Some("https://example.com/forum".to_owned()),
+            discord: Some("discord.gg/abc123".to_owned()),
+            teamspeak: Some("ts3server://203.0.113.7:9987".to_owned()),
+        }
+    );
+
+    // A Discord invite in the website rule is only taken as Discord
+    state.rules = vec![rule("website", "https://discord.com/invite/xyz")];
+    let links = state.community_links();
+    assert_eq!(links.website, None);
+    assert_eq!(
+        links.discord.as_deref(),
+        Some("https://discord.com/invite/xyz")
+    );
+}
+
+#[test]
+fn test_infer_region() {
+    let mut state = ServerState {
+        info: Info::from_cursor(Cursor::new(common::info_payload())).unwrap(),
+        players: Vec::new(),
+        rules: vec![rule("sv_region", "255")],
+        ping: None,
+    };
+    let ip = "10.0.0.1".parse().unwrap();
+    assert_eq!(state.infer_region(), None);
+    assert_eq!(
+        state.infer_region_with(ip, |_| Some(Region::Asia)),
+        Some(Region::Asia)
+    );
+
+    // Rules are the fallback, after the keywords and geolocation
+    state.rules = vec![rule("sv_region", "3")];
+    assert_eq!(state.infer_region(), Some(Region::Europe));
+    assert_eq!(
+        state.infer_region_with(ip, |_| Some(Region::Asia)),
+        Some(Region::Asia)
+    );
+    assert_eq!(state.infer_region_with(ip, |_| None), Some(Region::Europe));
+
+    state.info.extended_server_info.keywords = Some("secure,US-West".to_owned());
+    assert_eq!(state.infer_region(), Some(Region::UsWest));
+
+    state.info.extended_server_info.keywords = Some("secure,region:oce,us".to_owned());
+    assert_eq!(
+        state.infer_region_with(ip, |_| Some(Region::Asia)),
+        Some(Region::Australia)
+    );
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_query_all_learns_app_id() {
+    let address = common::mock_server(common::full_server(
+        common::ship_info_payload(),
+        common::players_payload(&["alice", "bob"], true),
+        common::rules_payload(&[("sv_gravity", "800")]),
+    ));
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let state = client.query_all(address).unwrap();
+
+    assert_eq!(state.info.app_id, 2400);
+    assert_eq!(state.players.len(), 2);
+    assert_eq!(state.players[1].the_ship.as_ref().unwrap().money, 500);
+    assert_eq!(state.rules, vec![rule("sv_gravity", "800")]);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_query_all_without_the_ship() {
+    // A non-Ship server misreporting The Ship's app ID.
+    let mut info = common::info_payload();
+    let offset = info
+        .windows(2)
+        .position(|w| w == 730u16.to_le_bytes())
+        .unwrap();
+    info[offset..offset + 2].copy_from_slice(&2400u16.to_le_bytes());
+
+    let address = common::mock_server(common::full_server(
+        info,
+        common::players_payload(&["alice", "bob"], false),
+        common::rules_payload(&[("sv_gravity", "800")]),
+    ));
+
+    let mut client = a2s::A2SClient::new().unwrap();
+    client.parse_the_ship(false);
+
+    let state = client.query_all(address).unwrap();
+
+    assert_eq!(state.info.the_ship, None);
+    assert_eq!(state.info.version, "1.38.0.0");
+    assert_eq!(state.players.len(), 2);
+    assert_eq!(state.players[1].the_ship, None);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_session_reuses_challenge() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let mut current = 7i32;
+    let address = common::mock_server(move |request| {
+        let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        if !request.ends_with(&current.to_le_bytes()) {
+            return vec![common::challenge(current)];
+        }
+
+        let payload = match request[4] {
+            0x54 => common::info_payload(),
+            0x55 => common::players_payload(&["alice"], false),
+            _ => common::rules_payload(&[("sv_gravity", "800")]),
+        };
+        // Rotate the challenge mid-batch, right after the first info query is answered
+        if count == 2 {
+            current = 9;
+        }
+        vec![common::single(&payload)]
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+    let mut session = a2s::state::ServerSession::new(&client, address);
+
+    let state = session.refresh_all().unwrap();
+    assert_eq!(state.players[0].name, "alice");
+    assert_eq!(state.rules, vec![rule("sv_gravity", "800")]);
+    assert_eq!(session.challenge(), Some(9));
+    assert_eq!(requests.load(Ordering::SeqCst), 5);
+
+    session.refresh_all().unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 8);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_session_from_info() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let address = common::mock_server(move |request| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        if !request.ends_with(&3i32.to_le_bytes()) {
+            return vec![common::challenge(3)];
+        }
+
+        let payload = match request[4] {
+            0x54 => common::ship_info_payload(),
+            0x55 => common::players_payload(&["alice"], true),
+            _ => common::rules_payload(&[("sv_gravity", "800")]),
+        };
+        vec![common::single(&payload)]
+    });
+
+    let client = a2s::A2SClient::new().unwrap();
+    let (mut session, info) = a2s::state::ServerSession::from_info(&client, address).unwrap();
+    assert_eq!(info.app_id, 2400);
+    assert_eq!(session.challenge(), Some(3));
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Both reuse the info's challenge, and players are parsed as The Ship's
+    let players = session.players().unwrap();
+    assert_eq!(players[0].the_ship.as_ref().unwrap().money, 500);
+    assert_eq!(session.rules().unwrap(), vec![rule("sv_gravity", "800")]);
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+}
+
+#[cfg(not(feature = "async"))]
+#[test]
+fn test_players_checked() {
+    // The info reports 5 players
+    let server = |names: &[&str]| {
+        common::mock_server(common::full_server(
+            common::info_payload(),
+            common::players_payload(names, false),
+            common::rules_payload(&[]),
+        ))
+    };
+    let truncated = server(&["alice", "bob"]);
+    let complete = server(&["alice", "bob", "carol", "dave", "erin"]);
+
+    let client = a2s::A2SClient::new().unwrap();
+
+    let list = client.players_checked(truncated).unwrap();
+    assert_eq!(list.players.len(), 2);
+    assert!(list.players_truncated);
+
+    assert!(!client.players_checked(complete).unwrap().players_truncated);
+}
@@ -0,0 +1,127 @@
+#[cfg(feature = "tcp")]
+mod common;
+
+#[cfg(feature = "tcp")]
+mod relay {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn read_frame(stream: &mut TcpStream) -> Option<Vec<u8>> {
+        let len = stream.read_u32_le().await.ok()?;
+        let mut data = vec![0; len as usize];
+        stream.read_exact(&mut data).await.ok()?;
+        Some(data)
+    }
+
+    async fn write_frame(stream: &mut TcpStream, data: &[u8]) {
+        stream.write_u32_le(data.len() as u32).await.unwrap();
+        stream.write_all(data).await.unwrap();
+    }
+
+    /// A TCP relay answering every framed request with the datagrams returned by a handler.
+    pub async fn spawn<F>(mut handler: F) -> std::net::SocketAddr
+    where
+        F: FnMut(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            while let Some(request) = read_frame(&mut stream).await {
+                for datagram in handler(&request) {
+                    write_frame(&mut stream, &datagram).await;
+                }
+            }
+        });
+
+        addr
+    }
+}
+
+#[cfg(feature = "tcp")]
+#[tokio::test]
+async fn test_tcp_transport_info() {
+    use std::time::Duration;
+
+    use a2s::query::InfoQuery;
+    use a2s::transport::{query_transport, TcpTransport};
+    use a2s::A2SConfig;
+
+    let addr = relay::spawn(|request| {
+        if common::is_probe(request) {
+            vec![common::challenge(42)]
+        } else {
+            vec![common::single(&common::info_payload())]
+        }
+    })
+    .await;
+
+    let transport = TcpTransport::connect(addr, Duration::from_secs(1))
+        .await
+        .unwrap();
+    let info = query_transport(&transport, &InfoQuery, &A2SConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(info.players, 5);
+    assert_eq!(info.max_players, 24);
+}
+
+#[cfg(feature = "tcp")]
+#[tokio::test]
+async fn test_tcp_transport_rules_multipacket() {
+    use std::time::Duration;
+
+    use a2s::query::RulesQuery;
+    use a2s::transport::{query_transport, TcpTransport};
+    use a2s::A2SConfig;
+
+    let addr = relay::spawn(|request| {
+        if request.ends_with(&42i32.to_le_bytes()) {
+            let mut payload = vec![0xff, 0xff, 0xff, 0xff];
+            payload.extend(common::rules_payload(&[("sv_gravity", "800")]));
+            let (first, second) = payload.split_at(payload.len() / 2);
+            vec![
+                common::fragment(3, 2, 0, first),
+                common::fragment(3, 2, 1, second),
+            ]
+        } else {
+            vec![common::challenge(42)]
+        }
+    })
+    .await;
+
+    let transport = TcpTransport::connect(addr, Duration::from_secs(1))
+        .await
+        .unwrap();
+    let rules = query_transport(&transport, &RulesQuery, &A2SConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(rules[0].name, "sv_gravity");
+    assert_eq!(rules[0].value, "800");
+}
+
+#[cfg(feature = "tcp")]
+#[tokio::test]
+async fn test_tcp_transport_oversized_frame() {
+    use std::time::Duration;
+
+    use a2s::transport::{AsyncDatagram, TcpTransport};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        use tokio::io::AsyncWriteExt;
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream.write_u32_le(u32::MAX).await.unwrap();
+        // Keep the connection open so the read doesn't fail on EOF instead
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+
+    let transport = TcpTransport::connect(addr, Duration::from_millis(500))
+        .await
+        .unwrap();
+    let err = transport.recv().await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
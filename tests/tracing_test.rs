@@ -0,0 +1,90 @@
+#[cfg(all(feature = "tracing", not(feature = "async")))]
+mod common;
+
+#[cfg(all(feature = "tracing", not(feature = "async")))]
+mod capture {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// The `request_id` of every span, and the events emitted within each.
+    #[derive(Default)]
+    pub struct Spans {
+        pub request_ids: Vec<Option<String>>,
+        pub events: Vec<usize>,
+        entered: Vec<usize>,
+    }
+
+    /// A subscriber recording into shared [`Spans`], for a single thread.
+    #[derive(Clone, Default)]
+    pub struct Capture(pub Arc<Mutex<Spans>>);
+
+    struct RequestId(Option<String>);
+
+    impl Visit for RequestId {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "request_id" {
+                self.0 = Some(value.to_owned());
+            }
+        }
+
+        fn record_debug(&mut self, _: &Field, _: &dyn Debug) {}
+    }
+
+    impl Subscriber for Capture {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut request_id = RequestId(None);
+            span.record(&mut request_id);
+
+            let mut spans = self.0.lock().unwrap();
+            spans.request_ids.push(request_id.0);
+            spans.events.push(0);
+            Id::from_u64(spans.request_ids.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {
+            let mut spans = self.0.lock().unwrap();
+            if let Some(&span) = spans.entered.last() {
+                spans.events[span] += 1;
+            }
+        }
+
+        fn enter(&self, span: &Id) {
+            let index = span.into_u64() as usize - 1;
+            self.0.lock().unwrap().entered.push(index);
+        }
+
+        fn exit(&self, _: &Id) {
+            self.0.lock().unwrap().entered.pop();
+        }
+    }
+}
+
+#[cfg(all(feature = "tracing", not(feature = "async")))]
+#[test]
+fn test_request_id_span() {
+    let address = common::mock_server(|_| vec![common::single(&common::info_payload())]);
+    let client = a2s::A2SClient::new().unwrap();
+
+    let capture = capture::Capture::default();
+    tracing::subscriber::with_default(capture.clone(), || {
+        client.with_request_id("7f3a").info(address).unwrap();
+        client.info(address).unwrap();
+    });
+
+    let spans = capture.0.lock().unwrap();
+    assert_eq!(spans.request_ids, [Some("7f3a".to_owned()), None]);
+    // The request and response datagrams, and the outcome
+    assert_eq!(spans.events, [3, 3]);
+}
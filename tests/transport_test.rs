@@ -0,0 +1,108 @@
+#[cfg(feature = "wasm")]
+mod common;
+
+#[cfg(feature = "wasm")]
+mod relay {
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::future::Future;
+    use std::io;
+
+    use a2s::transport::AsyncDatagram;
+
+    /// A transport answering every datagram with those returned by a handler, like a relay
+    /// would forward a server's.
+    pub struct Relay<F> {
+        handler: RefCell<F>,
+        inbox: RefCell<VecDeque<Vec<u8>>>,
+    }
+
+    impl<F: FnMut(&[u8]) -> Vec<Vec<u8>>> Relay<F> {
+        pub fn new(handler: F) -> Self {
+            Relay {
+                handler: RefCell::new(handler),
+                inbox: RefCell::default(),
+            }
+        }
+    }
+
+    impl<F: FnMut(&[u8]) -> Vec<Vec<u8>>> AsyncDatagram for Relay<F> {
+        fn send(&self, data: &[u8]) -> impl Future<Output = io::Result<()>> {
+            let datagrams = (self.handler.borrow_mut())(data);
+            self.inbox.borrow_mut().extend(datagrams);
+            async { Ok(()) }
+        }
+
+        fn recv(&self) -> impl Future<Output = io::Result<Vec<u8>>> {
+            let datagram = self.inbox.borrow_mut().pop_front();
+            async { datagram.ok_or_else(|| io::ErrorKind::TimedOut.into()) }
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn test_transport_info() {
+    use a2s::query::InfoQuery;
+    use a2s::transport::query_transport;
+    use a2s::A2SConfig;
+    use futures::executor::block_on;
+
+    let relay = relay::Relay::new(|_: &[u8]| vec![common::single(&common::info_payload())]);
+    let info = block_on(query_transport(&relay, &InfoQuery, &A2SConfig::default())).unwrap();
+
+    assert_eq!(info.players, 5);
+    assert_eq!(info.max_players, 24);
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn test_transport_rules_multipacket() {
+    use a2s::errors::Error;
+    use a2s::query::RulesQuery;
+    use a2s::transport::query_transport;
+    use a2s::A2SConfig;
+    use futures::executor::block_on;
+
+    let mut payload = vec![0xff, 0xff, 0xff, 0xff];
+    payload.extend(common::rules_payload(&[
+        ("mp_timelimit", "30"),
+        ("sv_gravity", "800"),
+    ]));
+    let (first, second) = payload.split_at(payload.len() / 2);
+    let (first, second) = (first.to_vec(), second.to_vec());
+
+    let relay = relay::Relay::new(move |request: &[u8]| {
+        if !request.ends_with(&42i32.to_le_bytes()) {
+            vec![common::challenge(42)]
+        } else {
+            vec![
+                common::fragment(7, 2, 0, &first),
+                common::fragment(7, 2, 1, &second),
+            ]
+        }
+    });
+    let rules = block_on(query_transport(&relay, &RulesQuery, &A2SConfig::default())).unwrap();
+
+    assert_eq!(rules.len(), 2);
+    assert_eq!(rules[1].name, "sv_gravity");
+    assert_eq!(rules[1].value, "800");
+
+    // A relay that goes quiet midway reports the fragments it got
+    let relay = relay::Relay::new(|request: &[u8]| {
+        if !request.ends_with(&42i32.to_le_bytes()) {
+            vec![common::challenge(42)]
+        } else {
+            vec![common::fragment(7, 2, 0, b"\xff\xff\xff\xffE")]
+        }
+    });
+    let err = block_on(query_transport(&relay, &RulesQuery, &A2SConfig::default())).unwrap_err();
+
+    assert!(matches!(
+        err,
+        Error::IncompleteResponse {
+            received: 1,
+            expected: 2
+        }
+    ));
+}
@@ -0,0 +1,319 @@
+//! Byte-exact fixtures of every response layout, laid out like the examples of the Valve
+//! Developer Community's "Server queries" page, checked field by field. Unlike the network tests,
+//! they run offline and pin down the parse of each layout against regressions.
+
+use a2s::fragment::FragmentAssembler;
+use a2s::info::{ServerOS, ServerType, TheShipMode};
+use a2s::players::Player;
+use a2s::rules::Rule;
+use a2s::{parse_response, ParseContext, ParseResponse, ParsedResponse, QueryKind};
+
+/// A2S_INFO response of a Counter-Strike: Source server, without extra data.
+#[rustfmt::skip]
+const SOURCE_INFO: &[u8] = &[
+    0xff, 0xff, 0xff, 0xff, 0x49, 0x02, 0x67, 0x61, 0x6d, 0x65, 0x32, 0x78, 0x73, 0x2e, 0x63, 0x6f, // ....I.game2xs.co
+    0x6d, 0x20, 0x43, 0x6f, 0x75, 0x6e, 0x74, 0x65, 0x72, 0x2d, 0x53, 0x74, 0x72, 0x69, 0x6b, 0x65, // m Counter-Strike
+    0x20, 0x53, 0x6f, 0x75, 0x72, 0x63, 0x65, 0x20, 0x23, 0x31, 0x00, 0x64, 0x65, 0x5f, 0x64, 0x75, //  Source #1.de_du
+    0x73, 0x74, 0x00, 0x63, 0x73, 0x74, 0x72, 0x69, 0x6b, 0x65, 0x00, 0x43, 0x6f, 0x75, 0x6e, 0x74, // st.cstrike.Count
+    0x65, 0x72, 0x2d, 0x53, 0x74, 0x72, 0x69, 0x6b, 0x65, 0x3a, 0x20, 0x53, 0x6f, 0x75, 0x72, 0x63, // er-Strike: Sourc
+    0x65, 0x00, 0xf0, 0x00, 0x05, 0x10, 0x04, 0x64, 0x6c, 0x00, 0x00, 0x31, 0x2e, 0x30, 0x2e, 0x30, // e......dl..1.0.0
+    0x2e, 0x32, 0x32, 0x00,                                                                         // .22.
+];
+
+/// A2S_INFO response of a CS:GO server with every extra data field: port, Steam ID,
+/// SourceTV, keywords and game ID (EDF 0xF1).
+#[rustfmt::skip]
+const SOURCE_INFO_EDF: &[u8] = &[
+    0xff, 0xff, 0xff, 0xff, 0x49, 0x11, 0x56, 0x61, 0x6c, 0x76, 0x65, 0x20, 0x43, 0x53, 0x3a, 0x47, // ....I.Valve CS:G
+    0x4f, 0x20, 0x53, 0x65, 0x72, 0x76, 0x65, 0x72, 0x00, 0x64, 0x65, 0x5f, 0x69, 0x6e, 0x66, 0x65, // O Server.de_infe
+    0x72, 0x6e, 0x6f, 0x00, 0x63, 0x73, 0x67, 0x6f, 0x00, 0x43, 0x6f, 0x75, 0x6e, 0x74, 0x65, 0x72, // rno.csgo.Counter
+    0x2d, 0x53, 0x74, 0x72, 0x69, 0x6b, 0x65, 0x3a, 0x20, 0x47, 0x6c, 0x6f, 0x62, 0x61, 0x6c, 0x20, // -Strike: Global 
+    0x4f, 0x66, 0x66, 0x65, 0x6e, 0x73, 0x69, 0x76, 0x65, 0x00, 0xda, 0x02, 0x0a, 0x14, 0x00, 0x64, // Offensive......d
+    0x77, 0x00, 0x01, 0x31, 0x2e, 0x33, 0x38, 0x2e, 0x37, 0x2e, 0x39, 0x00, 0xf1, 0x87, 0x69, 0x20, // w..1.38.7.9...i 
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x30, 0x01, 0x8c, 0x69, 0x47, 0x4f, 0x54, 0x56, 0x00, 0x73, 0x65, // .....0..iGOTV.se
+    0x63, 0x75, 0x72, 0x65, 0x2c, 0x76, 0x61, 0x6c, 0x76, 0x65, 0x5f, 0x64, 0x73, 0x2c, 0x65, 0x6d, // cure,valve_ds,em
+    0x70, 0x74, 0x79, 0x00, 0xda, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,                         // pty.........
+];
+
+/// Obsolete GoldSource A2S_INFO response (header `0x6D`) of a Half-Life server, not
+/// running a mod.
+#[rustfmt::skip]
+const GOLD_SOURCE_INFO: &[u8] = &[
+    0xff, 0xff, 0xff, 0xff, 0x6d, 0x31, 0x39, 0x32, 0x2e, 0x31, 0x36, 0x38, 0x2e, 0x31, 0x2e, 0x31, // ....m192.168.1.1
+    0x30, 0x3a, 0x32, 0x37, 0x30, 0x31, 0x35, 0x00, 0x48, 0x61, 0x6c, 0x66, 0x2d, 0x4c, 0x69, 0x66, // 0:27015.Half-Lif
+    0x65, 0x20, 0x44, 0x4d, 0x00, 0x63, 0x72, 0x6f, 0x73, 0x73, 0x66, 0x69, 0x72, 0x65, 0x00, 0x76, // e DM.crossfire.v
+    0x61, 0x6c, 0x76, 0x65, 0x00, 0x48, 0x61, 0x6c, 0x66, 0x2d, 0x4c, 0x69, 0x66, 0x65, 0x00, 0x0b, // alve.Half-Life..
+    0x20, 0x2f, 0x64, 0x77, 0x00, 0x00, 0x01, 0x00,                                                 //  /dw....
+];
+
+/// A2S_INFO response of a The Ship server, in Elimination mode with 3 witnesses and
+/// a 120 seconds duration.
+#[rustfmt::skip]
+const SHIP_INFO: &[u8] = &[
+    0xff, 0xff, 0xff, 0xff, 0x49, 0x07, 0x54, 0x68, 0x65, 0x20, 0x53, 0x68, 0x69, 0x70, 0x20, 0x53, // ....I.The Ship S
+    0x65, 0x72, 0x76, 0x65, 0x72, 0x00, 0x62, 0x61, 0x74, 0x61, 0x76, 0x69, 0x65, 0x72, 0x00, 0x73, // erver.batavier.s
+    0x68, 0x69, 0x70, 0x00, 0x54, 0x68, 0x65, 0x20, 0x53, 0x68, 0x69, 0x70, 0x00, 0x60, 0x09, 0x03, // hip.The Ship.`..
+    0x10, 0x00, 0x64, 0x77, 0x00, 0x01, 0x01, 0x03, 0x78, 0x31, 0x2e, 0x30, 0x2e, 0x30, 0x2e, 0x34, // ..dw....x1.0.0.4
+    0x00,                                                                                           // .
+];
+
+/// A2S_PLAYER response of the same server, each player followed by their deaths and
+/// money.
+#[rustfmt::skip]
+const SHIP_PLAYERS: &[u8] = &[
+    0xff, 0xff, 0xff, 0xff, 0x44, 0x03, 0x00, 0x43, 0x61, 0x70, 0x74, 0x61, 0x69, 0x6e, 0x00, 0x05, // ....D..Captain..
+    0x00, 0x00, 0x00, 0x00, 0xc0, 0x96, 0x43, 0x01, 0x00, 0x00, 0x00, 0xb0, 0x04, 0x00, 0x00, 0x01, // ......C.........
+    0x4d, 0x61, 0x74, 0x65, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x29, 0x42, 0x02, 0x00, 0x00, // Mate.......)B...
+    0x00, 0xf4, 0x01, 0x00, 0x00, 0x02, 0x53, 0x74, 0x6f, 0x77, 0x61, 0x77, 0x61, 0x79, 0x00, 0xff, // ......Stowaway..
+    0xff, 0xff, 0xff, 0x00, 0x00, 0x20, 0x41, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,       // ..... A........
+];
+
+/// First of the two fragments of a Source A2S_RULES response.
+#[rustfmt::skip]
+const SOURCE_RULES_0: &[u8] = &[
+    0xfe, 0xff, 0xff, 0xff, 0x23, 0x01, 0x00, 0x00, 0x02, 0x00, 0xe0, 0x04, 0xff, 0xff, 0xff, 0xff, // ....#...........
+    0x45, 0x10, 0x00, 0x63, 0x6f, 0x6f, 0x70, 0x00, 0x30, 0x00, 0x64, 0x65, 0x61, 0x74, 0x68, 0x6d, // E..coop.0.deathm
+    0x61, 0x74, 0x63, 0x68, 0x00, 0x31, 0x00, 0x64, 0x65, 0x63, 0x61, 0x6c, 0x66, 0x72, 0x65, 0x71, // atch.1.decalfreq
+    0x75, 0x65, 0x6e, 0x63, 0x79, 0x00, 0x31, 0x30, 0x00, 0x6d, 0x70, 0x5f, 0x61, 0x75, 0x74, 0x6f, // uency.10.mp_auto
+    0x6b, 0x69, 0x63, 0x6b, 0x00, 0x31, 0x00, 0x6d, 0x70, 0x5f, 0x61, 0x75, 0x74, 0x6f, 0x74, 0x65, // kick.1.mp_autote
+    0x61, 0x6d, 0x62, 0x61, 0x6c, 0x61, 0x6e, 0x63, 0x65, 0x00, 0x31, 0x00, 0x6d, 0x70, 0x5f, 0x63, // ambalance.1.mp_c
+    0x34, 0x74, 0x69, 0x6d, 0x65, 0x72, 0x00, 0x34, 0x35, 0x00, 0x6d, 0x70, 0x5f, 0x66, 0x72, 0x69, // 4timer.45.mp_fri
+    0x65, 0x6e, 0x64, 0x6c, 0x79, 0x66, 0x69, 0x72, 0x65, 0x00, 0x30, 0x00, 0x6d, 0x70, 0x5f, 0x72, // endlyfire.0.mp_r
+    0x6f, 0x75, 0x6e, 0x64,                                                                         // ound
+];
+
+/// Second of the two fragments of a Source A2S_RULES response.
+#[rustfmt::skip]
+const SOURCE_RULES_1: &[u8] = &[
+    0xfe, 0xff, 0xff, 0xff, 0x23, 0x01, 0x00, 0x00, 0x02, 0x01, 0xe0, 0x04, 0x74, 0x69, 0x6d, 0x65, // ....#.......time
+    0x00, 0x32, 0x2e, 0x35, 0x00, 0x6d, 0x70, 0x5f, 0x74, 0x69, 0x6d, 0x65, 0x6c, 0x69, 0x6d, 0x69, // .2.5.mp_timelimi
+    0x74, 0x00, 0x33, 0x30, 0x00, 0x6e, 0x65, 0x78, 0x74, 0x6c, 0x65, 0x76, 0x65, 0x6c, 0x00, 0x00, // t.30.nextlevel..
+    0x73, 0x76, 0x5f, 0x61, 0x6c, 0x6c, 0x74, 0x61, 0x6c, 0x6b, 0x00, 0x30, 0x00, 0x73, 0x76, 0x5f, // sv_alltalk.0.sv_
+    0x63, 0x68, 0x65, 0x61, 0x74, 0x73, 0x00, 0x30, 0x00, 0x73, 0x76, 0x5f, 0x67, 0x72, 0x61, 0x76, // cheats.0.sv_grav
+    0x69, 0x74, 0x79, 0x00, 0x38, 0x30, 0x30, 0x00, 0x73, 0x76, 0x5f, 0x6d, 0x61, 0x78, 0x73, 0x70, // ity.800.sv_maxsp
+    0x65, 0x65, 0x64, 0x00, 0x33, 0x32, 0x30, 0x00, 0x73, 0x76, 0x5f, 0x70, 0x61, 0x73, 0x73, 0x77, // eed.320.sv_passw
+    0x6f, 0x72, 0x64, 0x00, 0x30, 0x00, 0x74, 0x76, 0x5f, 0x70, 0x61, 0x73, 0x73, 0x77, 0x6f, 0x72, // ord.0.tv_passwor
+    0x64, 0x00, 0x30, 0x00,                                                                         // d.0.
+];
+
+/// First of the two fragments of the same A2S_RULES response, compressed with bzip2: its
+/// ID has the high bit set and it carries the decompressed size and CRC32.
+#[rustfmt::skip]
+const SOURCE_RULES_BZ2_0: &[u8] = &[
+    0xfe, 0xff, 0xff, 0xff, 0x56, 0x04, 0x00, 0x80, 0x02, 0x00, 0xe0, 0x04, 0xf0, 0x00, 0x00, 0x00, // ....V...........
+    0xc9, 0xd2, 0x63, 0x4d, 0x42, 0x5a, 0x68, 0x39, 0x31, 0x41, 0x59, 0x26, 0x53, 0x59, 0xc0, 0x7a, // ..cMBZh91AY&SY.z
+    0x14, 0x75, 0x00, 0x00, 0x78, 0x7f, 0x80, 0xc0, 0x00, 0x40, 0x00, 0x00, 0x01, 0x7e, 0x40, 0x02, // .u..x....@...~@.
+    0x00, 0x00, 0x00, 0xbf, 0xef, 0xff, 0xe0, 0x00, 0x00, 0xb0, 0x00, 0xbb, 0x6a, 0x1a, 0x99, 0x10, // ............j...
+    0xd3, 0x13, 0xd2, 0x00, 0x1a, 0x00, 0x04, 0xa8, 0xa7, 0xfa, 0x93, 0x27, 0xa2, 0x86, 0x8f, 0x50, // ...........'...P
+    0xf5, 0x1e, 0x90, 0x68, 0xd0, 0x21, 0xa8, 0xd2, 0x00, 0x68, 0xc9, 0xa6, 0x80, 0x68, 0x0c, 0xb5, // ...h.!...h...h..
+    0x74, 0x78, 0xc4, 0xa2, 0xb4, 0xf4, 0x49, 0x92, 0x4f, 0xac, 0xb9, 0xa0, 0x13, 0xd4, 0x03, 0x41, // tx....I.O......A
+    0x1c, 0x1c, 0xb6, 0x07, 0x82, 0x62, 0x28, 0xa0, 0x0c, 0x90, 0x1d, 0xed, 0x64,                   // .....b(.....d
+];
+
+/// Second of the two fragments of the compressed A2S_RULES response.
+#[rustfmt::skip]
+const SOURCE_RULES_BZ2_1: &[u8] = &[
+    0xfe, 0xff, 0xff, 0xff, 0x56, 0x04, 0x00, 0x80, 0x02, 0x01, 0xe0, 0x04, 0x6b, 0x67, 0x3d, 0xef, // ....V.......kg=.
+    0x07, 0x99, 0xc7, 0x95, 0xfa, 0x5e, 0x27, 0xa4, 0x40, 0xb2, 0x36, 0x2d, 0x24, 0xb4, 0xdb, 0x09, // .....^'.@.6-$...
+    0x11, 0x42, 0x14, 0xf1, 0xc7, 0x49, 0x13, 0x5e, 0x93, 0xf9, 0x07, 0x2d, 0x21, 0x23, 0x88, 0x40, // .B...I.^...-!#.@
+    0x9b, 0x34, 0xd5, 0x37, 0x34, 0xc1, 0x74, 0x77, 0x1e, 0x9a, 0x46, 0x42, 0x6b, 0x2d, 0xa9, 0x95, // .4.74.tw..FBk-..
+    0x81, 0x70, 0x51, 0xc8, 0xaa, 0x6a, 0x53, 0xe8, 0xb8, 0x2c, 0x87, 0x50, 0xf9, 0x33, 0x5c, 0x0d, // .pQ..jS..,.P.3\.
+    0xd5, 0x22, 0xdf, 0xb2, 0x37, 0xb8, 0x99, 0x2a, 0xc1, 0x9f, 0x56, 0xcd, 0xaa, 0x61, 0x36, 0xfa, // ."..7..*..V..a6.
+    0x96, 0x07, 0xa8, 0x42, 0x2b, 0x58, 0x23, 0xb9, 0x89, 0xf2, 0x9d, 0xf8, 0xbb, 0x92, 0x29, 0xc2, // ...B+X#.......).
+    0x84, 0x86, 0x03, 0xd0, 0xa3, 0xa8,                                                             // ......
+];
+
+/// First of the two fragments of the same A2S_RULES response from a GoldSource server, its
+/// number and total packed in one byte.
+#[rustfmt::skip]
+const GOLD_SOURCE_RULES_0: &[u8] = &[
+    0xfe, 0xff, 0xff, 0xff, 0x89, 0x07, 0x00, 0x00, 0x02, 0xff, 0xff, 0xff, 0xff, 0x45, 0x10, 0x00, // .............E..
+    0x63, 0x6f, 0x6f, 0x70, 0x00, 0x30, 0x00, 0x64, 0x65, 0x61, 0x74, 0x68, 0x6d, 0x61, 0x74, 0x63, // coop.0.deathmatc
+    0x68, 0x00, 0x31, 0x00, 0x64, 0x65, 0x63, 0x61, 0x6c, 0x66, 0x72, 0x65, 0x71, 0x75, 0x65, 0x6e, // h.1.decalfrequen
+    0x63, 0x79, 0x00, 0x31, 0x30, 0x00, 0x6d, 0x70, 0x5f, 0x61, 0x75, 0x74, 0x6f, 0x6b, 0x69, 0x63, // cy.10.mp_autokic
+    0x6b, 0x00, 0x31, 0x00, 0x6d, 0x70, 0x5f, 0x61, 0x75, 0x74, 0x6f, 0x74, 0x65, 0x61, 0x6d, 0x62, // k.1.mp_autoteamb
+    0x61, 0x6c, 0x61, 0x6e, 0x63, 0x65, 0x00, 0x31, 0x00, 0x6d, 0x70, 0x5f, 0x63, 0x34, 0x74, 0x69, // alance.1.mp_c4ti
+    0x6d, 0x65, 0x72, 0x00, 0x34, 0x35, 0x00, 0x6d, 0x70, 0x5f, 0x66, 0x72, 0x69, 0x65, 0x6e, 0x64, // mer.45.mp_friend
+    0x6c, 0x79, 0x66, 0x69, 0x72, 0x65, 0x00, 0x30, 0x00, 0x6d, 0x70, 0x5f, 0x72, 0x6f, 0x75, 0x6e, // lyfire.0.mp_roun
+    0x64,                                                                                           // d
+];
+
+/// Second of the two fragments of the GoldSource A2S_RULES response.
+#[rustfmt::skip]
+const GOLD_SOURCE_RULES_1: &[u8] = &[
+    0xfe, 0xff, 0xff, 0xff, 0x89, 0x07, 0x00, 0x00, 0x12, 0x74, 0x69, 0x6d, 0x65, 0x00, 0x32, 0x2e, // .........time.2.
+    0x35, 0x00, 0x6d, 0x70, 0x5f, 0x74, 0x69, 0x6d, 0x65, 0x6c, 0x69, 0x6d, 0x69, 0x74, 0x00, 0x33, // 5.mp_timelimit.3
+    0x30, 0x00, 0x6e, 0x65, 0x78, 0x74, 0x6c, 0x65, 0x76, 0x65, 0x6c, 0x00, 0x00, 0x73, 0x76, 0x5f, // 0.nextlevel..sv_
+    0x61, 0x6c, 0x6c, 0x74, 0x61, 0x6c, 0x6b, 0x00, 0x30, 0x00, 0x73, 0x76, 0x5f, 0x63, 0x68, 0x65, // alltalk.0.sv_che
+    0x61, 0x74, 0x73, 0x00, 0x30, 0x00, 0x73, 0x76, 0x5f, 0x67, 0x72, 0x61, 0x76, 0x69, 0x74, 0x79, // ats.0.sv_gravity
+    0x00, 0x38, 0x30, 0x30, 0x00, 0x73, 0x76, 0x5f, 0x6d, 0x61, 0x78, 0x73, 0x70, 0x65, 0x65, 0x64, // .800.sv_maxspeed
+    0x00, 0x33, 0x32, 0x30, 0x00, 0x73, 0x76, 0x5f, 0x70, 0x61, 0x73, 0x73, 0x77, 0x6f, 0x72, 0x64, // .320.sv_password
+    0x00, 0x30, 0x00, 0x74, 0x76, 0x5f, 0x70, 0x61, 0x73, 0x73, 0x77, 0x6f, 0x72, 0x64, 0x00, 0x30, // .0.tv_password.0
+    0x00,                                                                                           // .
+];
+
+fn info(datagram: &[u8]) -> a2s::info::Info {
+    match parse_response(datagram, QueryKind::Info, 0).unwrap() {
+        ParsedResponse::Info(info) => *info,
+        parsed => panic!("expected info, got {:?}", parsed),
+    }
+}
+
+/// Reassembles `datagrams` and parses the response as rules.
+fn reassembled_rules(datagrams: &[&[u8]]) -> Vec<Rule> {
+    let mut assembler = FragmentAssembler::new();
+    let (last, rest) = datagrams.split_last().unwrap();
+    for datagram in rest {
+        assert_eq!(assembler.push(datagram).unwrap(), None);
+    }
+
+    let payload = assembler.push(last).unwrap().unwrap();
+    Vec::<Rule>::parse(&payload, &ParseContext::default()).unwrap()
+}
+
+fn assert_rules(rules: &[Rule]) {
+    let rule = |name: &str| {
+        rules
+            .iter()
+            .find(|rule| rule.name == name)
+            .map(|rule| rule.value.as_str())
+    };
+
+    assert_eq!(rules.len(), 16);
+    assert_eq!(rules[0].name, "coop");
+    assert_eq!(rules[15].name, "tv_password");
+    assert_eq!(rule("mp_roundtime"), Some("2.5"));
+    assert_eq!(rule("nextlevel"), Some(""));
+    assert_eq!(rule("sv_gravity"), Some("800"));
+}
+
+#[test]
+fn test_vector_source_info() {
+    let info = info(SOURCE_INFO);
+    assert_eq!(info.protocol, 2);
+    assert_eq!(info.name, "game2xs.com Counter-Strike Source #1");
+    assert_eq!(info.map, "de_dust");
+    assert_eq!(info.folder, "cstrike");
+    assert_eq!(info.game, "Counter-Strike: Source");
+    assert_eq!(info.app_id, 240);
+    assert_eq!((info.players, info.max_players, info.bots), (5, 16, 4));
+    assert_eq!(info.server_type, ServerType::Dedicated);
+    assert_eq!(info.server_os, ServerOS::Linux);
+    assert!(!info.visibility);
+    assert!(!info.vac);
+    assert_eq!(info.the_ship, None);
+    assert_eq!(info.version, "1.0.0.22");
+    assert_eq!(info.edf, 0);
+    assert_eq!(info.extended_server_info.port, None);
+    assert!(info.source_tvs.is_empty());
+    assert!(info.extra.is_empty());
+}
+
+#[test]
+fn test_vector_source_info_edf() {
+    let info = info(SOURCE_INFO_EDF);
+    assert_eq!(info.protocol, 17);
+    assert_eq!(info.name, "Valve CS:GO Server");
+    assert_eq!(info.app_id, 730);
+    assert_eq!((info.players, info.max_players, info.bots), (10, 20, 0));
+    assert_eq!(info.server_os, ServerOS::Windows);
+    assert!(info.vac);
+    assert_eq!(info.version, "1.38.7.9");
+    assert_eq!(info.edf, 0xf1);
+
+    let extended = &info.extended_server_info;
+    assert_eq!(extended.port, Some(27015));
+    assert_eq!(extended.steam_id, Some(85568392920039456));
+    assert_eq!(extended.keywords.as_deref(), Some("secure,valve_ds,empty"));
+    assert_eq!(extended.game_id, Some(730));
+
+    assert_eq!(info.source_tvs.len(), 1);
+    let source_tv = info.source_tv().unwrap();
+    assert_eq!(source_tv.port, 27020);
+    assert_eq!(source_tv.name, "GOTV");
+    assert!(info.extra.is_empty());
+}
+
+#[test]
+fn test_vector_gold_source_info() {
+    let info = info(GOLD_SOURCE_INFO);
+    assert_eq!(
+        info.gold_source_address.as_deref(),
+        Some("192.168.1.10:27015")
+    );
+    assert_eq!(info.name, "Half-Life DM");
+    assert_eq!(info.map, "crossfire");
+    assert_eq!(info.folder, "valve");
+    assert_eq!(info.game, "Half-Life");
+    assert_eq!((info.players, info.max_players, info.bots), (11, 32, 0));
+    assert_eq!(info.protocol, 47);
+    assert_eq!(info.server_type, ServerType::Dedicated);
+    assert_eq!(info.server_os, ServerOS::Windows);
+    assert!(!info.visibility);
+    assert!(info.vac);
+    assert_eq!(info.gold_source_mod, None);
+}
+
+#[test]
+fn test_vector_the_ship() {
+    let info = info(SHIP_INFO);
+    assert_eq!(info.app_id, 2400);
+    assert_eq!(info.protocol, 7);
+    assert_eq!(info.map, "batavier");
+    assert_eq!((info.players, info.max_players), (3, 16));
+    let the_ship = info.the_ship.unwrap();
+    assert_eq!(the_ship.mode, TheShipMode::Elimination);
+    assert_eq!(the_ship.witnesses, 3);
+    assert_eq!(the_ship.duration, 120);
+    assert_eq!(info.version, "1.0.0.4");
+
+    let players = match parse_response(SHIP_PLAYERS, QueryKind::Players, info.app_id).unwrap() {
+        ParsedResponse::Players(players) => players,
+        parsed => panic!("expected players, got {:?}", parsed),
+    };
+    let summary: Vec<_> = players
+        .iter()
+        .map(|player: &Player| {
+            let the_ship = player.the_ship.as_ref().unwrap();
+            (
+                player.index,
+                player.name.as_str(),
+                player.score,
+                player.duration,
+                the_ship.deaths,
+                the_ship.money,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (0, "Captain", 5, 301.5, 1, 1200),
+            (1, "Mate", 0, 42.25, 2, 500),
+            (2, "Stowaway", -1, 10.0, 0, 0),
+        ]
+    );
+}
+
+#[test]
+fn test_vector_source_rules() {
+    assert_rules(&reassembled_rules(&[SOURCE_RULES_0, SOURCE_RULES_1]));
+    // Fragments may arrive in any order
+    assert_rules(&reassembled_rules(&[SOURCE_RULES_1, SOURCE_RULES_0]));
+}
+
+#[test]
+fn test_vector_source_rules_compressed() {
+    assert_rules(&reassembled_rules(&[
+        SOURCE_RULES_BZ2_0,
+        SOURCE_RULES_BZ2_1,
+    ]));
+}
+
+#[test]
+fn test_vector_gold_source_rules() {
+    assert_rules(&reassembled_rules(&[
+        GOLD_SOURCE_RULES_0,
+        GOLD_SOURCE_RULES_1,
+    ]));
+}